@@ -0,0 +1,51 @@
+//! Lexes the same mix of code at doubling sizes, so the timings show how lexing scales with
+//! the length of the source. Each size should take about twice as long as the one before.
+//!
+//! Run with `cargo bench --bench lexer`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use tinyjs::lexer::Lexer;
+
+/// A bit of everything the lexer handles, including non-ASCII text.
+const CHUNK: &str = r#"
+// Totals up an order, in € and ¥.
+function total(items, rate) {
+    let sum = 0;
+    for (let i = 0; i < items.length; i++) {
+        sum += items[i].price * 1_000 / 0x3e8 + .5e-1;
+    }
+    let label = `total: ${sum * rate} ✓`;
+    return { label: label, big: 12345678901234567890n, note: 'naïve "café"\n' };
+}
+"#;
+
+const RUNS: usize = 3;
+
+/// The best of a few runs, which is the least disturbed by whatever else the machine is doing.
+fn time(source: &str) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let tokens = black_box(Lexer::new(source).lex());
+        best = best.min(start.elapsed());
+        count = tokens.len();
+    }
+
+    (best, count)
+}
+
+fn main() {
+    let mut baseline = None;
+    for megabytes in [1, 2, 4, 8, 16] {
+        let source = CHUNK.repeat(megabytes * 1024 * 1024 / CHUNK.len());
+        let (elapsed, tokens) = time(&source);
+
+        let per_megabyte = elapsed / megabytes as u32;
+        let relative = per_megabyte.as_secs_f64() / *baseline.get_or_insert(per_megabyte.as_secs_f64());
+        println!(
+            "{megabytes:>2} MB: {tokens:>9} tokens in {elapsed:>10.2?} ({per_megabyte:.2?}/MB, {relative:.2}x the 1 MB rate)"
+        );
+    }
+}
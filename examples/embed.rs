@@ -0,0 +1,53 @@
+//! Scripting a pricing rule from Rust: load a script, feed it data through globals and
+//! arguments, and read the results back.
+//!
+//! Run with `cargo run --example embed`.
+
+use tinyjs::{Error, Literal, Runtime};
+
+const RULES: &str = r#"
+function price(items) {
+    let total = 0;
+    for (let i = 0; i < items.length; i++) {
+        total += items[i];
+    }
+    if (total > threshold) {
+        total = total * (1 - discount);
+    }
+    return total;
+}
+
+function check(total) {
+    if (total < 0) {
+        throw RangeError("negative total: " + total);
+    }
+    return true;
+}
+"#;
+
+fn main() -> Result<(), Error> {
+    let mut js = Runtime::new();
+    js.eval(RULES).inspect_err(|err| eprintln!("{}", err.render(RULES)))?;
+
+    js.set("threshold", 100);
+    js.set("discount", 0.25);
+
+    let total: f64 = js.call("price", vec![vec![40.0, 50.0, 30.0].into()])?.try_into()?;
+    println!("discounted total: {total}");
+
+    js.set("discount", 0.5);
+    let total: f64 = js.call("price", vec![vec![80, 40].into()])?.try_into()?;
+    println!("with a bigger discount: {total}");
+
+    let ok: bool = js.call("check", vec![total.into()])?.try_into()?;
+    println!("check passed: {ok}");
+
+    if let Err(err) = js.call("check", vec![Literal::from(-1)]) {
+        println!("check failed: {err}");
+    }
+
+    let names: Vec<String> = js.eval(r#""a,b,c".split(",");"#)?.try_into()?;
+    println!("split from JS: {names:?}");
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Exposing Rust to scripts: native functions with typed arguments that throw JS errors, a
+//! namespace of them, and an object whose properties live on the Rust side.
+//!
+//! Run with `cargo run --example host`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tinyjs::runtime::{error, Args, Context, HostObject};
+use tinyjs::{Error, Literal, Runtime};
+
+/// Feature flags shared with the host, which can flip them between calls.
+struct Flags(Rc<RefCell<HashMap<String, bool>>>);
+
+impl HostObject for Flags {
+    fn get(&self, name: &str) -> Option<Literal> {
+        self.0.borrow().get(name).map(|&on| on.into())
+    }
+
+    fn set(&self, name: &str, value: Literal) -> Result<bool, Literal> {
+        match value {
+            Literal::Boolean(on) => {
+                self.0.borrow_mut().insert(name.into(), on);
+                Ok(true)
+            },
+            other => Err(error("TypeError", format!("flag {name} must be a boolean, not {}", other.type_name())))
+        }
+    }
+}
+
+fn clamp(_: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+    let (value, low, high): (f64, f64, f64) = (args.arg(0)?, args.arg(1)?, args.arg(2)?);
+    if low > high {
+        return Err(error("RangeError", format!("empty range {low}..{high}")));
+    }
+
+    Ok(value.clamp(low, high).into())
+}
+
+const SCRIPT: &str = r#"
+function quote(total) {
+    if (flags.discounts) {
+        total = total * 0.9;
+    }
+    return util.clamp(total, 10, 500) + " " + currency();
+}
+"#;
+
+fn main() -> Result<(), Error> {
+    let mut js = Runtime::new();
+
+    let flags = Rc::new(RefCell::new(HashMap::from([("discounts".to_string(), true)])));
+    js.register_object("flags", Flags(Rc::clone(&flags)));
+
+    js.register_namespace("util", vec![
+        ("clamp", Runtime::function("util.clamp", clamp)),
+        ("greet", Runtime::function("util.greet", |_, args| {
+            let name: Option<String> = args.optional(0)?;
+            Ok(format!("hello, {}", name.as_deref().unwrap_or("stranger")).into())
+        })),
+        // Calls back into the script: `util.times(3, f)` is `[f(0), f(1), f(2)]`.
+        ("times", Runtime::function("util.times", |cx, args| {
+            let count: f64 = args.arg(0)?;
+            let results = (0..count as usize)
+                .map(|i| cx.call(&args.get(1), Literal::Undefined, vec![i.into()]))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(results.into())
+        })),
+    ]);
+
+    // Natives can read the script's globals through the context they're called with.
+    js.register("currency", |cx, _| Ok(cx.global("CURRENCY").unwrap_or_else(|| "USD".into())));
+
+    js.eval(SCRIPT).inspect_err(|err| eprintln!("{}", err.render(SCRIPT)))?;
+
+    println!("{}", String::try_from(js.call("quote", vec![1000.into()])?)?);
+
+    flags.borrow_mut().insert("discounts".into(), false);
+    js.set("CURRENCY", "EUR");
+    println!("{}", String::try_from(js.call("quote", vec![50.into()])?)?);
+
+    println!("{}", String::try_from(js.eval(r#"util.greet("Ada");"#)?)?);
+    println!("{}", String::try_from(js.eval("util.greet();")?)?);
+
+    let squares: Vec<f64> = js.eval("util.times(4, function(i) { return i * i; });")?.try_into()?;
+    println!("squares: {squares:?}");
+
+    js.eval("flags.beta = true;")?;
+    println!("beta set from JS: {:?}", flags.borrow().get("beta"));
+
+    for source in [r#"util.clamp("ten", 0, 1);"#, "util.clamp(5, 10, 1);", "flags.beta = 1;"] {
+        if let Err(err) = js.eval(source) {
+            println!("{source} -> {err}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+// Array methods, including the ones that call back into script functions, and `length`.
+// Exits with an uncaught error if any check fails.
+
+function same(a, b) {
+    if (a.length != b.length) {
+        return false;
+    }
+    for (let i = 0; i < a.length; i++) {
+        if (a[i] != b[i]) {
+            return false;
+        }
+    }
+    return true;
+}
+
+let nums = [3, 1, 4, 1, 5, 9, 2, 6];
+
+// map, filter and forEach pass (element, index, array).
+assert(same(nums.map(function(n) { return n * 2; }), [6, 2, 8, 2, 10, 18, 4, 12]), "map");
+assert(same(nums.filter(function(n) { return n % 2 == 0; }), [4, 2, 6]), "filter");
+assert(same(nums.map(function(n, i) { return i; }), [0, 1, 2, 3, 4, 5, 6, 7]), "callbacks get the index");
+
+let seen = [];
+let result = nums.forEach(function(n, i, arr) {
+    seen.push(arr == nums);
+});
+assert(result == undefined && seen.length == 8 && seen[0] && seen[7], "forEach passes the array itself");
+
+// Closures over outer state, and an explicit `this`.
+let total = 0;
+nums.forEach(function(n) {
+    total += n;
+});
+assert(total == 31, "callbacks are closures");
+
+let scaler = { factor: 10 };
+assert(same([1, 2].map(function(n) { return n * this.factor; }, scaler), [10, 20]), "the second argument is this");
+
+// Natives work as callbacks too.
+assert(same(["1", "2", "3"].map(Number), [1, 2, 3]), "native callbacks");
+assert(same([[1], [2, 3]].map(function(a) { return a.length; }), [1, 2]), "nested arrays");
+
+// Searching.
+assert(nums.find(function(n) { return n > 4; }) == 5, "find returns the first match");
+assert(nums.find(function(n) { return n > 100; }) == undefined, "find without a match");
+assert(nums.findIndex(function(n) { return n == 9; }) == 5, "findIndex");
+assert(nums.findIndex(function(n) { return n == 7; }) == -1, "findIndex without a match");
+assert(nums.some(function(n) { return n > 8; }), "some");
+assert(!nums.some(function(n) { return n > 9; }), "some without a match");
+assert(nums.every(function(n) { return n > 0; }), "every");
+assert(!nums.every(function(n) { return n > 1; }), "every with a failure");
+assert([].every(function(n) { return false; }) && ![].some(function(n) { return true; }), "empty arrays");
+
+let calls = 0;
+nums.some(function(n) {
+    calls++;
+    return n == 4;
+});
+assert(calls == 3, "some stops at the first match");
+
+// Folding.
+assert(nums.reduce(function(sum, n) { return sum + n; }, 0) == 31, "reduce with an initial value");
+assert(nums.reduce(function(max, n) { return Math.max(max, n); }) == 9, "reduce from the first element");
+assert(["a", "b", "c"].reduceRight(function(acc, s) { return acc + s; }) == "cba", "reduceRight");
+assert([].reduce(function(a, b) { return a + b; }, "init") == "init", "reduce of an empty array with an initial value");
+
+let caught = null;
+try {
+    [].reduce(function(a, b) { return a + b; });
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "TypeError", "reduce of an empty array with no initial value throws");
+
+// Sorting.
+let sorted = [3, 1, 10, 2].sort(function(a, b) { return a - b; });
+assert(same(sorted, [1, 2, 3, 10]), "sort with a comparator");
+assert(same([3, 1, 10, 2].sort(), [1, 10, 2, 3]), "sort compares strings by default");
+assert(same(["pear", "apple", "fig"].sort(), ["apple", "fig", "pear"]), "sort strings");
+assert(["\uFFFF", "\u{1F600}"].sort()[0] == "\u{1F600}", "sort compares UTF-16 code units");
+let byName = [{ toString: function() { return "b"; } }, { toString: function() { return "a"; } }];
+assert(byName.sort()[0] == "a", "sort converts elements with toString");
+
+let people = [
+    { name: "b", age: 30 },
+    { name: "a", age: 25 },
+    { name: "c", age: 30 },
+    { name: "d", age: 25 }
+];
+people.sort(function(x, y) { return x.age - y.age; });
+assert(same(people.map(function(p) { return p.name; }), ["a", "d", "b", "c"]), "sort is stable");
+
+let inPlace = [2, 1];
+assert(inPlace.sort() == inPlace && inPlace[0] == 1, "sort works in place and returns the array");
+
+// Exceptions thrown by callbacks propagate and can be caught.
+caught = null;
+try {
+    [1, 2, 3].map(function(n) {
+        if (n == 2) {
+            throw RangeError("two");
+        }
+        return n;
+    });
+} catch (e) {
+    caught = e;
+}
+assert(caught.message == "two", "errors from callbacks reach the caller");
+
+caught = null;
+let unsorted = [3, 2, 1];
+try {
+    unsorted.sort(function(a, b) {
+        throw Error("no comparing");
+    });
+} catch (e) {
+    caught = e;
+}
+assert(caught.message == "no comparing" && unsorted.length == 3, "a throwing comparator keeps every element");
+
+caught = null;
+try {
+    [1].map(5);
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "TypeError", "non-function callbacks throw");
+
+// Methods chain.
+let evens = [1, 2, 3, 4, 5, 6]
+    .filter(function(n) { return n % 2 == 0; })
+    .map(function(n) { return n * n; })
+    .reduce(function(a, b) { return a + b; });
+assert(evens == 56, "chained higher-order calls");
+
+// length is a property, and assigning to it truncates or pads the array.
+let list = [1, 2, 3];
+assert(list.length == 3 && [].length == 0, "length");
+list.length = 1;
+assert(list.length == 1 && list[0] == 1, "shrinking length drops elements");
+list.length = 3;
+assert(list.length == 3 && list[2] == undefined, "growing length pads with undefined");
+list[5] = 6;
+assert(list.length == 6, "writing past the end grows the array");
+
+caught = null;
+try {
+    list.length = -1;
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "RangeError", "invalid lengths throw");
+
+// push, pop, shift, unshift
+let stack = [];
+assert(stack.push(1, 2, 3) == 3 && same(stack, [1, 2, 3]), "push takes any number of elements");
+assert(stack.pop() == 3 && stack.length == 2, "pop");
+assert([].pop() == undefined && [].shift() == undefined, "pop and shift on empty arrays");
+assert(stack.unshift(-1, 0) == 4 && same(stack, [-1, 0, 1, 2]), "unshift");
+assert(stack.shift() == -1 && same(stack, [0, 1, 2]), "shift");
+
+// slice, splice, concat
+let letters = ["a", "b", "c", "d", "e"];
+assert(same(letters.slice(1, 3), ["b", "c"]) && same(letters.slice(-2), ["d", "e"]), "slice");
+assert(same(letters.slice(), letters) && letters.slice() != letters, "slice copies");
+
+let spliced = ["a", "b", "c", "d", "e"];
+assert(same(spliced.splice(1, 2), ["b", "c"]) && same(spliced, ["a", "d", "e"]), "splice removes");
+assert(same(spliced.splice(1, 0, "x", "y"), []) && same(spliced, ["a", "x", "y", "d", "e"]), "splice inserts");
+assert(same(spliced.splice(-1), ["e"]) && same(spliced, ["a", "x", "y", "d"]), "splice from the end");
+assert(same(spliced.splice(1, 2, "b"), ["x", "y"]) && same(spliced, ["a", "b", "d"]), "splice replaces");
+
+assert(same([1].concat([2, 3], 4, [[5]]).slice(0, 4), [1, 2, 3, 4]), "concat spreads one level");
+assert([1].concat([[5]])[1].length == 1, "concat keeps nested arrays");
+
+// indexOf, lastIndexOf, includes
+let repeated = [1, 2, 3, 2, 1];
+assert(repeated.indexOf(2) == 1 && repeated.indexOf(2, 2) == 3 && repeated.indexOf(9) == -1, "indexOf");
+assert(repeated.indexOf(1, -1) == 4, "indexOf from a negative position");
+assert(repeated.lastIndexOf(2) == 3 && repeated.lastIndexOf(2, 2) == 1 && repeated.lastIndexOf(9) == -1, "lastIndexOf");
+assert(repeated.includes(3) && !repeated.includes(3, 3), "includes");
+let nan = 0 / 0;
+assert([nan].includes(nan) && [nan].indexOf(nan) == -1, "includes finds NaN but indexOf doesn't");
+assert(["1"].indexOf(1) == -1, "indexOf compares strictly");
+
+// fill, flat
+assert(same([1, 2, 3, 4].fill(0, 1, 3), [1, 0, 0, 4]) && same(new Array(3).fill(7), [7, 7, 7]), "fill");
+let flattened = [1, [2, [3, [4]]]].flat();
+assert(flattened.length == 3 && flattened[1] == 2 && Array.isArray(flattened[2]), "flat defaults to one level");
+assert(same([1, [2, [3, [4]]]].flat(2).slice(0, 3), [1, 2, 3]), "flat with a depth");
+assert([1, [2, [3, [4]]]].flat(99).length == 4, "flat all the way down");
+
+// join stringifies every element.
+assert([1, "a", true, null, undefined].join("-") == "1-a-true--", "join stringifies elements");
+assert([1, [2, 3]].join(";") == "1;2,3" && [].join() == "", "join nests with commas");
+let cyclic = [1];
+cyclic.push(cyclic);
+assert(cyclic.join() == "1,", "join skips cycles");
+let labelled = { toString: function() { return "L"; } };
+assert([{}].join() == "[object Object]" && [labelled, 1].join(labelled) == "LL1", "join calls toString on elements and the separator");
+
+// reverse
+let forwards = [1, 2, 3];
+assert(forwards.reverse() == forwards && same(forwards, [3, 2, 1]), "reverse works in place");
+
+// Array.isArray, Array.of, Array.from and the constructor
+assert(Array.isArray([]) && !Array.isArray({}) && !Array.isArray("a"), "Array.isArray");
+assert(same(Array.of(7), [7]) && Array.of().length == 0, "Array.of");
+assert(same(Array.from([1, 2]), [1, 2]) && same(Array.from("abc"), ["a", "b", "c"]), "Array.from");
+assert(same(Array.from({ length: 2, "0": "x" }), ["x", undefined]), "Array.from an array-like object");
+assert(same(Array.from([1, 2], function(n, i) { return n * 10 + i; }), [10, 21]), "Array.from with a map function");
+assert(new Array(3).length == 3 && same(new Array(1, 2), [1, 2]) && same(Array("3"), ["3"]), "the Array constructor");
+
+caught = null;
+try {
+    new Array(1.5);
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "RangeError", "the Array constructor rejects invalid lengths");
+
+console.log("arrays: ok");
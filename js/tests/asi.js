@@ -0,0 +1,110 @@
+// Automatic semicolon insertion: statements ended by line breaks, `}` and the end of input,
+// the restricted productions, and the classic hazards where no semicolon is inserted.
+// Exits with an uncaught error if any check fails.
+
+// Line breaks end statements.
+let a = 1
+let b = 2
+let c
+assert(a + b == 3, "let statements")
+assert(c == undefined, "a declaration without a value")
+a = a + 1
+a += 1
+assert(a == 3, "expression statements")
+
+// So do `}` and the end of a line with a comment on it.
+function add(x, y) { return x + y }
+assert(add(1, 2) == 3, "before a closing brace")
+let d = 4 // a comment
+let e = 5 /* a comment
+that spans lines */ let f = 6
+assert(d + e + f == 15, "comments before and across line breaks")
+
+// Semicolons are still fine, including mixed with omitted ones.
+let g = 7; let h = 8
+assert(g + h == 15, "mixed semicolons")
+
+// Expressions carry on over line breaks when the next token can continue them.
+let sum = 1 +
+    2
+    + 3
+assert(sum == 6, "binary operators at either end of a line")
+let chained = [3, 1, 2]
+    .map(function(n) { return n * 2 })
+    .join(",")
+assert(chained == "6,2,4", "method chains starting lines with a dot")
+let assigned
+    = 9
+assert(assigned == 9, "an initializer on the next line")
+
+// Restricted productions: `return` followed by a line break returns nothing.
+function early() {
+    return
+        42
+}
+assert(early() == undefined, "return followed by a line break")
+
+function wrapped() {
+    return (
+        42
+    )
+}
+assert(wrapped() == 42, "return with a parenthesized value on the next lines")
+
+// `break` and `continue` end at a line break too.
+let seen = 0
+for (let i = 0; i < 10; i++) {
+    if (i == 2) {
+        continue
+    }
+    if (i == 5) {
+        break
+    }
+    seen += 1
+}
+assert(seen == 4, "break and continue")
+
+// A postfix `++` or `--` has to be on the operand's line; otherwise it's a prefix one.
+let p = 1
+let q = 1
+p
+++q
+assert(p == 1 && q == 2, "++ after a line break applies to the next line")
+p
+--q
+assert(p == 1 && q == 1, "-- after a line break applies to the next line")
+p++
+assert(p == 2, "postfix ++ on the same line")
+
+// Hazards: a line starting with `(`, `[` or a template continues the previous one.
+function called() {
+    let x = 1
+    let y = x
+    (function() {})()
+}
+assert(throws(called, "TypeError"), "a line starting with ( calls the previous line")
+
+let list = [10, 20, 30]
+let fromIndex = list
+[1]
+assert(fromIndex == 20, "a line starting with [ indexes the previous line")
+
+function tag(strings) {
+    return "tagged " + strings[0]
+}
+let tagged = tag
+`text`
+assert(tagged == "tagged text", "a line starting with a template tags the previous line")
+
+// Blocks and class bodies
+if (a == 3) { a = 4 }
+assert(a == 4, "a one-line block")
+class Counter {
+    static start = 10
+    static step = 2
+
+    next(n) { return n + Counter.step }
+}
+assert(Counter.start == 10 && new Counter().next(1) == 3, "class fields")
+
+console.log("asi: ok")
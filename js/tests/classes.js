@@ -0,0 +1,150 @@
+// Class declarations and expressions: constructors, methods, accessors, static members,
+// inheritance and super. Exits with an uncaught error if any check fails.
+
+class Point {
+    constructor(x, y) {
+        this.x = x;
+        this.y = y;
+    }
+
+    norm() {
+        return Math.sqrt(this.x * this.x + this.y * this.y);
+    }
+
+    get sum() {
+        return this.x + this.y;
+    }
+
+    set both(value) {
+        this.x = value;
+        this.y = value;
+    }
+
+    static origin() {
+        return new Point(0, 0);
+    }
+
+    static dimensions = 2;
+}
+
+assert(new Point(3, 4).norm() == 5, "methods run against the instance");
+let p = new Point(1, 2);
+assert(p.sum == 3, "getters are read like properties");
+p.both = 7;
+assert(p.x == 7 && p.y == 7, "setters run on assignment");
+assert(!p.hasOwnProperty("sum") && !p.hasOwnProperty("norm"), "members live on the prototype");
+assert(p.__proto__ == Point.prototype, "instances inherit from the class prototype");
+assert(Point.origin().norm() == 0, "static methods live on the class");
+assert(Point.dimensions == 2, "static fields");
+
+// Inheritance.
+class Point3 extends Point {
+    constructor(x, y, z) {
+        super(x, y);
+        this.z = z;
+    }
+
+    norm() {
+        let flat = super.norm();
+        return Math.sqrt(flat * flat + this.z * this.z);
+    }
+
+    get sum() {
+        return super.sum + this.z;
+    }
+
+    static origin() {
+        return new Point3(0, 0, 0);
+    }
+
+    static describe() {
+        return "a point in " + this.dimensions + " or " + super.dimensions + " dimensions";
+    }
+
+    static dimensions = 3;
+}
+
+let q = new Point3(2, 3, 6);
+assert(q.norm() == 7, "super.method() runs the parent's method with the same this");
+assert(q.sum == 11, "super.getter reads the parent's accessor");
+assert(Point3.origin().z == 0, "static methods can be overridden");
+assert(Point3.describe() == "a point in 3 or 2 dimensions", "super in static methods reaches the parent class");
+assert(q.__proto__.__proto__ == Point.prototype, "prototypes chain to the parent");
+
+// An implicit constructor passes every argument to the parent.
+class Named extends Point {
+    label() {
+        return "(" + this.x + ", " + this.y + ")";
+    }
+}
+assert(new Named(1, 2).label() == "(1, 2)", "implicit constructors forward their arguments");
+
+// Statics are inherited too.
+class Empty extends Point {}
+assert(Empty.origin().norm() == 0 && Empty.dimensions == 2, "static members are inherited");
+
+// Class expressions, and the class name inside its own body.
+let Counter = class {
+    constructor() {
+        this.count = 0;
+    }
+
+    bump() {
+        this.count++;
+        return this;
+    }
+};
+assert(new Counter().bump().bump().count == 2, "anonymous class expressions");
+
+let Factorial = class Fact {
+    static of(n) {
+        if (n <= 1) {
+            return 1;
+        }
+        return n * Fact.of(n - 1);
+    }
+};
+assert(Factorial.of(5) == 120, "a named class expression can refer to itself");
+
+// Extending a builtin.
+class ValidationError extends Error {
+    constructor(field) {
+        super(field + " is invalid");
+        this.field = field;
+    }
+}
+let caught = null;
+try {
+    throw new ValidationError("email");
+} catch (e) {
+    caught = e;
+}
+assert(caught.message == "email is invalid" && caught.field == "email", "classes can extend Error");
+
+caught = null;
+try {
+    class Broken extends 5 {}
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "TypeError", "extending a non-constructor throws");
+
+// Class constructors only run through new.
+let message = null;
+try {
+    Point(1, 2);
+} catch (e) {
+    message = e.name + ": " + e.message;
+}
+assert(message == "TypeError: Class constructor Point cannot be invoked without 'new'", "calling a class without new throws");
+assert(throws(function() { return [1].map(Point); }, "TypeError"), "even when a builtin calls it");
+assert(throws(function() { return ValidationError("name"); }, "TypeError"), "derived classes too");
+assert(new ValidationError("name").field == "name", "while new and super still run them");
+
+// Methods see updates to the prototype.
+Point.prototype.scale = function(k) {
+    return new Point(this.x * k, this.y * k);
+};
+assert(q.scale(2).x == 4, "prototype methods added later are inherited by subclasses");
+
+console.log("classes: ok");
@@ -0,0 +1,121 @@
+// Functions capture the environment they are defined in, not the one they are called from.
+// Exits with an uncaught error if any check fails.
+
+// Counters keep private state across calls.
+function makeCounter() {
+    let count = 0;
+    return function() {
+        count++;
+        return count;
+    };
+}
+
+let counter = makeCounter();
+let other = makeCounter();
+counter();
+counter();
+assert(counter() == 3, "counter keeps its own state");
+assert(other() == 1, "each call creates a fresh environment");
+
+// Factories close over their arguments.
+function adder(n) {
+    return function(x) { return x + n; };
+}
+
+let add2 = adder(2);
+let add10 = adder(10);
+assert(add2(1) == 3, "adder(2)");
+assert(add10(1) == 11, "adder(10)");
+
+// Module pattern: closures share the bindings of their enclosing call.
+let account = (function() {
+    let balance = 0;
+    return {
+        deposit: function(amount) { balance += amount; return balance; },
+        balance: function() { return balance; }
+    };
+})();
+account.deposit(5);
+account.deposit(7);
+assert(account.balance() == 12, "module functions share state");
+
+// Lexical, not dynamic, scoping.
+let scope = "global";
+function readScope() {
+    return scope;
+}
+function callWithLocal() {
+    let scope = "local";
+    return readScope();
+}
+assert(callWithLocal() == "global", "callee sees its definition scope, not the caller's");
+
+// Shadowing.
+let shadowed = 1;
+function shadow() {
+    let shadowed = 2;
+    {
+        let shadowed = 3;
+        assert(shadowed == 3, "innermost binding wins");
+    }
+    return shadowed;
+}
+assert(shadow() == 2, "block binding does not leak");
+assert(shadowed == 1, "function binding does not leak");
+
+function param(shadowed) {
+    shadowed = shadowed + 1;
+    return shadowed;
+}
+assert(param(10) == 11 && shadowed == 1, "parameters shadow outer variables");
+
+// Closures see later assignments to captured variables.
+let late = 1;
+function readLate() {
+    return late;
+}
+late = 2;
+assert(readLate() == 2, "captured by reference, not by value");
+
+// Inner declarations stay local to their function.
+function outer() {
+    function helper() { return "inner"; }
+    return helper();
+}
+function helper() { return "outer"; }
+assert(outer() == "inner" && helper() == "outer", "function declarations are block scoped");
+
+// Recursion through a named function expression.
+let fact = function f(n) {
+    if (n <= 1) {
+        return 1;
+    }
+    return n * f(n - 1);
+};
+assert(fact(5) == 120, "named function expressions can recurse");
+
+// Escaping closures created in a loop each get their own binding.
+let fns = [];
+for (let i = 0; i < 3; i++) {
+    fns.push(function() { return i; });
+}
+assert(fns[0]() == 0 && fns[1]() == 1 && fns[2]() == 2, "per-iteration loop bindings");
+
+// Closures nest.
+function outerCounter() {
+    let total = 0;
+    return function(step) {
+        return function() {
+            total += step;
+            return total;
+        };
+    };
+}
+let makeStep = outerCounter();
+let by1 = makeStep(1);
+let by5 = makeStep(5);
+by1();
+by5();
+assert(by1() == 7, "nested closures share the outermost binding");
+
+console.log("closures: ok");
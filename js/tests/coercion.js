@@ -0,0 +1,113 @@
+// Equality and type coercion: === and !==, the loose == rules, arithmetic and comparisons on
+// mixed types, and objects converting through valueOf and toString.
+// Exits with an uncaught error if any check fails.
+
+let nan = 0 / 0;
+
+function isNaN(x) {
+    return x !== x;
+}
+
+// Strict equality
+assert(1 === 1 && "a" === "a" && null === null && undefined === undefined, "same values");
+assert(!(1 === "1") && !(0 === false) && !(null === undefined), "different types are never equal");
+assert(1 !== "1" && !(1 !== 1), "!==");
+assert(nan !== nan && !(nan === nan), "NaN isn't equal to itself");
+assert(0 === -0, "the zeros are equal");
+let obj = {};
+let arr = [];
+function f() {}
+function g() {}
+assert(obj === obj && !({} === {}) && arr === arr && !([] === []), "objects and arrays by identity");
+assert(f === f && f !== g, "functions by identity");
+
+// Loose equality
+assert(null == undefined && undefined == null, "null and undefined equal each other");
+assert(!(null == 0) && !(undefined == 0) && !(null == false) && !(null == ""), "and nothing else");
+assert("1" == 1 && 1 == "1" && "" == 0 && " 12 " == 12 && "0x10" == 16, "strings convert to numbers");
+assert(!("abc" == 0) && "abc" != nan, "unparsable strings are NaN");
+assert(true == 1 && false == 0 && true == "1" && false == "", "booleans convert to numbers");
+assert(!(true == 2) && !(true == "true"), "true is only 1");
+assert([1] == 1 && [1, 2] == "1,2" && [] == "" && [] == 0, "arrays convert through join");
+assert({} == "[object Object]", "objects convert through toString");
+assert(!(nan == nan) && nan != nan, "NaN isn't loosely equal to itself either");
+assert(!({} == {}) && !([] == []), "two objects are only equal if they're the same one");
+
+// BigInts
+assert(1n == 1 && 1 == 1n && !(1n == 1.5) && 0n == 0, "BigInts and numbers compare by value");
+assert(1n == "1" && "0x10" == 16n && !(1n == "1.0") && !(1n == "x"), "BigInts and strings");
+assert(1n == true && 0n == false, "BigInts and booleans");
+assert(!(1n === 1) && 2n === 2n, "BigInts are only strictly equal to BigInts");
+assert(9007199254740993n != 9007199254740992, "comparisons with numbers are exact");
+
+// Arithmetic
+assert("5" * 2 === 10 && "10" / "4" === 2.5 && "7" % 2 === 1 && "3" - 1 === 2, "numeric strings");
+assert(true + 1 === 2 && false * 5 === 0, "booleans");
+assert(null + 1 === 1 && isNaN(undefined + 1), "null is 0 and undefined is NaN");
+assert(isNaN("one" - 1) && isNaN({} * 2), "anything else is NaN");
+assert("" - 1 === -1 && " " * 3 === 0, "blank strings are 0");
+assert(throws(function() { return 1n * 2; }, "TypeError"), "BigInts don't mix with numbers");
+
+// + concatenates when either side is a string
+assert("5" + 2 === "52" && 2 + "5" === "25" && 1 + 2 + "3" === "33", "strings win");
+assert(true + "" === "true" && null + "" === "null" && undefined + "x" === "undefinedx", "other primitives");
+assert([1, 2] + [3] === "1,23" && [] + [] === "" && 1 + [2] === "12", "arrays join first");
+assert({} + "" === "[object Object]" && [null, undefined] + "" === ",", "objects and holes");
+assert(1n + "" === "1" && 1.5e21 + "" === "1.5e+21", "numbers format like String(n)");
+
+// Unary + and -
+assert(+"42" === 42 && +"" === 0 && +"0b101" === 5 && +" 1e3 " === 1000, "+ converts to a number");
+assert(+"\ufeff 12\u2028" === 12 && isNaN(+"\u0085 12"), "number conversion skips the same whitespace as trim");
+assert(isNaN(+"abc") && isNaN(+"1px") && isNaN(+"inf") && +"Infinity" === 1 / 0, "only JS number syntax");
+assert(+true === 1 && +null === 0 && isNaN(+undefined) && +[] === 0 && +[7] === 7, "other values");
+assert(-"5" === -5 && -true === -1 && -[3] === -3, "- converts too");
+assert(throws(function() { return +1n; }, "TypeError"), "+ can't convert a BigInt");
+
+// ++ and --
+let counter = "5";
+counter++;
+assert(counter === 6, "++ converts to a number");
+let flag = true;
+flag--;
+assert(flag === 0, "-- on a boolean");
+
+// Relational comparisons
+assert("10" < "9" && "a" < "b" && "B" < "a" && "abc" < "abd" && "ab" < "abc", "strings compare by code units");
+assert(!("10" < 9) && "10" > 9 && 2 < "10", "a string and a number compare as numbers");
+assert(null >= 0 && null <= 0 && !(null > 0), "null is 0");
+assert(!(undefined < 1) && !(undefined >= 1) && !(nan < 1) && !(nan >= nan), "NaN is unordered");
+assert(1n < 2 && 2 > 1n && 1n < 1.5 && !(2n < 1.5) && 2n >= 2, "BigInts and numbers");
+assert(1n < "2" && !(1n < "x") && !(1n >= "x"), "BigInts and strings");
+assert([2] > 1 && [10] < [9], "arrays convert to strings first");
+assert(true > false && true >= 1, "booleans");
+
+// valueOf and toString
+let money = {
+    cents: 250,
+    valueOf: function() { return this.cents; },
+    toString: function() { return "$2.50"; }
+};
+assert(money * 2 === 500 && money + 1 === 251 && money > 100, "arithmetic prefers valueOf");
+assert(money == 250, "so does ==");
+assert(`${money}` === "$2.50", "templates prefer toString");
+
+let named = { toString: function() { return "named"; } };
+assert(named + "!" === "named!" && named == "named", "toString when valueOf returns the object");
+
+class Temperature {
+    constructor(degrees) {
+        this.degrees = degrees;
+    }
+
+    valueOf() {
+        return this.degrees;
+    }
+}
+assert(new Temperature(30) > new Temperature(20), "valueOf from a class");
+
+let broken = { valueOf: function() { return {}; }, toString: function() { return {}; } };
+assert(throws(function() { return broken + 1; }, "TypeError"), "no primitive from either method");
+let throwing = { valueOf: function() { throw RangeError("nope"); } };
+assert(throws(function() { return throwing * 1; }, "RangeError"), "errors from valueOf propagate");
+
+console.log("coercion: ok");
@@ -0,0 +1,45 @@
+#!/usr/bin/env tinyjs
+// Comments and whitespace: block comments, the `#!` line above, identifiers with `$` and
+// non-ASCII letters, and every kind of whitespace and line break.
+// Exits with an uncaught error if any check fails.
+
+// Block comments
+/* A comment on its own. */
+let a = /* between tokens */ 1 /**/ + /* across
+several
+lines */ 2;
+assert(a == 3, "block comments between tokens");
+/*** Extra stars ***/
+let b = 8 /* / * */ / 2 /*/ still a comment */;
+assert(b == 4, "slashes and stars inside comments");
+/* // a line comment inside a block comment */ let c = 5;
+assert(c == 5, "line comments don't hide the end of block comments");
+// /* a block comment inside a line comment
+let d = 6;
+assert(d == 6, "block comments don't start in line comments");
+assert("/* not a comment */".length == 19 && "// nor this".length == 11, "comments don't start in strings");
+
+// Whitespace
+let	e=1 +﻿2 +　3;
+assert(e == 6, "tabs, form feeds, the byte order mark and Unicode spaces");
+
+// Line terminators
+let f = 1;let g = 2;
+let h = 3; let i = 4; 
+assert(f + g + h + i == 10, "carriage returns, CRLF and the Unicode line and paragraph separators");
+// A line comment ends at a carriage return,let j = 7;
+assert(j == 7, "line comments end at any line terminator");
+// or at a line separator. let k = 8;
+assert(k == 8, "line comments end at U+2028");
+assert(`x
+y` == "x" + String.fromCharCode(10) + "y", "CRLF in a template is a line feed");
+
+// Identifiers
+let $ = 1;
+let _$x = 2;
+let $y$ = 3;
+let café = 4;
+let π = 5;
+assert($ + _$x + $y$ + café + π == 15, "dollar signs and non-ASCII letters in identifiers");
+
+console.log("comments: ok");
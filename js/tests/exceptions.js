@@ -0,0 +1,181 @@
+// try/catch/finally, throw, and runtime faults surfacing as catchable errors.
+// Exits with an uncaught error if any check fails.
+
+// Thrown values are bound to the catch parameter.
+let caught = null;
+try {
+    throw 42;
+} catch (e) {
+    caught = e;
+}
+assert(caught == 42, "catch binds the thrown value");
+
+// Runtime faults become typed error objects.
+let name = "";
+try {
+    let bad = 1n - 1;
+} catch (e) {
+    name = e.name;
+}
+assert(name == "TypeError", "mixing BigInts and numbers throws TypeError");
+
+try {
+    missing;
+} catch (e) {
+    name = e.name;
+}
+assert(name == "ReferenceError", "unknown identifiers throw ReferenceError");
+
+let arr = [1, 2];
+try {
+    arr[5];
+} catch (e) {
+    name = e.name;
+}
+assert(name == "RangeError", "out of bounds reads throw RangeError");
+
+try {
+    arr.nope();
+} catch (e) {
+    name = e.name;
+}
+assert(name == "TypeError", "calling a non-function throws TypeError");
+
+try {
+    let x = null;
+    x.field;
+} catch (e) {
+    name = e.name;
+}
+assert(name == "TypeError", "reading a property of null throws TypeError");
+
+// Errors thrown by user code keep their message.
+let message = "";
+try {
+    throw RangeError("too big");
+} catch (e) {
+    message = e.message;
+}
+assert(message == "too big", "error constructors keep their message");
+
+// Errors inherit from their constructor's prototype, which inherits from Error.prototype.
+function thrownBy(f) {
+    try {
+        f();
+    } catch (e) {
+        return e;
+    }
+}
+let reference = thrownBy(function() { return missing; });
+let typed = thrownBy(function() { return [].reduce(function(a, b) { return a; }); });
+let syntax = thrownBy(function() { return JSON.parse("{"); });
+let made = new RangeError("made");
+assert(Object.getPrototypeOf(reference) == ReferenceError.prototype, "runtime faults");
+assert(Object.getPrototypeOf(typed) == TypeError.prototype, "errors from builtins");
+assert(Object.getPrototypeOf(syntax) == SyntaxError.prototype, "errors from JSON");
+assert(Object.getPrototypeOf(made) == RangeError.prototype, "new RangeError()");
+assert(Object.getPrototypeOf(RangeError("called")) == RangeError.prototype, "RangeError() without new");
+assert(Object.getPrototypeOf(TypeError.prototype) == Error.prototype, "TypeError.prototype inherits from Error.prototype");
+assert(Object.getPrototypeOf(Error.prototype) == Object.prototype, "Error.prototype inherits from Object.prototype");
+assert(reference.hasOwnProperty("message") && made.hasOwnProperty("name"), "errors are ordinary objects");
+Error.prototype.describe = function() {
+    return this.name + ": " + this.message;
+};
+assert(typed.describe() == "TypeError: Reduce of empty array with no initial value", "Error.prototype can be extended");
+assert(made.describe() == "RangeError: made", "and reaches constructed errors");
+
+// Exceptions unwind through function calls.
+function explode() {
+    throw "boom";
+}
+
+function caller() {
+    explode();
+    return "unreachable";
+}
+
+try {
+    caller();
+} catch (e) {
+    caught = e;
+}
+assert(caught == "boom", "exceptions unwind through calls");
+
+// finally runs on every path.
+let log = "";
+try {
+    log = log + "t";
+} finally {
+    log = log + "f";
+}
+assert(log == "tf", "finally runs after a normal try");
+
+log = "";
+try {
+    try {
+        throw "inner";
+    } finally {
+        log = log + "f";
+    }
+} catch (e) {
+    log = log + "c";
+}
+assert(log == "fc", "finally runs before the exception propagates");
+
+function early() {
+    try {
+        return 1;
+    } finally {
+        log = "returned";
+    }
+}
+assert(early() == 1, "return value survives finally");
+assert(log == "returned", "finally runs on return");
+
+function overridden() {
+    try {
+        return 1;
+    } finally {
+        return 2;
+    }
+}
+assert(overridden() == 2, "return in finally overrides the try");
+
+// Rethrowing from a catch block.
+try {
+    try {
+        throw "first";
+    } catch (e) {
+        throw e + " again";
+    }
+} catch (e) {
+    caught = e;
+}
+assert(caught == "first again", "catch blocks can rethrow");
+
+// The binding is optional.
+let handled = false;
+try {
+    throw 1;
+} catch {
+    handled = true;
+}
+assert(handled, "catch without a binding");
+
+// Loops still honour break/continue around try.
+let count = 0;
+for (let i = 0; i < 5; i = i + 1) {
+    try {
+        if (i == 1) {
+            continue;
+        }
+        if (i == 3) {
+            break;
+        }
+    } finally {
+        count = count + 1;
+    }
+}
+assert(count == 4, "finally runs when break/continue leave the try");
+
+console.log("exceptions: ok");
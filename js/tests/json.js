@@ -0,0 +1,113 @@
+// JSON.parse and JSON.stringify, with revivers, replacers, indentation, toJSON and cycles.
+// Exits with an uncaught error if any check fails.
+
+// JSON is full of double quotes, so the texts below are written with single quotes instead.
+let quote = String.fromCharCode(34);
+let newline = String.fromCharCode(10);
+let backslash = String.fromCharCode(92);
+
+// Backslashes are written as `~`, which also keeps them clear of string escapes.
+function json(text) {
+    return text.replaceAll("'", quote).replaceAll("~", backslash);
+}
+
+// parse
+let parsed = JSON.parse(json("{'name': 'tinyjs', 'tags': ['a', 'b'], 'nested': {'ok': true, 'none': null}}"));
+assert(parsed.name == "tinyjs" && parsed.tags.length == 2 && parsed.tags[1] == "b", "objects and arrays");
+assert(parsed.nested.ok == true && parsed.nested.none == null, "literals");
+assert(parsed.hasOwnProperty("name"), "parsed objects are ordinary objects");
+assert(JSON.parse("42") == 42 && JSON.parse(" -1.5e2 ") == -150 && JSON.parse("0.25") == 0.25, "numbers");
+assert(JSON.parse(json("'a~nb'")) == "a" + newline + "b", "escapes");
+assert(JSON.parse(json("'~u0041~ud83d~ude00'")) == "A😀", "unicode escapes and surrogate pairs");
+assert(JSON.parse("[]").length == 0 && Object.keys(JSON.parse("{}")).length == 0, "empty containers");
+assert(JSON.parse(json("{'a': 1, 'a': 2}")).a == 2, "the last duplicate key wins");
+
+assert(throws(function() { JSON.parse("{"); }, "SyntaxError"), "unexpected end");
+assert(throws(function() { JSON.parse("[1,]"); }, "SyntaxError"), "trailing commas");
+assert(throws(function() { JSON.parse(json("{a: 1}")); }, "SyntaxError"), "unquoted keys");
+assert(throws(function() { JSON.parse("01"); }, "SyntaxError"), "leading zeros");
+assert(throws(function() { JSON.parse("tru"); }, "SyntaxError"), "misspelt literals");
+assert(throws(function() { JSON.parse("1 2"); }, "SyntaxError"), "trailing input");
+assert(throws(function() { JSON.parse(""); }, "SyntaxError"), "empty input");
+
+// parse with a reviver
+let order = [];
+let revived = JSON.parse(json("{'a': 1, 'b': {'c': 2}, 'drop': 3}"), function(key, value) {
+    order.push(key);
+    if (key == "drop") {
+        return undefined;
+    }
+    if (intrinsics.typeof(value) == "number") {
+        return value * 10;
+    }
+    return value;
+});
+assert(revived.a == 10 && revived.b.c == 20, "the reviver replaces values");
+assert(!revived.hasOwnProperty("drop"), "returning undefined removes the key");
+assert(order.join(",") == "a,c,b,drop,", "the reviver runs innermost first and ends at the root");
+
+// stringify
+assert(JSON.stringify({ a: 1, b: "x", c: true, d: null }) == json("{'a':1,'b':'x','c':true,'d':null}"), "objects");
+assert(JSON.stringify([1, "two", [3]]) == json("[1,'two',[3]]"), "arrays");
+assert(JSON.stringify("a" + quote + backslash + newline) == json("'a~'~~~n'"), "strings are escaped");
+assert(JSON.stringify(1 / 0) == "null" && JSON.stringify(0 / 0) == "null" && JSON.stringify(-0) == "0", "non-finite numbers and -0");
+assert(JSON.stringify(0.1) == "0.1" && JSON.stringify(1000000000 * 1000000000000) == "1e+21", "number formatting");
+assert(JSON.stringify({ f: function() {}, u: undefined, n: 1 }) == json("{'n':1}"), "functions and undefined are skipped in objects");
+assert(JSON.stringify([function() {}, undefined]) == "[null,null]", "and become null in arrays");
+assert(JSON.stringify(undefined) == undefined && JSON.stringify(function() {}) == undefined, "nothing to serialize");
+assert(JSON.stringify({}) == "{}" && JSON.stringify([]) == "[]", "empty containers");
+
+// Round trips
+let data = { list: [1, 2, { deep: "yes" }], flag: false };
+assert(JSON.stringify(JSON.parse(JSON.stringify(data))) == JSON.stringify(data), "round trip");
+
+// Replacers
+let doubled = JSON.stringify({ a: 1, b: "s", c: [2] }, function(key, value) {
+    if (intrinsics.typeof(value) == "number") {
+        return value * 2;
+    }
+    return value;
+});
+assert(doubled == json("{'a':2,'b':'s','c':[4]}"), "a replacer function");
+assert(JSON.stringify({ a: 1, b: 2, c: 3 }, ["c", "a", "missing"]) == json("{'c':3,'a':1}"), "a replacer array picks keys in its order");
+assert(JSON.stringify({ a: 1, b: 2 }, function(key, value) {
+    if (key == "b") {
+        return undefined;
+    }
+    return value;
+}) == json("{'a':1}"), "a replacer can drop keys");
+
+// Indentation
+let expected = ["{", "  'a': 1,", "  'b': [", "    2", "  ]", "}"].join(newline);
+assert(JSON.stringify({ a: 1, b: [2] }, null, 2) == json(expected), "numeric indentation");
+assert(JSON.stringify([1], null, "--") == "[" + newline + "--1" + newline + "]", "string indentation");
+assert(JSON.stringify({ a: [] }, null, 4) == json("{" + newline + "    'a': []" + newline + "}"), "empty containers stay on one line");
+assert(JSON.stringify([1], null, 20) == JSON.stringify([1], null, 10), "indentation is capped at 10");
+
+// toJSON
+let stamp = { toJSON: function(key) { return "stamp@" + key; } };
+assert(JSON.stringify({ when: stamp }) == json("{'when':'stamp@when'}"), "toJSON gets the key");
+
+class Point {
+    constructor(x, y) {
+        this.x = x;
+        this.y = y;
+    }
+
+    toJSON() {
+        return [this.x, this.y];
+    }
+}
+assert(JSON.stringify([new Point(1, 2)]) == "[[1,2]]", "toJSON from a prototype");
+
+// Cycles
+let cyclic = { name: "loop" };
+cyclic.self = cyclic;
+assert(throws(function() { JSON.stringify(cyclic); }, "TypeError"), "cyclic objects throw");
+let ring = [];
+ring.push(ring);
+assert(throws(function() { JSON.stringify(ring); }, "TypeError"), "cyclic arrays throw");
+let shared = { v: 1 };
+assert(JSON.stringify([shared, shared]) == json("[{'v':1},{'v':1}]"), "repeated but acyclic values are fine");
+
+console.log("json: ok");
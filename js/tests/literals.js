@@ -0,0 +1,41 @@
+// String literals: both kinds of quote, every escape sequence and line continuations.
+// Exits with an uncaught error if any check fails.
+
+// Quotes
+assert('single' == "single", "single and double quotes make the same string");
+assert('say "hi"' == "say " + String.fromCharCode(34) + "hi" + String.fromCharCode(34), "double quotes inside single quotes");
+assert("it's" == "it" + String.fromCharCode(39) + "s", "single quotes inside double quotes");
+assert('' == "" && ''.length == 0, "empty strings");
+
+// Single-character escapes
+assert("\"" == String.fromCharCode(34) && '\'' == String.fromCharCode(39), "escaped quotes");
+assert("\\" == String.fromCharCode(92) && "\\".length == 1, "escaped backslashes");
+assert("\n" == String.fromCharCode(10) && "\r" == String.fromCharCode(13), "line feed and carriage return");
+assert("\t" == String.fromCharCode(9) && "\v" == String.fromCharCode(11), "tabs");
+assert("\b" == String.fromCharCode(8) && "\f" == String.fromCharCode(12), "backspace and form feed");
+assert("\0" == String.fromCharCode(0) && "a\0b".length == 3, "null characters");
+assert("\a\c\d\q" == "acdq" && "\`\$" == "`$", "other characters escape to themselves");
+
+// Hex and unicode escapes
+assert("\x41\x62" == "Ab" && "\xe9" == String.fromCharCode(233), "hex escapes");
+assert("\u0041" == "A" && "\u00e9" == "é", "four-digit unicode escapes");
+assert("\u{41}" == "A" && "\u{1F600}" == "😀" && "\u{0000000041}" == "A", "code point escapes");
+assert("\uD83D\uDE00" == "😀" && "\uD83D\uDE00".length == 2, "surrogate pairs from two escapes");
+assert("\u{1F600}".charCodeAt(0) == 55357 && "\u{1F600}".codePointAt(0) == 128512, "astral escapes take two code units");
+
+// Line continuations
+assert("one \
+two" == "one two", "a backslash before a line break removes it");
+assert('a\
+\
+b' == "ab", "several line continuations");
+
+// Escapes mean the same in every kind of literal.
+assert("\x41\t\u{1F600}" == '\x41\t\u{1F600}' && "\x41\t\u{1F600}" == `\x41\t\u{1F600}`, "strings and templates agree");
+
+// The lexer and the string methods agree on what they built.
+assert("tab\there".split("\t").length == 2, "split on an escape");
+assert("a\nb".indexOf("\n") == 1, "indexOf an escape");
+assert(JSON.stringify("q\"\n") == "\"q\\\"\\n\"", "JSON.stringify escapes what the lexer unescaped");
+
+console.log("literals: ok");
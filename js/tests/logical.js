@@ -0,0 +1,94 @@
+// Logical and conditional operators: short-circuiting && and ||, ??, the ternary operator
+// and the logical assignments, all evaluating to one of their operands.
+// Exits with an uncaught error if any check fails.
+
+let calls = 0;
+function touch(value) {
+    calls += 1;
+    return value;
+}
+
+// && and || return the operand that decided the result.
+assert((1 && "two") === "two" && (0 && "two") === 0 && ("" && 1) === "", "&& returns operands");
+assert((1 || "two") === 1 && (0 || "two") === "two" && (null || undefined) === undefined, "|| returns operands");
+assert((null || "default") === "default" && ("set" || "default") === "set", "|| for defaults");
+
+// The right operand only runs when the left one doesn't decide.
+calls = 0;
+false && touch(1);
+true || touch(1);
+assert(calls == 0, "&& and || skip the right operand");
+true && touch(1);
+false || touch(1);
+assert(calls == 2, "and evaluate it otherwise");
+
+let missing = null;
+assert((missing && missing.name) === null, "guarding a property read");
+let present = { name: "x" };
+assert((present && present.name) === "x", "reading through the guard");
+
+// ?? only falls through on null and undefined.
+assert((null ?? "b") === "b" && (undefined ?? "b") === "b", "?? replaces null and undefined");
+assert((0 ?? 1) === 0 && ("" ?? "b") === "" && (false ?? true) === false, "but keeps other falsy values");
+calls = 0;
+assert(("a" ?? touch("b")) === "a" && calls == 0, "?? short-circuits");
+assert((null ?? undefined ?? 3) === 3, "?? chains");
+assert(((null || 0) ?? 5) === 0 && (null ?? (0 || 5)) === 5, "parentheses mix ?? with ||");
+
+// Precedence: && binds tighter than ||, and both looser than comparisons.
+assert((1 || 0 && 0) === 1 && ((1 || 0) && 0) === 0, "&& before ||");
+assert((1 < 2 && 3 > 2) === true && (0 == 1 || "x") === "x", "comparisons first");
+
+// The conditional operator
+let n = 5;
+assert((n > 3 ? "big" : "small") === "big" && (n > 9 ? "big" : "small") === "small", "picks a branch");
+calls = 0;
+let picked = true ? touch("yes") : touch("no");
+assert(picked === "yes" && calls == 1, "only evaluates the chosen branch");
+function sign(x) {
+    return x > 0 ? 1 : x < 0 ? -1 : 0;
+}
+assert(sign(4) === 1 && sign(-4) === -1 && sign(0) === 0, "nests to the right");
+assert((true ? false ? 1 : 2 : 3) === 2, "nests in the consequent");
+assert((0 || null ? "a" : "b") === "b" && (null ?? 1 ? "a" : "b") === "a", "looser than || and ??");
+let chosen;
+true ? chosen = "then" : chosen = "else";
+assert(chosen === "then", "assignments in the branches");
+let spread = n > 3
+    ? "over"
+    : "under";
+assert(spread === "over", "across lines");
+let nested = { kind: n % 2 == 0 ? "even" : "odd" };
+assert(nested.kind === "odd", "inside an object literal");
+
+// Logical assignment
+let a = null;
+a ??= "filled";
+assert(a === "filled", "??= assigns to null");
+a ??= "again";
+assert(a === "filled", "??= leaves values alone");
+let b = 0;
+b ||= 7;
+assert(b === 7, "||= assigns to falsy values");
+b &&= b + 1;
+assert(b === 8, "&&= assigns to truthy values");
+let c = "";
+assert((c &&= "never") === "" && c === "", "&&= evaluates to the old value when it doesn't assign");
+
+calls = 0;
+let full = 1;
+full ||= touch(2);
+full ??= touch(3);
+assert(full === 1 && calls == 0, "the right side isn't evaluated when short-circuited");
+
+let options = { retries: 0, name: undefined };
+options.retries ||= 3;
+options.name ??= "default";
+options.timeout ??= 100;
+assert(options.retries === 3 && options.name === "default" && options.timeout === 100, "on properties");
+let counts = [0, 1];
+counts[0] ||= 10;
+counts[1] &&= 20;
+assert(counts[0] === 10 && counts[1] === 20, "on elements");
+
+console.log("logical: ok");
@@ -0,0 +1,118 @@
+// The Math namespace, including its NaN, -0 and empty-argument edge cases.
+// Exits with an uncaught error if any check fails.
+
+let inf = Infinity;
+let nan = NaN;
+
+function isNaN(x) {
+    return x != x;
+}
+
+// Zeros compare equal, so their sign shows through division.
+function isNegativeZero(x) {
+    return x == 0 && 1 / x == -inf;
+}
+
+function isPositiveZero(x) {
+    return x == 0 && 1 / x == inf;
+}
+
+function near(a, b) {
+    return Math.abs(a - b) < 0.000000001;
+}
+
+// The NaN and Infinity globals
+assert(inf === 1 / 0 && -inf === -1 / 0 && inf > 1e308, "Infinity");
+assert(isNaN(nan) && nan !== nan && intrinsics.typeof(nan) == "number", "NaN");
+
+// Constants
+assert(near(Math.PI, 3.141592653589793) && near(Math.E, 2.718281828459045), "PI and E");
+assert(near(Math.LN2, 0.6931471805599453) && near(Math.LN10, 2.302585092994046), "LN2 and LN10");
+assert(near(Math.LOG2E, 1.4426950408889634) && near(Math.LOG10E, 0.4342944819032518), "LOG2E and LOG10E");
+assert(near(Math.SQRT2, 1.4142135623730951) && near(Math.SQRT1_2, 0.7071067811865476), "SQRT2 and SQRT1_2");
+
+// Rounding
+assert(Math.floor(1.7) == 1 && Math.floor(-1.2) == -2, "floor");
+assert(Math.ceil(1.2) == 2 && Math.ceil(-1.7) == -1, "ceil");
+assert(isNegativeZero(Math.ceil(-0.5)), "ceil keeps the sign of zero");
+assert(Math.trunc(1.7) == 1 && Math.trunc(-1.7) == -1 && isNegativeZero(Math.trunc(-0.2)), "trunc");
+assert(Math.round(2.5) == 3 && Math.round(-2.5) == -2 && Math.round(2.4) == 2, "round takes halves up");
+assert(Math.round(-2.6) == -3 && Math.round(0.49999999999999994) == 0, "round near the halfway point");
+assert(isNegativeZero(Math.round(-0.4)) && isNegativeZero(Math.round(-0)) && isPositiveZero(Math.round(0.2)), "round keeps the sign of zero");
+assert(Math.fround(5.5) == 5.5 && Math.fround(5.05) != 5.05, "fround");
+
+// Signs and magnitudes
+assert(Math.abs(-3) == 3 && Math.abs("-2") == 2 && isPositiveZero(Math.abs(-0)), "abs");
+assert(Math.sign(-5) == -1 && Math.sign(5) == 1, "sign");
+assert(isNegativeZero(Math.sign(-0)) && isPositiveZero(Math.sign(0)) && isNaN(Math.sign(nan)), "sign of zeros and NaN");
+
+// max and min
+assert(Math.max(1, 3, 2) == 3 && Math.min(1, 3, 2) == 1, "max and min");
+assert(Math.max(5) == 5 && Math.min(5) == 5, "a single argument");
+assert(Math.max() == -inf && Math.min() == inf, "no arguments");
+assert(isNaN(Math.max(1, nan, 3)) && isNaN(Math.min(nan, 1)), "NaN wins");
+assert(isNaN(Math.max(1, "x")), "arguments are converted to numbers");
+assert(isPositiveZero(Math.max(-0, 0)) && isPositiveZero(Math.max(0, -0)), "max prefers +0");
+assert(isNegativeZero(Math.min(0, -0)) && isNegativeZero(Math.min(-0, 0)), "min prefers -0");
+assert(Math.max("7", 3) == 7, "numeric strings");
+assert(Math.max([1]) == 1 && Math.min([5], 2) == 2 && Math.max([]) == 0 && isNaN(Math.max([1, 2])), "arrays convert through their string");
+assert(Math.max(null, -1) == 0 && Math.max(true, 0) == 1 && isNaN(Math.max(undefined, 1)), "null, booleans and undefined");
+
+// Powers and roots
+assert(Math.pow(2, 10) == 1024 && Math.pow(4, 0.5) == 2 && Math.pow(2, -1) == 0.5, "pow");
+assert(Math.pow(nan, 0) == 1 && isNaN(Math.pow(1, nan)), "pow with NaN");
+assert(isNaN(Math.pow(1, inf)) && isNaN(Math.pow(-1, -inf)), "pow of one to an infinite power");
+assert(Math.sqrt(16) == 4 && isNaN(Math.sqrt(-1)) && isNegativeZero(Math.sqrt(-0)), "sqrt");
+assert(Math.cbrt(27) == 3 && Math.cbrt(-8) == -2, "cbrt");
+assert(Math.hypot(3, 4) == 5 && Math.hypot() == 0 && Math.hypot(-5) == 5, "hypot");
+assert(Math.hypot(nan, inf) == inf && isNaN(Math.hypot(nan, 1)), "hypot with NaN and infinities");
+
+// Exponentials and logarithms
+assert(Math.exp(0) == 1 && near(Math.exp(1), Math.E), "exp");
+assert(isNegativeZero(Math.expm1(-0)) && near(Math.expm1(1), Math.E - 1), "expm1");
+assert(Math.log(1) == 0 && Math.log(0) == -inf && isNaN(Math.log(-1)), "log");
+assert(Math.log2(8) == 3 && Math.log10(1000) == 3, "log2 and log10");
+assert(near(Math.log1p(Math.E - 1), 1) && isNegativeZero(Math.log1p(-0)), "log1p");
+
+// Trigonometry
+assert(Math.sin(0) == 0 && Math.cos(0) == 1 && Math.tan(0) == 0, "sin, cos and tan");
+assert(near(Math.sin(Math.PI / 2), 1) && near(Math.cos(Math.PI), -1), "trig at multiples of PI");
+assert(near(Math.asin(1), Math.PI / 2) && Math.acos(1) == 0 && near(Math.atan(1), Math.PI / 4), "inverse trig");
+assert(isNaN(Math.asin(2)) && isNaN(Math.acos(-2)), "inverse trig out of range");
+assert(near(Math.atan2(1, 1), Math.PI / 4) && near(Math.atan2(0, -1), Math.PI), "atan2");
+assert(isNegativeZero(Math.atan2(-0, 1)), "atan2 keeps the sign of zero");
+assert(Math.sinh(0) == 0 && Math.cosh(0) == 1 && Math.tanh(0) == 0 && Math.tanh(inf) == 1, "hyperbolic functions");
+assert(Math.asinh(0) == 0 && Math.acosh(1) == 0 && Math.atanh(1) == inf && isNaN(Math.acosh(0)), "inverse hyperbolic functions");
+
+// 32-bit integer helpers
+assert(Math.clz32(1) == 31 && Math.clz32(0) == 32 && Math.clz32(-1) == 0, "clz32");
+assert(Math.clz32(4294967296 + 1) == 31 && Math.clz32(nan) == 32, "clz32 wraps to 32 bits");
+assert(Math.imul(3, 4) == 12 && Math.imul(-5, 12) == -60, "imul");
+assert(Math.imul(4294967295, 5) == -5 && Math.imul(2147483647, 2) == -2, "imul wraps");
+
+// Objects convert through valueOf, and what it throws propagates.
+let seven = { valueOf: function() { return 7; } };
+assert(Math.abs(seven) == 7 && Math.pow(seven, 2) == 49 && Math.max(1, seven) == 7, "valueOf");
+assert(Math.floor({ toString: function() { return "2.5"; } }) == 2, "toString when there's no valueOf");
+let calls = 0;
+let counted = { valueOf: function() { calls += 1; return nan; } };
+Math.max(counted, counted, 1);
+assert(calls == 2, "every argument is converted, even after a NaN");
+let throwing = { valueOf: function() { throw RangeError("nope"); } };
+assert(throws(function() { return Math.sqrt(throwing); }, "RangeError"), "errors from valueOf propagate");
+assert(throws(function() { return Math.min(1, throwing); }, "RangeError"), "from any argument");
+
+// Missing arguments are NaN.
+assert(isNaN(Math.floor()) && isNaN(Math.sqrt()) && isNaN(Math.pow(2)), "missing arguments");
+
+// random
+let inRange = true;
+for (let i = 0; i < 100; i++) {
+    let r = Math.random();
+    if (r < 0 || r >= 1) {
+        inRange = false;
+    }
+}
+assert(inRange, "random is in [0, 1)");
+
+console.log("math: ok");
@@ -0,0 +1,45 @@
+// Numeric literals: every radix, exponents, leading dots, separators and BigInts.
+// Exits with an uncaught error if any check fails.
+
+// Decimals
+assert(0 == 0 && 42 == 40 + 2 && 3.25 == 13 / 4, "integers and fractions");
+assert(.5 == 0.5 && .125 * 8 == 1, "a leading dot");
+assert(5. == 5 && 1.0 == 1, "a trailing dot");
+assert(0.1 + 0.2 != 0.3, "doubles are doubles");
+
+// Exponents
+assert(1e3 == 1000 && 1E3 == 1000 && 2e+2 == 200, "positive exponents");
+assert(1e-9 == 0.000000001 && 25e-1 == 2.5, "negative exponents");
+assert(1.5e2 == 150 && .5e1 == 5, "exponents on fractions");
+assert(1e21 == 1000000000 * 1000000000000 && String(1e21) == "1e+21", "large exponents");
+assert(1e400 == 1 / 0 && 1e-400 == 0, "exponents out of range");
+
+// Other radixes
+assert(0xff == 255 && 0XFF == 255 && 0xDeadBeef == 3735928559, "hexadecimal");
+assert(0o17 == 15 && 0O777 == 511, "octal");
+assert(0b1010 == 10 && 0B11111111 == 255, "binary");
+assert(0x0 == 0 && 0b0 == 0 && 0o0 == 0, "zeros in every radix");
+
+// Separators
+assert(1_000_000 == 1000000 && 1_0.0_1 == 10.01, "decimal separators");
+assert(1e1_0 == 10000000000, "separators in exponents");
+assert(0xff_ff == 65535 && 0b1010_1010 == 170 && 0o7_7 == 63, "separators in other radixes");
+
+// Property access after a number
+assert(1.5.toString() == "1.5" && 0xff.toString() == "255", "members of numeric literals");
+
+// BigInts
+assert(intrinsics.typeof(10n) == "bigint" && intrinsics.typeof(0n) == "bigint", "typeof");
+assert(String(123n) == "123" && `${0x10n}` == "16", "BigInts print without the suffix");
+assert(0x1fn == 31n && 0o10n == 8n && 0b11n == 3n && 1_000n == 1000n, "BigInt literals in every radix");
+assert(2n + 3n == 5n && 10n - 20n == -10n && 6n * 7n == 42n, "arithmetic");
+assert(7n / 2n == 3n && -7n / 2n == -3n && -7n % 2n == -1n, "division truncates");
+assert(9007199254740993n - 9007199254740992n == 1n, "BigInts don't lose precision");
+assert(123456789012345678901234567890n * 10n == 1234567890123456789012345678900n, "arbitrary size");
+assert(1n < 2n && 2n >= 2n && 1n < 2 && 3 > 2n, "comparisons");
+assert(!0n && !!1n, "truthiness");
+assert("n=" + 5n == "n=5", "string concatenation");
+assert(throws(function() { return 1n + 1; }, "TypeError"), "mixing BigInts and numbers throws");
+assert(throws(function() { return 1n / 0n; }, "RangeError"), "division by zero throws");
+
+console.log("numbers: ok");
@@ -0,0 +1,98 @@
+// Objects and arrays are shared by reference: aliasing, nested mutation and identity equality.
+// Exits with an uncaught error if any check fails.
+
+// Aliases see each other's writes.
+let a = { x: 1 };
+let b = a;
+b.x = 2;
+assert(a.x == 2, "aliases share one object");
+b.y = 3;
+assert(a.y == 3, "new properties are visible through aliases");
+
+// Functions mutate the caller's object.
+function bump(obj) {
+    obj.count = obj.count + 1;
+}
+let counter = { count: 0 };
+bump(counter);
+bump(counter);
+assert(counter.count == 2, "mutation inside a callee is visible to the caller");
+
+function fill(arr) {
+    arr.push(1);
+    arr[1] = 2;
+}
+let list = [];
+fill(list);
+assert(list.length == 2 && list[1] == 2, "arrays are shared with callees");
+
+// Nested assignment.
+let config = { server: { limits: { retries: 1 } } };
+config.server.limits.retries = 5;
+assert(config.server.limits.retries == 5, "a.b.c = value");
+config.server.port = 8080;
+assert(config.server.port == 8080, "nested property creation");
+
+let inner = config.server;
+inner.limits.retries = 7;
+assert(config.server.limits.retries == 7, "nested objects are shared too");
+
+let grid = [[0, 0], [0, 0]];
+grid[1][0] = 4;
+assert(grid[1][0] == 4, "nested index assignment");
+
+let holder = { items: [1, 2] };
+holder.items[0] = 9;
+assert(holder.items[0] == 9, "index assignment through a property");
+
+let rows = [{ v: 1 }];
+rows[0].v = 2;
+assert(rows[0].v == 2, "property assignment through an index");
+
+// Compound assignment and update operators on properties and elements.
+let stats = { hits: 1, names: ["a"] };
+stats.hits += 2;
+stats.hits++;
+assert(stats.hits == 4, "compound assignment on properties");
+stats.names[0] += "b";
+assert(stats.names[0] == "ab", "compound assignment on elements");
+
+let i = 0;
+let nums = [10, 20];
+nums[i++] += 1;
+assert(nums[0] == 11 && i == 1, "target evaluated once");
+
+// Identity equality.
+let p = { v: 1 };
+let q = { v: 1 };
+assert(p == p, "an object equals itself");
+assert(p != q, "structurally equal objects are distinct");
+assert(p == { v: 1 } == false, "fresh literals are distinct");
+let r = p;
+assert(r == p, "aliases are equal");
+assert([1] != [1], "arrays compare by identity");
+
+// Objects stored in closures stay shared.
+function makeStore() {
+    let state = { n: 0 };
+    return {
+        state: state,
+        inc: function() { state.n++; }
+    };
+}
+let store = makeStore();
+store.inc();
+store.inc();
+assert(store.state.n == 2, "closures and properties share the object");
+
+// Writing to a missing object is a TypeError.
+let caught = null;
+try {
+    let missing = undefined;
+    missing.x = 1;
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "TypeError", "assigning a property of undefined throws");
+
+console.log("objects: ok");
@@ -0,0 +1,80 @@
+// Operator precedence and associativity of the expression parser.
+// Exits with an uncaught error if any check fails.
+
+// Multiplicative binds tighter than additive.
+assert(2 + 3 * 4 == 14, "2 + 3 * 4");
+assert(2 * 3 + 4 == 10, "2 * 3 + 4");
+assert(2 + 3 * 4 - 5 == 9, "2 + 3 * 4 - 5");
+assert(1 + 6 / 2 == 4, "1 + 6 / 2");
+assert(1 + 7 % 4 == 4, "1 + 7 % 4");
+assert((2 + 3) * 4 == 20, "parentheses override precedence");
+
+// Binary operators are left-associative.
+assert(1 - 2 - 3 == -4, "1 - 2 - 3");
+assert(10 - 2 + 3 == 11, "10 - 2 + 3");
+assert(8 / 4 / 2 == 1, "8 / 4 / 2");
+assert(2 * 3 % 4 == 2, "2 * 3 % 4");
+assert(100 / 10 * 2 == 20, "100 / 10 * 2");
+assert("a" + 1 + 2 == "a12", "string concatenation is left-associative");
+assert(1 + 2 + "a" == "3a", "numeric addition happens before concatenation");
+
+// Arithmetic binds tighter than comparison, comparison tighter than equality.
+assert(1 + 2 == 3, "1 + 2 == 3");
+assert(1 + 1 < 3, "1 + 1 < 3");
+assert(2 * 2 >= 4, "2 * 2 >= 4");
+assert(1 < 2 == 3 < 4, "relational binds tighter than equality");
+assert(1 > 2 != 3 > 4 == false, "equality is left-associative");
+
+// && binds tighter than ||, and both are looser than equality.
+assert(true || false && false, "&& before ||");
+assert((true || false) && false == false, "parenthesised ||");
+assert(1 == 1 && 2 == 2, "equality before &&");
+assert(1 == 2 || 2 == 2, "equality before ||");
+
+// Prefix operators bind tighter than binary ones but looser than postfix ones.
+assert(-2 * 3 == -6, "-2 * 3");
+assert(-(2 + 3) == -5, "-(2 + 3)");
+assert(2 - -3 == 5, "2 - -3");
+assert(- -1 == 1, "nested negation");
+assert(!false && false == false, "! before &&");
+assert(!!true, "double negation");
+
+let arr = [4, 5];
+let obj = { v: 3 };
+assert(-arr[0] == -4, "indexing before negation");
+assert(-obj.v * 2 == -6, "member access before negation");
+assert(!arr.includes(4) == false, "calls before !");
+
+let i = 1;
+assert(-i++ == -1, "postfix ++ before negation");
+assert(i == 2, "postfix ++ still applies");
+assert(i++ + 1 == 3, "postfix ++ before +");
+assert(i == 3, "postfix ++ in a sum");
+assert(++i * 2 == 8 && i == 4, "prefix ++ gives the new value, before *");
+assert(--i + 1 == 4 && i == 3, "prefix -- before +");
+
+// Assignment binds loosest and is right-associative.
+let a = 0;
+let b = 0;
+a = b = 5;
+assert(a == 5 && b == 5, "a = b = 5");
+a = 1 + 2 * 3;
+assert(a == 7, "assignment takes the whole right-hand side");
+
+let x = 2;
+x += 2 * 3;
+assert(x == 8, "x += 2 * 3");
+x -= 1 + 1;
+assert(x == 6, "x -= 1 + 1");
+x *= 1 + 1;
+assert(x == 12, "x *= 1 + 1");
+x /= 2 + 1;
+assert(x == 4, "x /= 2 + 1");
+x %= 1 + 2;
+assert(x == 1, "x %= 1 + 2");
+
+let y = 0;
+a = y += 3;
+assert(a == 3 && y == 3, "compound assignment is right-associative");
+
+console.log("precedence: ok");
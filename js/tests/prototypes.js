@@ -0,0 +1,141 @@
+// Prototype chains, `this` binding, `new` and the builtin prototypes.
+// Exits with an uncaught error if any check fails.
+
+// Methods see the object they were called on.
+let counter = {
+    count: 0,
+    bump: function() {
+        this.count += 1;
+        return this;
+    }
+};
+counter.bump().bump();
+assert(counter.count == 2, "this is the receiver, so calls chain");
+
+assert(intrinsics.typeof(this) == "undefined", "top-level this is undefined");
+
+// Constructors and shared methods.
+function Point(x, y) {
+    this.x = x;
+    this.y = y;
+}
+Point.prototype.sum = function() {
+    return this.x + this.y;
+};
+
+let p = new Point(1, 2);
+assert(p.x == 1 && p.y == 2, "new binds this to the fresh object");
+assert(p.sum() == 3, "methods are found on the prototype");
+assert(p.__proto__ == Point.prototype, "instances inherit from F.prototype");
+assert(Object.getPrototypeOf(p) == Point.prototype, "Object.getPrototypeOf");
+assert(p.hasOwnProperty("x") && !p.hasOwnProperty("sum"), "own and inherited properties differ");
+
+let q = new Point(10, 20);
+Point.prototype.scaled = function(k) {
+    return new Point(this.x * k, this.y * k);
+};
+assert(q.scaled(2).sum() == 60, "prototypes can be extended after instances exist");
+
+// Own properties shadow inherited ones.
+p.sum = function() {
+    return 0;
+};
+assert(p.sum() == 0 && q.sum() == 30, "own properties shadow the prototype");
+
+// A constructor returning an object replaces the instance; primitives are ignored.
+function Boxed() {
+    this.ignored = true;
+    return { boxed: true };
+}
+assert(new Boxed().boxed, "returning an object overrides new");
+function Plain() {
+    this.kept = true;
+    return 5;
+}
+assert(new Plain().kept, "returning a primitive keeps the instance");
+function Empty() {}
+let e = new Empty;
+assert(e.__proto__ == Empty.prototype, "parentheses are optional with no arguments");
+assert(e, "objects without own properties are still truthy");
+
+// Object.create and __proto__ chains.
+let animal = {
+    speak: function() {
+        return this.name + " makes a sound";
+    }
+};
+let dog = Object.create(animal);
+dog.name = "Rex";
+assert(dog.speak() == "Rex makes a sound", "Object.create links the prototype");
+
+let puppy = Object.create(dog);
+puppy.name = "Bit";
+assert(puppy.speak() == "Bit makes a sound", "lookups walk the whole chain");
+
+let made = new Object();
+made.a = 1;
+assert(made.hasOwnProperty("a") && !Object().hasOwnProperty("a"), "new Object() inherits from Object.prototype");
+assert(Object.getPrototypeOf(Object()) == Object.prototype && Object(animal) == animal, "Object() passes objects through");
+
+let bare = Object.create(null);
+assert(bare.toString == undefined, "Object.create(null) inherits nothing");
+
+let swapped = {};
+swapped.__proto__ = animal;
+swapped.name = "Cat";
+assert(swapped.speak() == "Cat makes a sound", "assigning __proto__ changes the prototype");
+
+let caught = null;
+try {
+    animal.__proto__ = puppy;
+} catch (err) {
+    caught = err;
+}
+assert(caught.name == "TypeError", "cyclic prototype chains are rejected");
+
+// `new` with a member expression callee.
+let shapes = { Point: Point };
+assert(new shapes.Point(3, 4).sum() == 7, "new accepts a property as the constructor");
+
+caught = null;
+try {
+    new counter.count();
+} catch (err) {
+    caught = err;
+}
+assert(caught.name == "TypeError", "new on a non-function throws");
+
+// Builtin prototypes are ordinary, extendable objects.
+Array.prototype.sum = function() {
+    let total = 0;
+    for (let i = 0; i < this.length; i++) {
+        total += this[i];
+    }
+    return total;
+};
+assert([1, 2, 3].sum() == 6, "Array.prototype can be extended");
+
+String.prototype.shout = function() {
+    return this + "!";
+};
+assert("hi".shout() == "hi!", "String.prototype can be extended");
+
+Object.prototype.describe = function() {
+    return "an object";
+};
+assert(p.describe() == "an object" && [].describe() == "an object", "everything inherits Object.prototype");
+
+assert(Array.prototype.push == [].push, "array methods live on Array.prototype");
+assert(new Array(1, 2).length == 2, "Array builds arrays");
+assert(String(12) == "12" && Number("42") == 42, "String and Number convert");
+
+// Bad arguments to the Object statics throw rather than aborting.
+assert(throws(function() { Object.create(5); }, "TypeError"), "Object.create with a primitive prototype");
+assert(throws(function() { Object.getPrototypeOf(1); }, "TypeError"), "Object.getPrototypeOf on a primitive");
+assert(throws(function() { Object.keys(null); }, "TypeError"), "Object.keys on null");
+assert(Object.keys({ a: 1, b: 2 }).join() == "a,b", "Object.keys on an object");
+
+let err = new Error("boom");
+assert(err.message == "boom", "native constructors work with new");
+
+console.log("prototypes: ok");
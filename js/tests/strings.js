@@ -0,0 +1,106 @@
+// String.prototype methods and the `length` property. Positions count UTF-16 code units, so
+// the emoji below takes up two of them. Exits with an uncaught error if any check fails.
+
+let s = "hello world";
+let wide = "a😀b";
+
+// length
+assert(s.length == 11 && "".length == 0, "length");
+assert(wide.length == 4, "length counts UTF-16 code units");
+
+// charAt, charCodeAt, codePointAt
+assert(s.charAt(4) == "o" && s.charAt(99) == "" && s.charAt(-1) == "", "charAt");
+assert(s.charAt() == "h", "charAt defaults to 0");
+assert(s.charCodeAt(0) == 104, "charCodeAt");
+assert(intrinsics.typeof(s.charCodeAt(99)) == "number" && !(s.charCodeAt(99) == s.charCodeAt(99)), "charCodeAt out of range is NaN");
+assert(wide.charCodeAt(1) == 55357 && wide.charCodeAt(2) == 56832, "charCodeAt sees surrogate halves");
+assert(wide.codePointAt(1) == 128512, "codePointAt combines a surrogate pair");
+assert(wide.codePointAt(2) == 56832, "codePointAt on a trailing surrogate");
+assert(wide.codePointAt(3) == 98 && wide.codePointAt(4) == undefined, "codePointAt");
+
+// indexOf, lastIndexOf, includes
+assert(s.indexOf("o") == 4 && s.indexOf("o", 5) == 7 && s.indexOf("z") == -1, "indexOf");
+assert(s.indexOf("") == 0 && s.indexOf("", 99) == 11, "indexOf an empty string");
+assert(wide.indexOf("b") == 3, "indexOf counts code units");
+assert(s.lastIndexOf("o") == 7 && s.lastIndexOf("o", 6) == 4 && s.lastIndexOf("z") == -1, "lastIndexOf");
+assert(s.lastIndexOf("") == 11, "lastIndexOf an empty string");
+assert(s.includes("lo w") && !s.includes("hello", 1), "includes");
+
+// startsWith, endsWith
+assert(s.startsWith("hell") && !s.startsWith("world") && s.startsWith("world", 6), "startsWith");
+assert(s.endsWith("world") && s.endsWith("hello", 5) && !s.endsWith("hello"), "endsWith");
+
+// slice, substring
+assert(s.slice(6) == "world" && s.slice(0, 5) == "hello", "slice");
+assert(s.slice(-5) == "world" && s.slice(-5, -2) == "wor", "slice with negative indices");
+assert(s.slice(5, 2) == "", "slice with start after end");
+assert(wide.slice(1, 3) == "😀", "slice by code units");
+assert(s.substring(6) == "world" && s.substring(5, 0) == "hello", "substring swaps its arguments");
+assert(s.substring(-3, 2) == "he", "substring clamps negative indices to 0");
+
+// Case and whitespace
+assert(s.toUpperCase() == "HELLO WORLD" && "MiXeD".toLowerCase() == "mixed", "case conversion");
+assert("  padded  ".trim() == "padded", "trim");
+assert("  padded  ".trimStart() == "padded  " && "  padded  ".trimEnd() == "  padded", "trimStart and trimEnd");
+assert("\t\n\r\v\f\u00a0\u2028\u2029\ufeff\u3000x ".trim() == "x", "trim strips JS whitespace and line terminators");
+assert(" \u0085x".trim().length == 2 && "\u200bx".trim().length == 2, "but not U+0085 or zero width spaces");
+
+// padStart, padEnd, repeat
+assert("5".padStart(3, "0") == "005" && "5".padEnd(3, "0") == "500", "padStart and padEnd");
+assert("x".padStart(6, "ab") == "ababax", "padding repeats and truncates the filler");
+assert("x".padStart(3) == "  x" && "long".padStart(2) == "long", "default filler and short targets");
+assert("x".padEnd(3, "") == "x", "an empty filler pads nothing");
+assert(throws(function() { return "a".padStart(Infinity); }, "RangeError"), "padStart past the longest string throws");
+assert(throws(function() { return "a".padEnd(1e10, "xy"); }, "RangeError"), "and so does padEnd");
+assert("a".padEnd(Infinity, "") == "a", "unless there's nothing to pad with");
+assert("ab".repeat(3) == "ababab" && "ab".repeat(0) == "", "repeat");
+assert(throws(function() { return "ab".repeat(1e10); }, "RangeError") && "".repeat(1e10) == "", "repeat past the longest string throws");
+
+let caught = null;
+try {
+    "ab".repeat(-1);
+} catch (e) {
+    caught = e;
+}
+assert(caught.name == "RangeError", "repeat with a negative count throws");
+
+// replace, replaceAll
+assert("a-b-c".replace("-", "+") == "a+b-c", "replace changes the first match");
+assert("a-b-c".replaceAll("-", "+") == "a+b+c", "replaceAll changes every match");
+assert("abc".replace("b", "[$&]") == "a[b]c", "$& inserts the match");
+assert("abc".replace("b", "$`$'") == "aacc", "$` and $' insert the surroundings");
+assert("abc".replace("b", "$$") == "a$c", "$$ is a literal dollar sign");
+assert("abc".replace("z", "y") == "abc", "replace without a match");
+assert("a-b-c".replaceAll("-", function(match, position) { return position; }) == "a1b3c", "replacement functions");
+assert("aaa".replaceAll("aa", "b") == "ba", "replaceAll doesn't overlap matches");
+
+// concat, split
+assert("a".concat("b", 1, true) == "ab1true", "concat");
+assert("a,b,,c".split(",").length == 4, "split");
+assert("abc".split("").join("-") == "a-b-c", "split into characters");
+assert("abc".split().length == 1, "split without a separator");
+assert("a,b,c".split(",", 2).join("") == "ab", "split with a limit");
+
+// Arguments are converted through toString and valueOf, like any string conversion.
+let ell = { toString: function() { return "l"; } };
+let two = { valueOf: function() { return 2; }, toString: function() { return "ll"; } };
+assert("hello".indexOf(ell) == 2 && "hello".lastIndexOf(ell) == 3 && "hello".includes(two), "searches");
+assert("hello".startsWith({ toString: function() { return "he"; } }) && "hello".endsWith(two, 4), "startsWith and endsWith");
+assert("hello".replace(ell, "L") == "heLlo" && "hello".replaceAll(ell, ell) == "hello", "replace patterns and replacements");
+assert("hello".replace("h", function() { return ell; }) == "lello", "replacement functions' results");
+assert("x".padStart(4, ell) == "lllx" && "x".padEnd(2, [7]) == "x7", "pad fillers");
+assert("a".concat(ell, [1, 2]) == "al1,2" && "hello".split(ell).length == 3, "concat and split");
+assert(throws(function() { return "a".indexOf({ toString: function() { throw RangeError("no"); } }); }, "RangeError"), "errors from toString propagate");
+
+// String.fromCharCode
+assert(String.fromCharCode(104, 105) == "hi", "fromCharCode");
+assert(String.fromCharCode(55357, 56832) == "😀", "fromCharCode joins surrogate pairs");
+assert(String.fromCharCode(65536 + 65) == "A", "fromCharCode truncates to 16 bits");
+
+// Methods work on string values held in variables and returned from calls.
+function greet(name) {
+    return "hi " + name;
+}
+assert(greet("bob").toUpperCase().slice(3) == "BOB", "methods chain");
+
+console.log("strings: ok");
@@ -0,0 +1,121 @@
+// Template literals: interpolation, nesting, escapes, line breaks, tagged templates and
+// String.raw. Exits with an uncaught error if any check fails.
+
+let newline = String.fromCharCode(10);
+let backslash = String.fromCharCode(92);
+
+// Interpolation
+let name = "world";
+let count = 3;
+assert(`hello` == "hello" && `` == "", "templates without substitutions");
+assert(`hello ${name}!` == "hello world!", "a substitution");
+assert(`${count} + ${count} = ${count + count}` == "3 + 3 = 6", "expressions");
+assert(`${name}` == "world" && `${count}${count}` == "33", "substitutions at the edges");
+assert(`${true} ${null} ${undefined}` == "true null undefined", "values are converted to strings");
+assert(`${1 / 0} ${-0}` == "Infinity 0", "numbers are formatted like String(n)");
+assert(`${[1, [2, 3]]} ${{}}` == "1,2,3 [object Object]", "objects are converted with toString");
+
+let point = { x: 1, y: 2 };
+assert(`(${point.x}, ${point.y})` == "(1, 2)", "member access");
+assert(`${{ x: 5 }.x}` == "5", "braces inside a substitution");
+assert(`${[1, 2, 3].map(function(n) { return n * 2; }).join("")}` == "246", "functions inside a substitution");
+
+// Nesting
+assert(`a ${`b ${`c ${count}`}`} d` == "a b c 3 d", "nested templates");
+assert(`${`${`${name}`}`}` == "world", "deeply nested templates");
+
+// Escapes and line breaks
+assert(`a\nb` == "a" + newline + "b", "escapes");
+assert(`\x41B\u{43}\u{1F600}` == "ABC😀", "hex and unicode escapes");
+assert(`\`\$\{` == "`${", "escaped backticks and substitutions");
+assert(`\${name}` == "${name}", "an escaped dollar sign doesn't start a substitution");
+assert(`a
+b` == "a" + newline + "b", "templates span lines");
+assert(`a\
+b` == "ab", "line continuations");
+assert(`$` == "$" && `{}` == "{}" && `$ {}` == "$ {}", "lone dollar signs and braces");
+
+// Tagged templates
+function tag(strings, a, b) {
+    return strings.join("|") + " / " + a + " / " + b;
+}
+assert(tag`x${1}y${2}z` == "x|y|z / 1 / 2", "tags get the strings and each substitution");
+assert(tag`${1}${2}` == "|| / 1 / 2", "empty strings around substitutions");
+
+function count_args() {
+    return arguments.length;
+}
+assert(count_args`none` == 1 && count_args`${1}${2}${3}` == 4, "one argument per substitution");
+
+function raw(strings) {
+    return strings.raw[0];
+}
+assert(raw`a\nb` == "a" + backslash + "nb", "raw strings keep escapes");
+assert(raw`a\
+b` == "a" + backslash + newline + "b", "raw strings keep line continuations");
+
+function cooked(strings) {
+    return strings[0];
+}
+assert(cooked`\unicode` == undefined && raw`\unicode` == backslash + "unicode", "invalid escapes cook to undefined");
+
+let sites = [];
+function remember(strings) {
+    sites.push(strings);
+}
+for (let i = 0; i < 2; i++) {
+    remember`same site`;
+}
+remember`same site`;
+assert(sites[0] == sites[1] && sites[0] != sites[2], "each call site passes the same strings array");
+
+// The strings array and its raw array are frozen, so one call can't change what the next sees.
+function grow(strings) {
+    try {
+        strings.push("x");
+    } catch (e) {
+        return e.name;
+    }
+    return strings.length;
+}
+assert(grow`a${1}b` == "TypeError" && grow`a${1}b` == "TypeError", "the strings array can't grow");
+function overwrite(strings) {
+    try {
+        strings[0] = "changed";
+    } catch (e) {
+    }
+    try {
+        strings.raw[0] = "changed";
+    } catch (e) {
+    }
+    return strings[0] + strings.raw[0];
+}
+assert(overwrite`same` == "samesame" && overwrite`same` == "samesame", "elements and raw strings can't change");
+let plain = [1, 2];
+assert(plain.raw == undefined, "other arrays have no raw property");
+plain.label = "named";
+assert(plain.label == "named" && plain.length == 2, "arrays can have named properties");
+
+let greeter = {
+    greeting: "hi",
+    greet: function(strings, who) {
+        return this.greeting + strings[0] + who;
+    }
+};
+assert(greeter.greet` ${name}` == "hi world", "method tags get their object as this");
+
+function html(strings) {
+    let out = strings[0];
+    for (let i = 1; i < strings.length; i++) {
+        out += String(arguments[i]).toUpperCase() + strings[i];
+    }
+    return out;
+}
+assert(html`<b>${name}</b> x${count}` == "<b>WORLD</b> x3", "a tag that rebuilds the string");
+
+// String.raw
+assert(String.raw`a\nb${count}\t` == "a" + backslash + "nb3" + backslash + "t", "String.raw");
+assert(String.raw`\u{1F600}` == backslash + "u{1F600}", "String.raw leaves unicode escapes alone");
+assert(String.raw({ raw: ["x", "y", "z"] }, 1) == "x1yz", "String.raw with a plain object");
+
+console.log("templates: ok");
@@ -1,16 +1,43 @@
-use std::collections::HashMap;
-use crate::lexer::Token;
+use num_bigint::BigInt;
+use crate::lexer::{Span, SpannedToken, Token};
+
+/// Characters that end a line, and so end `//` comments and count towards line numbers.
+pub fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+/// Whitespace other than line terminators: tabs, form feeds, the byte order mark and every
+/// Unicode space separator. `char::is_whitespace` covers the separators but also counts
+/// `U+0085`, which JS doesn't.
+pub fn is_whitespace(c: char) -> bool {
+    c == '\u{FEFF}' || (c.is_whitespace() && !is_line_terminator(c) && c != '\u{85}')
+}
 
 pub struct Lexer {
     source: String,
-    pos: usize
+    /// Byte offset of the next character, which is always on a char boundary.
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// Number of `{` currently open.
+    braces: usize,
+    /// The brace depth at each open `${`, innermost last, so the `}` closing a substitution
+    /// can be told apart from one closing a block or object.
+    templates: Vec<usize>,
+    /// Whether a line break has been skipped since the last token.
+    newline: bool
 }
 
 impl Lexer {
     pub fn new(source: impl AsRef<str>) -> Self {
         Self {
             source: source.as_ref().to_string(),
-            pos: 0,
+            offset: 0,
+            line: 1,
+            column: 1,
+            braces: 0,
+            templates: Vec::new(),
+            newline: false,
         }
     }
 
@@ -18,20 +45,33 @@ impl Lexer {
         self.peek_ahead(0)
     }
 
+    /// Looks `by` characters past the cursor. Only ever called with small `by`, so this stays
+    /// constant time however far into the source we are.
     fn peek_ahead(&self, by: usize) -> Option<char> {
-        if self.pos + by >= self.source.len() {
-            None
-        } else {
-            self.source.chars().nth(self.pos + by)
-        }
+        self.source[self.offset..].chars().nth(by)
     }
 
     fn consume(&mut self) -> Option<char> {
         let res = self.peek();
-        self.pos += 1;
+
+        if let Some(c) = res {
+            self.offset += c.len_utf8();
+            // `\r\n` is one line break, counted at the `\n`.
+            if is_line_terminator(c) && !(c == '\r' && self.peek() == Some('\n')) {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
         res
     }
 
+    fn span_from(&self, start: usize, line: usize, column: usize) -> Span {
+        Span::new(start, self.offset, line, column)
+    }
+
     fn consume_while(&mut self, predicate: impl Fn(char) -> bool) {
         while let Some(c) = self.peek() {
             if predicate(c) {
@@ -42,91 +82,417 @@ impl Lexer {
         }
     }
 
-    fn lex_identifier(&mut self) -> Token {
-        let mut word = String::new();
-
-        let keyword_map = HashMap::from([
-            ("let", Token::Let),
-            ("var", Token::Var),
-            ("if", Token::If),
-            ("else", Token::Else),
-            ("while", Token::While),
-            ("for", Token::For),
-            ("do", Token::Do),
-            ("continue", Token::Continue),
-            ("break", Token::Break),
-            ("return", Token::Return),
-            ("function", Token::Function),
-            ("true", Token::True),
-            ("false", Token::False),
-            ("null", Token::Null),
-            ("undefined", Token::Undefined),
-        ]);
+    fn keyword(word: &str) -> Option<Token> {
+        Some(match word {
+            "let" => Token::Let,
+            "var" => Token::Var,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "for" => Token::For,
+            "do" => Token::Do,
+            "continue" => Token::Continue,
+            "break" => Token::Break,
+            "return" => Token::Return,
+            "function" => Token::Function,
+            "try" => Token::Try,
+            "catch" => Token::Catch,
+            "finally" => Token::Finally,
+            "throw" => Token::Throw,
+            "this" => Token::This,
+            "new" => Token::New,
+            "class" => Token::Class,
+            "extends" => Token::Extends,
+            "super" => Token::Super,
+            "true" => Token::True,
+            "false" => Token::False,
+            "null" => Token::Null,
+            "undefined" => Token::Undefined,
+            _ => return None
+        })
+    }
 
+    /// Skips a `/* */` comment, with the cursor just past the `/*`. Gives `false` if the source
+    /// ends before the comment does.
+    fn skip_block_comment(&mut self) -> bool {
+        while let Some(c) = self.consume() {
+            // A comment spanning lines separates tokens just like a line break would.
+            self.newline |= is_line_terminator(c);
+            if c == '*' && self.peek() == Some('/') {
+                self.consume();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn lex_identifier(&mut self) -> Token {
         // Parse until whitespace or punctuation.
+        let start = self.offset;
+        self.consume_while(|c| c.is_alphanumeric() || c == '_' || c == '$');
+        let word = &self.source[start..self.offset];
+
+        Self::keyword(word).unwrap_or_else(|| Token::Identifier(word.to_string()))
+    }
+
+    /// Consumes a run of digits in `radix`, which may be split up by single `_` separators,
+    /// and gives the digits without them.
+    fn lex_digits(&mut self, radix: u32) -> Result<String, String> {
+        let mut digits = String::new();
+        let mut separated = false;
+
         while let Some(c) = self.peek() {
-            if c.is_alphanumeric() || c == '_' || c == '$' {
-                word.push(self.consume().unwrap());
+            if c == '_' {
+                if digits.is_empty() || separated {
+                    return Err("Numeric separators are only allowed between digits".into());
+                }
+                separated = true;
+            } else if c.is_digit(radix) {
+                digits.push(c);
+                separated = false;
             } else {
                 break;
             }
+            self.consume();
         }
 
-        if let Some(token) = keyword_map.get(word.as_str()) {
-            token.clone()
-        } else {
-            Token::Identifier(word)
+        if separated {
+            return Err("Numeric separators are only allowed between digits".into());
         }
+        Ok(digits)
     }
 
+    /// Lexes a number or BigInt literal: decimal with an optional fraction and exponent, or an
+    /// integer with a `0x`, `0o` or `0b` prefix. Either kind may use `_` separators, and an
+    /// integer followed by `n` is a BigInt.
     fn lex_numeric(&mut self) -> Token {
-        let mut number = String::new();
+        let number = match (self.peek(), self.peek_ahead(1)) {
+            (Some('0'), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B')) => self.lex_prefixed(),
+            _ => self.lex_decimal()
+        };
+
+        // `3in`, `0b12` or `1.2.3` would otherwise read as two tokens. Whatever is wrong, the
+        // rest of the literal goes with it, so it's only reported once. A `.` that isn't
+        // followed by a digit is a property access, as in `1.5.toString()`.
+        let is_part = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+        let second_point = self.peek() == Some('.') && self.peek_ahead(1).is_some_and(|c| c.is_ascii_digit());
+        let number = match (number, self.peek()) {
+            (Ok(_), _) if second_point => Err("Malformed number literal".into()),
+            (Ok(_), Some(c)) if c.is_ascii_digit() => Err(format!("Invalid digit '{c}' in numeric literal")),
+            (Ok(_), Some(c)) if is_part(c) => Err("Identifier starts immediately after numeric literal".into()),
+            (number, _) => number
+        };
+        if number.is_err() {
+            self.consume_while(|c| is_part(c) || c == '.');
+        }
+
+        number.unwrap_or_else(Token::Error)
+    }
+
+    fn lex_prefixed(&mut self) -> Result<Token, String> {
+        self.consume();
+        let (radix, name) = match self.consume() {
+            Some('x' | 'X') => (16, "hexadecimal"),
+            Some('o' | 'O') => (8, "octal"),
+            _ => (2, "binary")
+        };
+
+        if self.peek() == Some('_') {
+            return Err("Numeric separators are only allowed between digits".into());
+        }
+        let digits = self.lex_digits(radix)?;
+        if digits.is_empty() {
+            return Err(format!("Expected {name} digits"));
+        }
 
-        while self.peek().is_some() && (self.peek().unwrap().is_digit(10) || self.peek().unwrap() == '.') {
-            number.push(self.consume().unwrap());
+        if self.peek() == Some('n') {
+            self.consume();
+            return Ok(Token::BigInt(BigInt::parse_bytes(digits.as_bytes(), radix).unwrap()));
         }
 
-        Token::Number(number.parse::<f64>().expect("Invalid number"))
+        let value = digits.chars().fold(0., |value, digit| value * radix as f64 + digit.to_digit(radix).unwrap() as f64);
+        Ok(Token::Number(value))
     }
 
-    fn lex_literal(&mut self) -> Token {
+    fn lex_decimal(&mut self) -> Result<Token, String> {
+        let integer = self.lex_digits(10)?;
+        if integer.len() > 1 && integer.starts_with('0') {
+            return Err("Decimals with leading zeros are not allowed".into());
+        }
+
+        let mut fraction = None;
+        if self.peek() == Some('.') {
+            self.consume();
+            if self.peek() == Some('_') {
+                return Err("Numeric separators are only allowed between digits".into());
+            }
+            fraction = Some(self.lex_digits(10)?);
+        }
+
+        let mut exponent = None;
+        if let Some('e' | 'E') = self.peek() {
+            self.consume();
+            let sign = match self.peek() {
+                Some(sign @ ('+' | '-')) => {
+                    self.consume();
+                    sign.to_string()
+                },
+                _ => String::new()
+            };
+            if self.peek() == Some('_') {
+                return Err("Numeric separators are only allowed between digits".into());
+            }
+            let digits = self.lex_digits(10)?;
+            if digits.is_empty() {
+                return Err("Expected digits in exponent".into());
+            }
+            exponent = Some(sign + &digits);
+        }
+
+        if self.peek() == Some('n') {
+            self.consume();
+            if fraction.is_some() || exponent.is_some() {
+                return Err("BigInt literals can't have a fraction or exponent".into());
+            }
+            return Ok(Token::BigInt(integer.parse().unwrap()));
+        }
+
+        let text = format!("{}.{}e{}",
+            if integer.is_empty() { "0" } else { &integer },
+            fraction.filter(|fraction| !fraction.is_empty()).unwrap_or_else(|| "0".into()),
+            exponent.unwrap_or_else(|| "0".into()));
+        Ok(Token::Number(text.parse().unwrap()))
+    }
+
+    /// Lexes a string literal delimited by `quote`, applying escapes. Like JS, it can't run
+    /// past the end of the line, except through a line continuation.
+    fn lex_string(&mut self, quote: char) -> Token {
         self.consume(); // Consume opening quote.
-        let mut literal = String::new();
+        let mut units = Vec::new();
+        let mut invalid = None;
+
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.consume();
+                    break;
+                },
+                None | Some('\n' | '\r') => return Token::Error("Unterminated string literal".into()),
+                Some('\\') => {
+                    self.consume();
+                    // Keep going to the closing quote, so the error covers the whole string.
+                    match self.lex_escape() {
+                        Ok(escaped) => units.extend(escaped),
+                        Err(message) => invalid = invalid.or(Some(message))
+                    }
+                },
+                Some(c) => {
+                    self.consume();
+                    units.extend(c.encode_utf16(&mut [0; 2]).iter());
+                }
+            }
+        }
+
+        match invalid {
+            Some(message) => Token::Error(message),
+            None => Token::StringLiteral(String::from_utf16_lossy(&units))
+        }
+    }
 
-        while self.peek() != Some('"') {
-            literal.push(self.consume().unwrap());
+    /// Consumes exactly `count` hex digits, or as many as there are before something else.
+    fn lex_hex_digits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            let digit = self.peek()?.to_digit(16)?;
+            self.consume();
+            value = value * 16 + digit;
         }
-        self.consume(); // And closing quote.
 
-        Token::StringLiteral(literal)
+        Some(value)
+    }
+
+    /// The UTF-16 code units an escape sequence stands for, with the backslash already consumed.
+    /// `\u` escapes give code units rather than characters, so that two of them can spell out a
+    /// surrogate pair. A line continuation stands for nothing at all.
+    fn lex_escape(&mut self) -> Result<Vec<u16>, String> {
+        let c = match self.consume() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('v') => '\u{b}',
+            Some('0') if !matches!(self.peek(), Some('0'..='9')) => '\0',
+            Some('0'..='9') => return Err("Octal escape sequences are not allowed".into()),
+            Some('x') => {
+                let unit = self.lex_hex_digits(2).ok_or("Invalid hexadecimal escape sequence")?;
+                return Ok(vec![unit as u16]);
+            },
+            Some('u') => {
+                let invalid = || "Invalid Unicode escape sequence".to_string();
+                let code = if self.peek() == Some('{') {
+                    self.consume();
+                    let mut code: u32 = 0;
+                    let mut digits = 0;
+                    while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+                        self.consume();
+                        code = code.saturating_mul(16).saturating_add(digit);
+                        digits += 1;
+                    }
+                    if digits == 0 || code > 0x10FFFF || self.peek() != Some('}') {
+                        return Err(invalid());
+                    }
+                    self.consume();
+                    code
+                } else {
+                    self.lex_hex_digits(4).ok_or_else(invalid)?
+                };
+
+                return Ok(match char::from_u32(code) {
+                    Some(c) => c.encode_utf16(&mut [0; 2]).to_vec(),
+                    None => vec![code as u16]
+                });
+            },
+            Some('\r') => {
+                if self.peek() == Some('\n') {
+                    self.consume();
+                }
+                return Ok(Vec::new());
+            },
+            Some('\n' | '\u{2028}' | '\u{2029}') => return Ok(Vec::new()),
+            Some(c) => c,
+            None => return Err("Unterminated escape sequence".into())
+        };
+
+        Ok(c.encode_utf16(&mut [0; 2]).to_vec())
+    }
+
+    /// Lexes template text up to and including the next `${` or closing backtick, just after
+    /// the opening backtick or the `}` that ended a substitution, giving the cooked text, the
+    /// raw text and whether the template ended, or `None` if the source ends first. Line breaks
+    /// are normalised to `\n` in both.
+    fn lex_template(&mut self) -> Option<(Option<String>, String, bool)> {
+        let mut cooked = Some(Vec::new());
+        let mut raw = String::new();
+
+        let tail = loop {
+            match self.peek() {
+                None => return None,
+                Some('`') => {
+                    self.consume();
+                    break true;
+                },
+                Some('$') if self.peek_ahead(1) == Some('{') => {
+                    self.consume();
+                    self.consume();
+                    self.templates.push(self.braces);
+                    break false;
+                },
+                Some('\\') => {
+                    let start = self.offset;
+                    self.consume();
+                    let escaped = self.lex_escape();
+
+                    let written = &self.source[start..self.offset];
+                    raw.push_str(&written.replace("\r\n", "\n").replace('\r', "\n"));
+                    match (escaped, &mut cooked) {
+                        (Ok(units), Some(cooked)) => cooked.extend(units),
+                        (Err(_), _) => cooked = None,
+                        _ => {}
+                    }
+                },
+                Some(c) => {
+                    self.consume();
+                    let c = if c == '\r' {
+                        if self.peek() == Some('\n') {
+                            self.consume();
+                        }
+                        '\n'
+                    } else {
+                        c
+                    };
+
+                    raw.push(c);
+                    if let Some(cooked) = &mut cooked {
+                        cooked.extend(c.encode_utf16(&mut [0; 2]).iter());
+                    }
+                }
+            }
+        };
+
+        Some((cooked.map(|units| String::from_utf16_lossy(&units)), raw, tail))
     }
 
-    pub fn lex(&mut self) -> Vec<Token> {
+    pub fn lex(&mut self) -> Vec<SpannedToken> {
         let mut tokens = vec![];
 
+        // A `#!` line lets scripts be run directly, and is only allowed as the very first thing.
+        if self.source.starts_with("#!") {
+            self.consume_while(|c| !is_line_terminator(c));
+        }
+
         while let Some(c) = self.peek() {
+            let (start, line, column) = (self.offset, self.line, self.column);
 
             let token: Option<Token> = match c {
-                'a'..='z' | 'A'..='Z' | '_' => Some(self.lex_identifier()),
+                c if c.is_alphabetic() || c == '_' || c == '$' => Some(self.lex_identifier()),
                 '0'..='9' => Some(self.lex_numeric()),
-                '"' => Some(self.lex_literal()),
-                ' ' | '\n' | '\t' => { self.consume(); None },
+                '"' | '\'' => Some(self.lex_string(c)),
+                '`' => {
+                    self.consume();
+                    Some(match self.lex_template() {
+                        Some((cooked, raw, tail)) => Token::Template { cooked, raw, tail },
+                        None => Token::Error("Unterminated template literal".into())
+                    })
+                },
+                c if is_whitespace(c) => { self.consume(); None },
+                c if is_line_terminator(c) => {
+                    self.consume();
+                    self.newline = true;
+                    None
+                },
                 '(' => { self.consume(); Some(Token::LeftParen) },
                 ')' => { self.consume(); Some(Token::RightParen) },
-                '{' => { self.consume(); Some(Token::LeftBrace) },
-                '}' => { self.consume(); Some(Token::RightBrace) },
+                '{' => {
+                    self.consume();
+                    self.braces += 1;
+                    Some(Token::LeftBrace)
+                },
+                '}' => {
+                    self.consume();
+                    if self.templates.last() == Some(&self.braces) {
+                        self.templates.pop();
+                        Some(match self.lex_template() {
+                            Some((cooked, raw, tail)) => Token::TemplateContinuation { cooked, raw, tail },
+                            None => Token::Error("Unterminated template literal".into())
+                        })
+                    } else {
+                        self.braces = self.braces.saturating_sub(1);
+                        Some(Token::RightBrace)
+                    }
+                },
                 ';' => { self.consume(); Some(Token::Semicolon) },
                 ':' => { self.consume(); Some(Token::Colon) },
                 ',' => { self.consume(); Some(Token::Comma) },
+                '.' if self.peek_ahead(1).is_some_and(|c| c.is_ascii_digit()) => Some(self.lex_numeric()),
                 '.' => { self.consume(); Some(Token::Dot) },
                 '[' => { self.consume(); Some(Token::LeftBracket) },
                 ']' => { self.consume(); Some(Token::RightBracket) },
-                '/' => {  // TODO: Implement multiline comments.
+                '/' => {
                     self.consume();
                     if self.peek() == Some('/') {
-                        self.consume_while(|c| c != '\n');
+                        self.consume_while(|c| !is_line_terminator(c));
                         None
+                    } else if self.peek() == Some('*') {
+                        self.consume();
+                        if self.skip_block_comment() {
+                            None
+                        } else {
+                            Some(Token::Error("Unterminated comment".into()))
+                        }
                     } else if self.peek() == Some('=') {
+                        self.consume();
                         Some(Token::SlashEqual)
                     } else {
                         Some(Token::Slash)
@@ -180,7 +546,12 @@ impl Lexer {
                     self.consume();
                     if self.peek() == Some('=') {
                         self.consume();
-                        Some(Token::EqualEqual)
+                        if self.peek() == Some('=') {
+                            self.consume();
+                            Some(Token::EqualEqualEqual)
+                        } else {
+                            Some(Token::EqualEqual)
+                        }
                     } else {
                         Some(Token::Equal)
                     }
@@ -189,7 +560,12 @@ impl Lexer {
                     self.consume();
                     if self.peek() == Some('=') {
                         self.consume();
-                        Some(Token::BangEqual)
+                        if self.peek() == Some('=') {
+                            self.consume();
+                            Some(Token::BangEqualEqual)
+                        } else {
+                            Some(Token::BangEqual)
+                        }
                     } else {
                         Some(Token::Bang)
                     }
@@ -216,7 +592,12 @@ impl Lexer {
                     self.consume();
                     if self.peek() == Some('&') {
                         self.consume();
-                        Some(Token::AmpAmp)
+                        if self.peek() == Some('=') {
+                            self.consume();
+                            Some(Token::AmpAmpEqual)
+                        } else {
+                            Some(Token::AmpAmp)
+                        }
                     } else {
                         Some(Token::Amp)
                     }
@@ -225,18 +606,47 @@ impl Lexer {
                     self.consume();
                     if self.peek() == Some('|') {
                         self.consume();
-                        Some(Token::PipePipe)
+                        if self.peek() == Some('=') {
+                            self.consume();
+                            Some(Token::PipePipeEqual)
+                        } else {
+                            Some(Token::PipePipe)
+                        }
                     } else {
                         Some(Token::Pipe)
                     }
                 }
-                _ => { self.consume(); None }
+                '?' => {
+                    self.consume();
+                    if self.peek() == Some('?') {
+                        self.consume();
+                        if self.peek() == Some('=') {
+                            self.consume();
+                            Some(Token::QuestionQuestionEqual)
+                        } else {
+                            Some(Token::QuestionQuestion)
+                        }
+                    } else {
+                        Some(Token::Question)
+                    }
+                }
+                _ => {
+                    self.consume();
+                    Some(Token::Error(format!("Unexpected character '{c}'")))
+                }
             };
 
-            token.map(|t| tokens.push(t));
+            if let Some(token) = token {
+                tokens.push(SpannedToken { token, span: self.span_from(start, line, column), newline_before: self.newline });
+                self.newline = false;
+            }
         }
 
-        tokens.push(Token::EOF);
+        tokens.push(SpannedToken {
+            token: Token::EOF,
+            span: self.span_from(self.offset, self.line, self.column),
+            newline_before: self.newline,
+        });
         tokens
     }
 }
\ No newline at end of file
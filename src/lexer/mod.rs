@@ -1,5 +1,8 @@
+#[allow(clippy::module_inception)]
 mod lexer;
+mod span;
 mod token;
 
 pub use lexer::*;
-pub use token::*;
\ No newline at end of file
+pub use span::*;
+pub use token::*;
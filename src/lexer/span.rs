@@ -0,0 +1,60 @@
+use std::fmt;
+use super::lexer::is_line_terminator;
+
+/// A region of source text. `start`/`end` are byte offsets into the source,
+/// `line`/`column` are the 1-based position of `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self { start, end, line, column }
+    }
+
+    /// Span covering both `self` and `other`, keeping the position of whichever starts first.
+    pub fn to(self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start { (self, other) } else { (other, self) };
+
+        Span {
+            start: first.start,
+            end: first.end.max(last.end),
+            line: first.line,
+            column: first.column,
+        }
+    }
+
+    /// Renders the source line containing this span with a caret underline, e.g.
+    ///
+    /// ```text
+    ///   |
+    /// 3 | let x = 5 y;
+    ///   |           ^
+    /// ```
+    pub fn snippet(&self, source: &str) -> String {
+        let start = self.start.min(source.len());
+        let line_start = source[..start].char_indices().rev()
+            .find(|&(_, c)| is_line_terminator(c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let line_end = source[start..].find(is_line_terminator).map_or(source.len(), |i| start + i);
+        let line = &source[line_start..line_end];
+
+        let gutter = self.line.to_string();
+        let pad = " ".repeat(gutter.len());
+        let indent = " ".repeat(source[line_start..start].chars().count());
+        let end = self.end.min(line_start + line.len()).max(start);
+        let width = source[start..end].chars().count().max(1);
+
+        format!("{pad} |\n{gutter} | {line}\n{pad} | {indent}{}", "^".repeat(width))
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
@@ -1,10 +1,20 @@
-
+use num_bigint::BigInt;
+use crate::lexer::Span;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Identifier(String),
     StringLiteral(String),
     Number(f64),
+    /// `123n`
+    BigInt(BigInt),
+    /// The start of a template literal, from its opening backtick up to the first `${`, or the
+    /// whole template if `tail` is set. `cooked` is the text with escapes applied, or `None` if
+    /// one was invalid, which only tagged templates allow; `raw` is the text as written.
+    Template { cooked: Option<String>, raw: String, tail: bool },
+    /// The rest of a template literal after a substitution, from its closing `}` up to the
+    /// next `${` or closing backtick.
+    TemplateContinuation { cooked: Option<String>, raw: String, tail: bool },
 
     Null,
     Undefined,
@@ -16,6 +26,9 @@ pub enum Token {
     Continue, Break,
     Return,
     Function,
+    Try, Catch, Finally, Throw,
+    This, New,
+    Class, Extends, Super,
     True,
     False,
 
@@ -27,6 +40,7 @@ pub enum Token {
     Dot,
     Colon,
     Semicolon,
+    Question,
 
     // Operators
     Slash,
@@ -41,18 +55,118 @@ pub enum Token {
     StarEqual,
     Bang,
     BangEqual,
+    BangEqualEqual,
     Equal,
     EqualEqual,
+    EqualEqualEqual,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
     Amp,
     AmpAmp,
+    AmpAmpEqual,
     Pipe,
     PipePipe,
+    PipePipeEqual,
+    QuestionQuestion,
+    QuestionQuestionEqual,
     PlusPlus,
     MinusMinus,
 
+    /// Source the lexer couldn't make a token of, such as an unterminated string, with a
+    /// description of what's wrong. The parser reports these as syntax errors.
+    Error(String),
+
     EOF,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+    /// Whether a line break comes between this token and the one before it, which is where
+    /// the parser may insert a missing semicolon.
+    pub newline_before: bool,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Token::Identifier(name) => return write!(f, "identifier '{name}'"),
+            Token::StringLiteral(s) => return write!(f, "string \"{s}\""),
+            Token::Number(n) => return write!(f, "number {n}"),
+            Token::BigInt(n) => return write!(f, "bigint {n}n"),
+            Token::Template { .. } => return write!(f, "template literal"),
+            Token::EOF => return write!(f, "end of file"),
+            Token::Error(message) => return write!(f, "{message}"),
+            Token::Null => "null",
+            Token::Undefined => "undefined",
+            Token::Let => "let",
+            Token::Var => "var",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::For => "for",
+            Token::Do => "do",
+            Token::Continue => "continue",
+            Token::Break => "break",
+            Token::Return => "return",
+            Token::Function => "function",
+            Token::Try => "try",
+            Token::Catch => "catch",
+            Token::Finally => "finally",
+            Token::Throw => "throw",
+            Token::This => "this",
+            Token::New => "new",
+            Token::Class => "class",
+            Token::Extends => "extends",
+            Token::Super => "super",
+            Token::True => "true",
+            Token::False => "false",
+            Token::LeftParen => "(",
+            Token::RightParen => ")",
+            Token::LeftBrace => "{",
+            Token::RightBrace | Token::TemplateContinuation { .. } => "}",
+            Token::LeftBracket => "[",
+            Token::RightBracket => "]",
+            Token::Comma => ",",
+            Token::Dot => ".",
+            Token::Colon => ":",
+            Token::Semicolon => ";",
+            Token::Question => "?",
+            Token::Slash => "/",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Percent => "%",
+            Token::PercentEqual => "%=",
+            Token::SlashEqual => "/=",
+            Token::PlusEqual => "+=",
+            Token::MinusEqual => "-=",
+            Token::StarEqual => "*=",
+            Token::Bang => "!",
+            Token::BangEqual => "!=",
+            Token::BangEqualEqual => "!==",
+            Token::Equal => "=",
+            Token::EqualEqual => "==",
+            Token::EqualEqualEqual => "===",
+            Token::Greater => ">",
+            Token::GreaterEqual => ">=",
+            Token::Less => "<",
+            Token::LessEqual => "<=",
+            Token::Amp => "&",
+            Token::AmpAmp => "&&",
+            Token::AmpAmpEqual => "&&=",
+            Token::Pipe => "|",
+            Token::PipePipe => "||",
+            Token::PipePipeEqual => "||=",
+            Token::QuestionQuestion => "??",
+            Token::QuestionQuestionEqual => "??=",
+            Token::PlusPlus => "++",
+            Token::MinusMinus => "--",
+        };
+
+        write!(f, "'{text}'")
+    }
+}
@@ -0,0 +1,10 @@
+//! A small JavaScript engine: lexer, parser, AST optimizer, and both a tree-walking interpreter
+//! and a bytecode VM. Hosts embedding it will usually only need [`Runtime`].
+
+pub mod lexer;
+pub mod parser;
+pub mod optim;
+pub mod runtime;
+
+pub use parser::Literal;
+pub use runtime::{Error, Runtime};
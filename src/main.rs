@@ -1,35 +1,39 @@
 #![allow(dead_code, unused_imports)]
 
 use std::io::Write;
-use std::{env, fs};
+use std::{env, fs, process};
 use std::rc::Rc;
 use std::time::Instant;
 use env_logger::Builder;
 use log::info;
 
-mod lexer;
-mod parser;
-mod optim;
-mod runtime;
+mod repl;
 
-
-use crate::lexer::Lexer;
-use crate::parser::AST;
-use crate::runtime::{interpreter, Interpreter};
-use crate::optim::Optimizer;
+use tinyjs::lexer::Lexer;
+use tinyjs::parser::AST;
+use tinyjs::runtime::{Emitter, Interpreter, Vm};
+use tinyjs::optim::Optimizer;
+use crate::repl::Repl;
 
 enum Mode {
     File(String),
     Interactive
 }
 
+enum Engine {
+    Interpreter,
+    Vm
+}
+
 struct Config {
-    mode: Mode
+    mode: Mode,
+    engine: Engine
 }
 
 fn main() {
     Builder::new()
         .filter(None, log::LevelFilter::Trace)
+        .filter(Some("rustyline"), log::LevelFilter::Warn)
         .format(|buf, record| {
             writeln!(
                 buf,
@@ -41,36 +45,69 @@ fn main() {
         })
         .init();
 
-    let args: Vec<String> = env::args().collect();
-
-    let mode = if args.len() < 2 {
-        Mode::Interactive
-    } else {
-        Mode::File(args[1].clone())
-    };
+    let mut mode = Mode::Interactive;
+    let mut engine = Engine::Interpreter;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--vm" => engine = Engine::Vm,
+            _ => mode = Mode::File(arg),
+        }
+    }
 
     let config = Rc::new(Config {
-        mode
+        mode,
+        engine
     });
 
     let contents = match &config.mode {
         Mode::File(f) => {
             fs::read_to_string(f).expect("Something went wrong reading the file")
         }
-        Mode::Interactive => "".into()
+        Mode::Interactive => {
+            if let Err(err) = Repl::new().run() {
+                eprintln!("REPL error: {err}");
+                process::exit(1);
+            }
+            return;
+        }
     };
 
     let tokens = Lexer::new(&contents).lex();
 
-    let ast = AST::new(tokens);
+    let ast = match AST::new(tokens) {
+        Ok(ast) => ast,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}\n", error.render(&contents));
+            }
+            eprintln!("Aborting due to {} syntax error(s).", errors.len());
+            process::exit(1);
+        }
+    };
 
     let mut optim = Optimizer::new(ast);
     let ast = optim.optimize();
 
     // println!("{:#?}", ast);
 
-    let mut interpreter = Interpreter::new(ast);
     let start = Instant::now();
-    interpreter.run();
+    let result = match config.engine {
+        Engine::Interpreter => Interpreter::new(ast).run(),
+        Engine::Vm => {
+            let program = match Emitter::new().emit_program(&ast) {
+                Ok(program) => program,
+                Err(error) => {
+                    eprintln!("{}", error.render(&contents));
+                    process::exit(1);
+                }
+            };
+            Vm::new(program).run()
+        }
+    };
     info!("Execution finished in {:.2}ms.", start.elapsed().as_micros() as f64 / 1000.0);
+
+    if let Err(exception) = result {
+        eprintln!("{}", exception.render(&contents));
+        process::exit(1);
+    }
 }
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod optim;
 
 pub use optim::*;
\ No newline at end of file
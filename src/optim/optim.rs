@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use log::trace;
-use crate::parser::{BinaryOperator, Expression, Literal, Statement, UnaryOperator, AST};
+use crate::parser::{BinaryOperator, ClassMember, Expression, ExpressionKind, Literal, Statement, StatementKind, UnaryOperator, AST};
 
 #[derive(Clone)]
 #[derive(Debug)]
@@ -18,10 +18,6 @@ impl ConstVal {
             ConstVal::Boolean(b) => Literal::Boolean(b),
         }
     }
-
-    pub fn into_expression(self) -> Expression {
-        Expression::Literal(self.into_literal())
-    }
 }
 
 pub struct Optimizer {
@@ -31,7 +27,7 @@ pub struct Optimizer {
 }
 
 impl Optimizer {
-    pub(crate) fn new(ast: AST) -> Self {
+    pub fn new(ast: AST) -> Self {
         Self {
             ast,
             constants: vec![HashMap::new()],
@@ -87,203 +83,287 @@ impl Optimizer {
     }
 
     fn propagate_expression(&mut self, expr: Expression) -> Expression {
-        match expr {
-            Expression::Literal(l) => Expression::Literal(l),
-            Expression::Identifier(id) => {
+        let span = expr.span;
+        let kind = match expr.kind {
+            ExpressionKind::Literal(l) => ExpressionKind::Literal(l),
+            ExpressionKind::Identifier(id) => {
                 if let Some(saved_const) = self.get_constant(id.as_str()) {
                     trace!("Propagating constant: {id} = {saved_const:?}");
-                    saved_const.into_expression()
+                    ExpressionKind::Literal(saved_const.into_literal())
                 } else {
-                    Expression::Identifier(id)
+                    ExpressionKind::Identifier(id)
                 }
             }
-            Expression::Object { properties } => {
+            ExpressionKind::Object { properties } => {
                 let properties = properties.into_iter().map(|(k, v)| (k, self.propagate_expression(*v).into())).collect();
 
-                Expression::Object { properties }
+                ExpressionKind::Object { properties }
             },
-            Expression::Array { elements } => {
+            ExpressionKind::Array { elements } => {
                 let elements = elements.into_iter().map(|el| self.propagate_expression(*el).into()).collect();
 
-                Expression::Array { elements }
+                ExpressionKind::Array { elements }
+            },
+            ExpressionKind::Template { quasis, expressions } => {
+                let expressions = expressions.into_iter().map(|expr| self.propagate_expression(*expr).into()).collect();
+
+                ExpressionKind::Template { quasis, expressions }
             },
-            e @ Expression::Increment { .. } => e,
-            e @ Expression::Decrement { .. } => e,
-            Expression::BinaryOp { left, op, right } => {
-                Expression::BinaryOp { left: self.propagate_expression(*left).into(), op, right: self.propagate_expression(*right).into() }
+            e @ ExpressionKind::TemplateObject(_) => e,
+            e @ ExpressionKind::Increment { .. } => e,
+            e @ ExpressionKind::Decrement { .. } => e,
+            ExpressionKind::BinaryOp { left, op, right } => {
+                ExpressionKind::BinaryOp { left: self.propagate_expression(*left).into(), op, right: self.propagate_expression(*right).into() }
             },
-            Expression::UnaryOp { op, expr } => {
-                Expression::UnaryOp { op, expr: self.propagate_expression(*expr).into() }
+            ExpressionKind::UnaryOp { op, expr } => {
+                ExpressionKind::UnaryOp { op, expr: self.propagate_expression(*expr).into() }
             },
-            e @ Expression::FunctionCall { .. } => e,
-            Expression::Assignment { target, value } => {
-                if let Expression::Identifier(id) = *target.clone() {
-                    if let Some(_) = self.get_constant(id.as_str()) {
-                        trace!("Constant {id} changed. Invalidating.");
-                        self.remove_constant(id.as_str());
-                    }
+            ExpressionKind::Logical { left, op, right } if op.is_assignment() => {
+                if let ExpressionKind::Identifier(id) = left.kind.clone()
+                    && self.get_constant(id.as_str()).is_some() {
+                    trace!("Constant {id} may change. Invalidating.");
+                    self.remove_constant(id.as_str());
                 }
-                Expression::Assignment { target, value: self.propagate_expression(*value).into() }
+                ExpressionKind::Logical { left, op, right: self.propagate_expression(*right).into() }
             },
-            e @ Expression::Index { .. } => e,
-            e @ Expression::Property { .. } => e,
-        }
+            ExpressionKind::Logical { left, op, right } => {
+                ExpressionKind::Logical { left: self.propagate_expression(*left).into(), op, right: self.propagate_expression(*right).into() }
+            },
+            ExpressionKind::Conditional { condition, consequent, alternative } => ExpressionKind::Conditional {
+                condition: self.propagate_expression(*condition).into(),
+                consequent: self.propagate_expression(*consequent).into(),
+                alternative: self.propagate_expression(*alternative).into(),
+            },
+            e @ ExpressionKind::FunctionCall { .. } => e,
+            e @ ExpressionKind::New { .. } => e,
+            e @ ExpressionKind::This => e,
+            e @ ExpressionKind::SuperCall { .. } => e,
+            e @ ExpressionKind::SuperProperty { .. } => e,
+            ExpressionKind::Class { name, parent, constructor, members } => ExpressionKind::Class {
+                name,
+                parent: parent.map(|parent| self.propagate_expression(*parent).into()),
+                constructor: self.propagate_expression(*constructor).into(),
+                members: members.into_iter().map(|member| ClassMember { value: self.propagate_expression(*member.value).into(), ..member }).collect(),
+            },
+            ExpressionKind::Function { name, args, body } => {
+                ExpressionKind::Function { name, args, body: self.propagate_statement(*body).into() }
+            },
+            ExpressionKind::Assignment { target, value } => {
+                if let ExpressionKind::Identifier(id) = target.kind.clone()
+                    && self.get_constant(id.as_str()).is_some() {
+                    trace!("Constant {id} changed. Invalidating.");
+                    self.remove_constant(id.as_str());
+                }
+                ExpressionKind::Assignment { target, value: self.propagate_expression(*value).into() }
+            },
+            e @ ExpressionKind::Index { .. } => e,
+            e @ ExpressionKind::Property { .. } => e,
+        };
+
+        Expression::new(kind, span)
     }
 
     fn propagate_statement(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            Statement::Expression(ex) => {
-                Statement::Expression(self.propagate_expression(*ex).into()).into()
+        let span = stmt.span;
+        let kind = match stmt.kind {
+            StatementKind::Expression(ex) => {
+                StatementKind::Expression(self.propagate_expression(*ex).into())
             },
-            Statement::Return(ex) => {
-                let ret = Statement::Return(self.propagate_expression(*ex).into()).into();
-
-                ret
+            StatementKind::Return(ex) => {
+                StatementKind::Return(self.propagate_expression(*ex).into())
             },
-            Statement::Continue => Statement::Continue.into(),
-            Statement::Break => Statement::Break.into(),
-            Statement::If { condition, consequence, alternative } => {
+            StatementKind::Continue => StatementKind::Continue,
+            StatementKind::Break => StatementKind::Break,
+            StatementKind::If { condition, consequence, alternative } => {
                 let condition = self.propagate_expression(*condition);
                 let consequence = self.propagate_statement(*consequence);
                 let alternative = alternative.map(|alt| self.propagate_statement(*alt.clone()).into());
 
-                Statement::If { condition: condition.into(), consequence: consequence.into(), alternative }
+                StatementKind::If { condition: condition.into(), consequence: consequence.into(), alternative }
             },
-            Statement::While { condition, body } => {
+            StatementKind::While { condition, body } => {
                 let condition = self.propagate_expression(*condition);
                 let body = self.propagate_statement(*body).into();
 
-                Statement::While { condition: condition.into(), body }
+                StatementKind::While { condition: condition.into(), body }
             },
-            Statement::For { init, condition, update, body } => {
+            StatementKind::For { init, condition, update, body } => {
                 let init = init.map(|init| self.propagate_statement(*init.clone()).into());
                 let condition = condition.map(|condition| self.propagate_expression(*condition.clone()).into());
                 let update = update.map(|update| self.propagate_expression(*update.clone()).into());
                 let body = self.propagate_statement(*body).into();
 
-                Statement::For { init, condition, update, body }
+                StatementKind::For { init, condition, update, body }
             },
-            Statement::Function { name, args, body } => {
-                Statement::Function { name, args, body: self.propagate_statement(*body).into() }
+            StatementKind::Function { name, args, body } => {
+                StatementKind::Function { name, args, body: self.propagate_statement(*body).into() }
             },
-            Statement::Scope { statements } => {
+            StatementKind::Scope { statements } => {
                 self.enter();
                 let statements = statements.into_iter().map(|stmt| self.propagate_statement(stmt)).collect();
                 self.exit();
 
-                Statement::Scope { statements }.into()
+                StatementKind::Scope { statements }
             },
-            Statement::Let { name, value } => {
+            StatementKind::Let { name, value } => {
                 let expr = self.propagate_expression(*value);
-                match expr.clone() {
-                    Expression::Literal(l) => {
-                        match l {
-                            Literal::Number(n) => {
-                                self.mark_constant(name.clone(), ConstVal::Number(n));
-                            }
-                            Literal::String(s) => {
-                                self.mark_constant(name.clone(), ConstVal::StringLiteral(s));
-                            }
-                            Literal::Boolean(b) => {
-                                self.mark_constant(name.clone(), ConstVal::Boolean(b));
-                            }
-                            _ => {}
+                if let ExpressionKind::Literal(l) = expr.kind.clone() {
+                    match l {
+                        Literal::Number(n) => {
+                            self.mark_constant(name.clone(), ConstVal::Number(n));
                         }
-                    },
-                    _ => {}
-                };
+                        Literal::String(s) => {
+                            self.mark_constant(name.clone(), ConstVal::StringLiteral(s));
+                        }
+                        Literal::Boolean(b) => {
+                            self.mark_constant(name.clone(), ConstVal::Boolean(b));
+                        }
+                        _ => {}
+                    }
+                }
 
-                Statement::Let { name, value: expr.into() }
+                StatementKind::Let { name, value: expr.into() }
+            },
+            StatementKind::Throw(ex) => StatementKind::Throw(self.propagate_expression(*ex).into()),
+            StatementKind::Try { body, catch_param, handler, finalizer } => {
+                let body = self.propagate_statement(*body).into();
+                let handler = handler.map(|handler| self.propagate_statement(*handler).into());
+                let finalizer = finalizer.map(|finalizer| self.propagate_statement(*finalizer).into());
+
+                StatementKind::Try { body, catch_param, handler, finalizer }
             }
-        }
+        };
+
+        Statement::new(kind, span)
     }
 
     fn fold_statement(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            Statement::Expression(expr) => Statement::Expression(self.fold_expression(*expr).into()),
-            Statement::Return(expr) => Statement::Return(self.fold_expression(*expr).into()),
-            Statement::Continue => Statement::Continue,
-            Statement::Break => Statement::Break,
-            Statement::If { condition, consequence, alternative } => Statement::If { condition: self.fold_expression(*condition).into(), consequence: self.fold_statement(*consequence).into(), alternative: alternative.map(|alt| self.fold_statement(*alt.clone()).into()) },
-            Statement::While { condition, body } => Statement::While { condition: self.fold_expression(*condition).into(), body: self.fold_statement(*body).into() },
-            Statement::For { init, condition, update, body } => {
-                Statement::For {
+        let span = stmt.span;
+        let kind = match stmt.kind {
+            StatementKind::Expression(expr) => StatementKind::Expression(self.fold_expression(*expr).into()),
+            StatementKind::Return(expr) => StatementKind::Return(self.fold_expression(*expr).into()),
+            StatementKind::Continue => StatementKind::Continue,
+            StatementKind::Break => StatementKind::Break,
+            StatementKind::If { condition, consequence, alternative } => StatementKind::If { condition: self.fold_expression(*condition).into(), consequence: self.fold_statement(*consequence).into(), alternative: alternative.map(|alt| self.fold_statement(*alt.clone()).into()) },
+            StatementKind::While { condition, body } => StatementKind::While { condition: self.fold_expression(*condition).into(), body: self.fold_statement(*body).into() },
+            StatementKind::For { init, condition, update, body } => {
+                StatementKind::For {
                     init: init.map(|init| self.fold_statement(*init.clone()).into()),
                     condition: condition.map(|condition| self.fold_expression(*condition.clone()).into()),
                     update: update.map(|update| self.fold_expression(*update.clone()).into()),
                     body: self.fold_statement(*body).into(),
                 }
             }
-            Statement::Function { name, args, body } => Statement::Function { name, args, body: self.fold_statement(*body).into() },
-            Statement::Scope { statements } => {
+            StatementKind::Function { name, args, body } => StatementKind::Function { name, args, body: self.fold_statement(*body).into() },
+            StatementKind::Scope { statements } => {
                 let statements = statements.into_iter().map(|stmt| self.fold_statement(stmt)).collect();
-                Statement::Scope { statements }.into()
+                StatementKind::Scope { statements }
             }
-            Statement::Let { name, value } => Statement::Let { name, value: self.fold_expression(*value).into() },
-        }
+            StatementKind::Let { name, value } => StatementKind::Let { name, value: self.fold_expression(*value).into() },
+            StatementKind::Throw(expr) => StatementKind::Throw(self.fold_expression(*expr).into()),
+            StatementKind::Try { body, catch_param, handler, finalizer } => StatementKind::Try {
+                body: self.fold_statement(*body).into(),
+                catch_param,
+                handler: handler.map(|handler| self.fold_statement(*handler).into()),
+                finalizer: finalizer.map(|finalizer| self.fold_statement(*finalizer).into()),
+            },
+        };
+
+        Statement::new(kind, span)
     }
 
     fn fold_expression(&mut self, expr: Expression) -> Expression {
-        match expr {
-            e @  Expression::Literal(..) => e,
-            e @ Expression::Identifier(..) => e,
-            Expression::Object { properties } => {
-                Expression::Object {
+        let span = expr.span;
+        let kind = match expr.kind {
+            e @  ExpressionKind::Literal(..) => e,
+            e @ ExpressionKind::Identifier(..) => e,
+            ExpressionKind::Object { properties } => {
+                ExpressionKind::Object {
                     properties: properties.into_iter().map(|(k, v)| (k, self.fold_expression(*v).into())).collect(),
                 }
             },
-            e @ Expression::Increment { .. } => e,
-            e @ Expression::Decrement { .. } => e,
-            Expression::Array { elements } => Expression::Array { elements: elements.into_iter().map(|el| self.fold_expression(*el).into()).collect() },
-            Expression::BinaryOp { left, op, right } => {
-                match (*left.clone(), op.clone(), *right.clone()) {
-                    (Expression::Literal(Literal::Number(l)), BinaryOperator::Add, Expression::Literal(Literal::Number(r))) => {
+            ExpressionKind::Template { quasis, expressions } => ExpressionKind::Template {
+                quasis,
+                expressions: expressions.into_iter().map(|expr| self.fold_expression(*expr).into()).collect(),
+            },
+            e @ ExpressionKind::TemplateObject(_) => e,
+            e @ ExpressionKind::Increment { .. } => e,
+            e @ ExpressionKind::Decrement { .. } => e,
+            ExpressionKind::Array { elements } => ExpressionKind::Array { elements: elements.into_iter().map(|el| self.fold_expression(*el).into()).collect() },
+            ExpressionKind::BinaryOp { left, op, right } => {
+                match (left.kind.clone(), op.clone(), right.kind.clone()) {
+                    (ExpressionKind::Literal(Literal::Number(l)), BinaryOperator::Add, ExpressionKind::Literal(Literal::Number(r))) => {
                         trace!("Folding {l} + {r} into {}", l + r);
-                        Expression::Literal(Literal::Number(l + r))
+                        ExpressionKind::Literal(Literal::Number(l + r))
                     },
-                    (Expression::Literal(Literal::String(l)), BinaryOperator::Add, Expression::Literal(Literal::String(r))) => {
+                    (ExpressionKind::Literal(Literal::String(l)), BinaryOperator::Add, ExpressionKind::Literal(Literal::String(r))) => {
                         trace!("Folding '{l}' + '{r}' into '{}'", l.clone() + r.as_str());
-                        Expression::Literal(Literal::String(l.clone() + r.as_str()))
+                        ExpressionKind::Literal(Literal::String(l.clone() + r.as_str()))
                     },
-                    (Expression::Literal(Literal::Number(l)), BinaryOperator::Sub, Expression::Literal(Literal::Number(r))) => {
-                        trace!("Folding {l} - {r} into {}", l + r);
-                        Expression::Literal(Literal::Number(l - r))
+                    (ExpressionKind::Literal(Literal::Number(l)), BinaryOperator::Sub, ExpressionKind::Literal(Literal::Number(r))) => {
+                        trace!("Folding {l} - {r} into {}", l - r);
+                        ExpressionKind::Literal(Literal::Number(l - r))
                     },
-                    (Expression::Literal(Literal::Number(l)), BinaryOperator::Mul, Expression::Literal(Literal::Number(r))) => {
+                    (ExpressionKind::Literal(Literal::Number(l)), BinaryOperator::Mul, ExpressionKind::Literal(Literal::Number(r))) => {
                         trace!("Folding {l} * {r} into {}", l * r);
-                        Expression::Literal(Literal::Number(l * r))
+                        ExpressionKind::Literal(Literal::Number(l * r))
                     },
-                    (Expression::Literal(Literal::Number(l)), BinaryOperator::Div, Expression::Literal(Literal::Number(r))) => {
+                    (ExpressionKind::Literal(Literal::Number(l)), BinaryOperator::Div, ExpressionKind::Literal(Literal::Number(r))) => {
                         trace!("Folding {l} / {r} into {}", l / r);
-                        Expression::Literal(Literal::Number(l / r))
+                        ExpressionKind::Literal(Literal::Number(l / r))
                     },
-                    (Expression::Literal(Literal::Number(l)), BinaryOperator::Mod, Expression::Literal(Literal::Number(r))) => {
+                    (ExpressionKind::Literal(Literal::Number(l)), BinaryOperator::Mod, ExpressionKind::Literal(Literal::Number(r))) => {
                         trace!("Folding {l} % {r} into {}", l % r);
-                        Expression::Literal(Literal::Number(l % r))
+                        ExpressionKind::Literal(Literal::Number(l % r))
                     },
-                    _ => Expression::BinaryOp { left: self.fold_expression(*left.clone()).into(), op: op.clone(), right: self.fold_expression(*right.clone()).into() },
+                    _ => ExpressionKind::BinaryOp { left: self.fold_expression(*left.clone()).into(), op: op.clone(), right: self.fold_expression(*right.clone()).into() },
                 }
             },
-            Expression::UnaryOp { op, expr } => {
-                match (op.clone(), *expr.clone()) {
-                    (UnaryOperator::Negate, Expression::Literal(Literal::Number(n))) => {
+            ExpressionKind::UnaryOp { op, expr } => {
+                match (op.clone(), expr.kind.clone()) {
+                    (UnaryOperator::Negate, ExpressionKind::Literal(Literal::Number(n))) => {
                         trace!("Folding -{n} into {}", -n);
-                        Expression::Literal(Literal::Number(-n))
+                        ExpressionKind::Literal(Literal::Number(-n))
                     },
-                    (UnaryOperator::Not, Expression::Literal(Literal::Boolean(b))) => {
+                    (UnaryOperator::Not, ExpressionKind::Literal(Literal::Boolean(b))) => {
                         trace!("Folding !{b} into {}", !b);
-                        Expression::Literal(Literal::Boolean(!b))
+                        ExpressionKind::Literal(Literal::Boolean(!b))
                     },
-                    _ => Expression::UnaryOp { op: op.clone(), expr: self.fold_expression(*expr.clone()).into()}
+                    _ => ExpressionKind::UnaryOp { op: op.clone(), expr: self.fold_expression(*expr.clone()).into()}
                 }
             },
-            Expression::FunctionCall { callee, args } => {
-                Expression::FunctionCall { callee: self.fold_expression(*callee).into(), args: args.into_iter().map(|arg| self.fold_expression(*arg).into()).collect() }
+            ExpressionKind::Logical { left, op, right } => {
+                ExpressionKind::Logical { left: self.fold_expression(*left).into(), op, right: self.fold_expression(*right).into() }
             },
-            Expression::Assignment { target, value } => Expression::Assignment { target, value: self.fold_expression(*value).into() },
-            Expression::Index { target, index } => Expression::Index { target, index: self.fold_expression(*index).into() },
-            e @ Expression::Property { .. } => e,
-        }
+            ExpressionKind::Conditional { condition, consequent, alternative } => ExpressionKind::Conditional {
+                condition: self.fold_expression(*condition).into(),
+                consequent: self.fold_expression(*consequent).into(),
+                alternative: self.fold_expression(*alternative).into(),
+            },
+            ExpressionKind::FunctionCall { callee, args } => {
+                ExpressionKind::FunctionCall { callee: self.fold_expression(*callee).into(), args: args.into_iter().map(|arg| self.fold_expression(*arg).into()).collect() }
+            },
+            ExpressionKind::New { callee, args } => {
+                ExpressionKind::New { callee: self.fold_expression(*callee).into(), args: args.into_iter().map(|arg| self.fold_expression(*arg).into()).collect() }
+            },
+            e @ ExpressionKind::This => e,
+            ExpressionKind::SuperCall { args } => ExpressionKind::SuperCall {
+                args: args.map(|args| args.into_iter().map(|arg| self.fold_expression(*arg).into()).collect()),
+            },
+            e @ ExpressionKind::SuperProperty { .. } => e,
+            ExpressionKind::Class { name, parent, constructor, members } => ExpressionKind::Class {
+                name,
+                parent: parent.map(|parent| self.fold_expression(*parent).into()),
+                constructor: self.fold_expression(*constructor).into(),
+                members: members.into_iter().map(|member| ClassMember { value: self.fold_expression(*member.value).into(), ..member }).collect(),
+            },
+            ExpressionKind::Assignment { target, value } => ExpressionKind::Assignment { target, value: self.fold_expression(*value).into() },
+            ExpressionKind::Index { target, index } => ExpressionKind::Index { target, index: self.fold_expression(*index).into() },
+            e @ ExpressionKind::Property { .. } => e,
+            ExpressionKind::Function { name, args, body } => ExpressionKind::Function { name, args, body: self.fold_statement(*body).into() },
+        };
+
+        Expression::new(kind, span)
     }
 
     fn constant_folding(&mut self) {
@@ -296,11 +376,11 @@ impl Optimizer {
     #[allow(dead_code, unused_variables)]
     fn valid_loop_body(&self, body: Statement) -> bool {
         // Zero vars inside body, range known AOT
-        match body {
-            Statement::While { condition, .. } => {
+        match body.kind {
+            StatementKind::While { condition, .. } => {
                 false
             },
-            Statement::For { init, condition, update, .. } => {
+            StatementKind::For { init, condition, update, .. } => {
                 false
             },
             _ => panic!("valid_loop_body called on non-loop.")
@@ -310,8 +390,8 @@ impl Optimizer {
     #[allow(dead_code, unused_variables)]
     fn unroll_for(&self, for_stmt: Statement) -> Vec<Statement> {
 
-        let (init, condition, update) = match for_stmt {
-            Statement::For { init, condition, update, .. } => (init, condition, update),
+        let (init, condition, update) = match for_stmt.kind {
+            StatementKind::For { init, condition, update, .. } => (init, condition, update),
             _ => panic!("unroll_for called on non-for.")
         };
 
@@ -319,16 +399,16 @@ impl Optimizer {
         let condition = *condition.unwrap();
         let update = *update.unwrap();
 
-        let (var_name, var_value) = match init {
-            Statement::Let { name, value } => (name, *value),
+        let (var_name, var_value) = match init.kind {
+            StatementKind::Let { name, value } => (name, *value),
             _ => panic!("Unable to unroll for loop with non-let init.")
         };
 
         let _finished = |var_name: String, var_val: Literal, cond: Expression| {
-            match cond {
-                Expression::BinaryOp { left, op, right } => {
-                    let var = match *left {
-                        Expression::Identifier(id) => id,
+            match cond.kind {
+                ExpressionKind::BinaryOp { left, op, right } => {
+                    let var = match left.kind {
+                        ExpressionKind::Identifier(id) => id,
                         _ => panic!("Unable to unroll for loop with non-identifier condition left.")
                     };
 
@@ -336,17 +416,17 @@ impl Optimizer {
                         panic!("Unable to unroll for loop with non-matching left variable name.");
                     }
 
-                    match (var_val, op, *right) {
-                        (Literal::Number(val), BinaryOperator::LessThan, Expression::Literal(Literal::Number(n))) => {
+                    match (var_val, op, right.kind) {
+                        (Literal::Number(val), BinaryOperator::LessThan, ExpressionKind::Literal(Literal::Number(n))) => {
                             val < n
                         },
-                        (Literal::Number(val), BinaryOperator::LessThanOrEqual, Expression::Literal(Literal::Number(n))) => {
+                        (Literal::Number(val), BinaryOperator::LessThanOrEqual, ExpressionKind::Literal(Literal::Number(n))) => {
                             val <= n
                         },
-                        (Literal::Number(val), BinaryOperator::GreaterThan, Expression::Literal(Literal::Number(n))) => {
+                        (Literal::Number(val), BinaryOperator::GreaterThan, ExpressionKind::Literal(Literal::Number(n))) => {
                             val > n
                         },
-                        (Literal::Number(val), BinaryOperator::GreaterThanOrEqual, Expression::Literal(Literal::Number(n))) => {
+                        (Literal::Number(val), BinaryOperator::GreaterThanOrEqual, ExpressionKind::Literal(Literal::Number(n))) => {
                             val >= n
                         },
                         _ => panic!("Unable to unroll for loop with non binary-op condition.")
@@ -361,41 +441,54 @@ impl Optimizer {
         stmts
     }
 
+    #[allow(dead_code)]
     fn unroll_while(&self, _for_stmt: Statement) -> Vec<Statement> {
         vec![]
     }
 
+    #[allow(dead_code)]
     fn unroll_statement(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            Statement::While { condition, body } => {
-                let while_stmt = Statement::While { condition: condition.clone(), body: body.clone() };
+        let span = stmt.span;
+        let kind = match stmt.kind {
+            StatementKind::While { condition, body } => {
+                let while_stmt = Statement::new(StatementKind::While { condition, body }, span);
                 if self.valid_loop_body(while_stmt.clone()) {
-                    let stmts = self.unroll_while(while_stmt.clone());
-                    Statement::Scope { statements: stmts }.into()
+                    let stmts = self.unroll_while(while_stmt);
+                    StatementKind::Scope { statements: stmts }
                 } else {
-                    while_stmt.clone()
+                    while_stmt.kind
                 }
             },
-            Statement::For { init, condition, update, body } => {
-                let for_stmt = Statement::For { init: init.clone(), condition: condition.clone(), update: update.clone(), body: body.clone() };
+            StatementKind::For { init, condition, update, body } => {
+                let for_stmt = Statement::new(StatementKind::For { init, condition, update, body }, span);
                 if self.valid_loop_body(for_stmt.clone()) {
-                    let stmts = self.unroll_for(for_stmt.clone());
-                    Statement::Scope { statements: stmts }.into()
+                    let stmts = self.unroll_for(for_stmt);
+                    StatementKind::Scope { statements: stmts }
                 } else {
-                    for_stmt.clone()
+                    for_stmt.kind
                 }
             }
-            e @ Statement::Expression(_) => e,
-            e @ Statement::Return(_) => e,
-            e @ Statement::Continue => e,
-            e @ Statement::Break => e,
-            Statement::If { condition, consequence, alternative } => Statement::If { condition, consequence: self.unroll_statement(*consequence).into(), alternative: alternative.map(|alt| self.unroll_statement(*alt.clone()).into()) },
-            Statement::Function { name, args, body } => Statement::Function { name, args, body: self.unroll_statement(*body).into() },
-            Statement::Scope { statements } => Statement::Scope { statements: statements.into_iter().map(|stmt| self.unroll_statement(stmt)).collect() },
-            e @ Statement::Let { .. } => e,
-        }
+            e @ StatementKind::Expression(_) => e,
+            e @ StatementKind::Return(_) => e,
+            e @ StatementKind::Continue => e,
+            e @ StatementKind::Break => e,
+            StatementKind::If { condition, consequence, alternative } => StatementKind::If { condition, consequence: self.unroll_statement(*consequence).into(), alternative: alternative.map(|alt| self.unroll_statement(*alt.clone()).into()) },
+            StatementKind::Function { name, args, body } => StatementKind::Function { name, args, body: self.unroll_statement(*body).into() },
+            StatementKind::Scope { statements } => StatementKind::Scope { statements: statements.into_iter().map(|stmt| self.unroll_statement(stmt)).collect() },
+            e @ StatementKind::Let { .. } => e,
+            e @ StatementKind::Throw(_) => e,
+            StatementKind::Try { body, catch_param, handler, finalizer } => StatementKind::Try {
+                body: self.unroll_statement(*body).into(),
+                catch_param,
+                handler: handler.map(|handler| self.unroll_statement(*handler).into()),
+                finalizer: finalizer.map(|finalizer| self.unroll_statement(*finalizer).into()),
+            },
+        };
+
+        Statement::new(kind, span)
     }
 
+    #[allow(dead_code)]
     fn loop_unrolling(&mut self) {
         // Conditions
         // - Loop bounds and increment are known AOT
@@ -1,7 +1,10 @@
 use std::cell::RefCell;
 use std::rc::Rc;
-use crate::lexer::Token;
+use num_bigint::{BigInt, Sign};
+use crate::lexer::{Span, SpannedToken};
+use crate::parser::ParseError;
 use crate::parser::parser::Parser;
+use crate::runtime::{Args, Context, Environment, HostObject};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BinaryOperator {
@@ -9,36 +12,99 @@ pub enum BinaryOperator {
     Sub,
     Mul,
     Div,
-    BinaryAnd,
-    BinaryOr,
     GreaterThan,
     LessThan,
     GreaterThanOrEqual,
     LessThanOrEqual,
+    /// `==`, which converts its operands to a common type before comparing them.
     Equal,
     NotEqual,
+    /// `===`, which is `Literal`'s `PartialEq`.
+    StrictEqual,
+    StrictNotEqual,
     Mod,
     PlusEqual,
     MinusEqual,
     MulEqual,
-    DivEqual
+    DivEqual,
+    ModEqual
+}
+
+impl BinaryOperator {
+    /// Compound assignments such as `+=`.
+    pub fn is_assignment(&self) -> bool {
+        matches!(self, BinaryOperator::PlusEqual | BinaryOperator::MinusEqual | BinaryOperator::MulEqual
+            | BinaryOperator::DivEqual | BinaryOperator::ModEqual)
+    }
+}
+
+/// Operators that only evaluate their right operand when the left one doesn't decide the
+/// result, which is then whichever operand was evaluated last.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicalOperator {
+    And,
+    Or,
+    /// `??`, which only falls through to the right operand on `null` and `undefined`.
+    Nullish,
+    AndEqual,
+    OrEqual,
+    NullishEqual,
+}
+
+impl LogicalOperator {
+    /// Logical assignments such as `||=`, which only assign when they don't short-circuit.
+    pub fn is_assignment(&self) -> bool {
+        matches!(self, LogicalOperator::AndEqual | LogicalOperator::OrEqual | LogicalOperator::NullishEqual)
+    }
+
+    /// Whether `left` decides the result on its own, so the right operand is skipped.
+    pub fn short_circuits(&self, left: &Literal) -> bool {
+        match self {
+            LogicalOperator::And | LogicalOperator::AndEqual => !left.truthy(),
+            LogicalOperator::Or | LogicalOperator::OrEqual => left.truthy(),
+            LogicalOperator::Nullish | LogicalOperator::NullishEqual => {
+                !matches!(left, Literal::Null | Literal::Undefined)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOperator {
     Negate,
+    /// `+x`, which converts `x` to a number.
+    Plus,
     Not,
 }
 
+/// A native function's body. Gets the engine calling it along with the call's `this` and
+/// arguments, and returns `Err` to throw.
+pub type NativeFunc = Rc<dyn Fn(&mut dyn Context, Args) -> Result<Literal, Literal>>;
+
 #[derive(Clone)]
 pub struct NativeFn {
-    pub func: Rc<dyn Fn(Vec<Box<Literal>>) -> Box<Literal>>,
+    pub func: NativeFunc,
+    /// Own properties, such as a constructor's `prototype`.
+    pub object: ObjectRef,
     name: String,
 }
 
 impl NativeFn {
-    pub fn new(name: String, func: Rc<dyn Fn(Vec<Box<Literal>>) -> Box<Literal>>) -> Self {
-        Self { func, name }
+    /// A native function that ignores `this` and can't throw.
+    pub fn new(name: String, func: Rc<dyn Fn(Vec<Literal>) -> Box<Literal>>) -> Self {
+        Self::method(name, move |_, args| func(args))
+    }
+
+    /// A native function that gets `this` but can't throw.
+    pub fn method(name: String, func: impl Fn(Literal, Vec<Literal>) -> Box<Literal> + 'static) -> Self {
+        Self::host(name, Rc::new(move |_, args| {
+            let this = args.this.clone();
+            Ok(*func(this, args.into_values()))
+        }))
+    }
+
+    pub fn host(name: String, func: NativeFunc) -> Self {
+        Self { func, object: Object::new(Vec::new(), None), name }
     }
 }
 
@@ -54,42 +120,278 @@ impl PartialEq for NativeFn {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// An object's own properties, in insertion order.
+pub type Properties = Vec<(String, Box<Literal>)>;
+
+pub type ObjectRef = Rc<RefCell<Object>>;
+
+/// A JS object: its own properties, plus the object it inherits everything else from.
+#[derive(Default)]
+pub struct Object {
+    pub properties: Properties,
+    pub proto: Option<ObjectRef>,
+    /// Properties computed by the host, which take precedence over `properties`.
+    pub host: Option<Rc<dyn HostObject>>,
+    /// Set on a class constructor's function object: only `new` and `super(...)` may run it.
+    pub class: bool,
+}
+
+impl Object {
+    pub fn new(properties: Properties, proto: Option<ObjectRef>) -> ObjectRef {
+        Rc::new(RefCell::new(Self { properties, proto, host: None, class: false }))
+    }
+
+    pub fn get_own(&self, name: &str) -> Option<Literal> {
+        if let Some(val) = self.host.as_ref().and_then(|host| host.get(name)) {
+            return Some(val);
+        }
+
+        self.properties.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, val)| *val.clone())
+    }
+
+    /// Looks `name` up on the object itself, then along its prototype chain.
+    pub fn get(&self, name: &str) -> Option<Literal> {
+        match self.get_own(name) {
+            Some(val) => Some(val),
+            None => self.proto.as_ref()?.borrow().get(name)
+        }
+    }
+
+    /// Sets an own property, keeping its position if it already exists.
+    pub fn set(&mut self, name: impl Into<String>, value: Literal) {
+        let name = name.into();
+        match self.properties.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, val)) => *val = value.into(),
+            None => self.properties.push((name, value.into()))
+        }
+    }
+}
+
+pub type ArrayRef = Rc<RefCell<Array>>;
+
+/// A JS array: its elements, which it derefs to, plus any named properties of its own, such
+/// as a tagged template's `raw`.
+#[derive(Debug, Default)]
+pub struct Array {
+    pub elements: Vec<Literal>,
+    pub properties: Properties,
+    /// Frozen arrays, like a tagged template's strings, throw on any change.
+    pub frozen: bool,
+}
+
+impl Array {
+    pub fn new(elements: Vec<Literal>) -> ArrayRef {
+        Rc::new(RefCell::new(Self { elements, ..Self::default() }))
+    }
+
+    pub fn get_own(&self, name: &str) -> Option<Literal> {
+        self.properties.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, val)| *val.clone())
+    }
+
+    /// Sets a named property, keeping its position if it already exists.
+    pub fn set(&mut self, name: impl Into<String>, value: Literal) {
+        let name = name.into();
+        match self.properties.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, val)) => *val = value.into(),
+            None => self.properties.push((name, value.into()))
+        }
+    }
+}
+
+impl std::ops::Deref for Array {
+    type Target = Vec<Literal>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl std::ops::DerefMut for Array {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.elements
+    }
+}
+
+// Prototypes are shared by many objects and would swamp the output, so only own properties are shown.
+impl std::fmt::Debug for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.properties.iter().map(|(key, val)| (key, val))).finish()
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum Literal {
     Number(f64),
+    BigInt(BigInt),
     String(String),
     Null,
     Boolean(bool),
     Undefined,
-    Array(Rc<RefCell<Vec<Box<Literal>>>>),
-    Object(Vec<(String, Box<Literal>)>),
+    Array(ArrayRef),
+    Object(ObjectRef),
     Function {
         args: Vec<String>,
-        body: Box<Statement>
+        body: Rc<Statement>,
+        env: Environment,
+        /// Own properties, such as `prototype`.
+        object: ObjectRef
     },
-    NativeFunction(NativeFn)
+    NativeFunction(NativeFn),
+    /// A getter/setter pair. Only ever stored as a property: reading the property calls `get`
+    /// and assigning to it calls `set`.
+    Accessor {
+        get: Option<Box<Literal>>,
+        set: Option<Box<Literal>>
+    }
 }
 
 impl Literal {
-    pub(crate) fn truthy(&self) -> bool {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Literal::String(_) => "string",
+            Literal::Number(_) => "number",
+            Literal::BigInt(_) => "bigint",
+            Literal::Boolean(_) => "boolean",
+            Literal::Null => "null",
+            Literal::Undefined => "undefined",
+            Literal::Object(_) => "object",
+            Literal::Array(_) => "array",
+            Literal::Function { .. } => "function",
+            Literal::NativeFunction(_) => "native function",
+            Literal::Accessor { .. } => "accessor",
+        }
+    }
+
+    pub fn truthy(&self) -> bool {
         match self {
             Literal::Number(n) => *n != 0. && !(*n).is_nan(),
-            Literal::String(s) => s.len() > 0,
+            Literal::BigInt(n) => n.sign() != Sign::NoSign,
+            Literal::String(s) => !s.is_empty(),
             Literal::Null => false,
             Literal::Boolean(b) => *b,
             Literal::Undefined => false,
             Literal::Array(a) => {
                 !a.borrow().is_empty()
             },
-            Literal::Object(o) => !o.is_empty(),
+            Literal::Object(_) => true,
             Literal::Function { .. } => true,
             Literal::NativeFunction(_) => true,
+            Literal::Accessor { .. } => true,
+        }
+    }
+}
+
+// JS strict equality: arrays, objects and functions are compared by identity, everything else
+// by value, so `NaN` is unequal to itself and the two zeros are equal.
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Number(a), Literal::Number(b)) => a == b,
+            (Literal::BigInt(a), Literal::BigInt(b)) => a == b,
+            (Literal::String(a), Literal::String(b)) => a == b,
+            (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
+            (Literal::Null, Literal::Null) | (Literal::Undefined, Literal::Undefined) => true,
+            (Literal::Array(a), Literal::Array(b)) => Rc::ptr_eq(a, b),
+            (Literal::Object(a), Literal::Object(b)) => Rc::ptr_eq(a, b),
+            (Literal::Function { object: a, .. }, Literal::Function { object: b, .. }) => Rc::ptr_eq(a, b),
+            (Literal::NativeFunction(a), Literal::NativeFunction(b)) => a == b,
+            _ => false,
         }
     }
 }
 
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::String(s) => write!(f, "{s}"),
+            Literal::Number(n) => write!(f, "{}", number_to_string(*n)),
+            Literal::BigInt(n) => write!(f, "{n}"),
+            Literal::Boolean(b) => write!(f, "{b}"),
+            Literal::Null => write!(f, "null"),
+            Literal::Undefined => write!(f, "undefined"),
+            Literal::Object(_) => write!(f, "[object]"),
+            Literal::Array(_) => write!(f, "[array]"),
+            Literal::Function { .. } => write!(f, "[function]"),
+            Literal::NativeFunction(_) => write!(f, "[native function]"),
+            Literal::Accessor { .. } => write!(f, "[accessor]"),
+        }
+    }
+}
+
+/// Formats a number the way JS does: the shortest digits that round-trip, in exponent form
+/// outside `1e-7..1e21`, and with both zeros as `0`.
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".into();
+    }
+    if n == 0. {
+        return "0".into();
+    }
+    if n.is_infinite() {
+        return if n > 0. { "Infinity".into() } else { "-Infinity".into() };
+    }
+    if n < 0. {
+        return format!("-{}", number_to_string(-n));
+    }
+
+    // `{:e}` gives the shortest round-tripping digits, as `d.ddde±x`.
+    let exp = format!("{n:e}");
+    let (mantissa, exponent) = exp.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let point = exponent.parse::<i32>().unwrap() + 1;
+
+    if k <= point && point <= 21 {
+        format!("{digits}{}", "0".repeat((point - k) as usize))
+    } else if 0 < point && point <= 21 {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{digits}", "0".repeat(-point as usize))
+    } else {
+        let sign = if point > 0 { "+" } else { "-" };
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { String::new() } else { format!(".{rest}") };
+        format!("{first}{rest}e{sign}{}", (point - 1).abs())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberKind {
+    Method,
+    Getter,
+    Setter,
+    /// `static name = value;`
+    Field,
+}
+
+/// A method, accessor or static field in a class body.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
+pub struct ClassMember {
+    pub name: String,
+    pub kind: MemberKind,
+    pub is_static: bool,
+    /// A function expression, or the initializer of a field.
+    pub value: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionKind {
     Literal(Literal),
     Identifier(String),
     Object {
@@ -107,6 +409,18 @@ pub enum Expression {
         op: UnaryOperator,
         expr: Box<Expression>,
     },
+    /// `left && right` and friends. For the assignment forms `left` is the target.
+    Logical {
+        left: Box<Expression>,
+        op: LogicalOperator,
+        right: Box<Expression>,
+    },
+    /// `condition ? consequent : alternative`
+    Conditional {
+        condition: Box<Expression>,
+        consequent: Box<Expression>,
+        alternative: Box<Expression>,
+    },
     FunctionCall {
         callee: Box<Expression>,
         args: Vec<Box<Expression>>,
@@ -123,16 +437,70 @@ pub enum Expression {
         target: Box<Expression>,
         name: String,
     },
+    /// `++target` if `prefix` is set, otherwise `target++`.
     Increment {
         target: Box<Expression>,
+        prefix: bool,
     },
     Decrement {
         target: Box<Expression>,
+        prefix: bool,
+    },
+    This,
+    /// `new callee(args)`
+    New {
+        callee: Box<Expression>,
+        args: Vec<Box<Expression>>,
+    },
+    /// `class name? extends parent? { ... }`. The constructor is a function expression, filled
+    /// in by the parser when the class body doesn't declare one.
+    Class {
+        name: Option<String>,
+        parent: Option<Box<Expression>>,
+        constructor: Box<Expression>,
+        members: Vec<ClassMember>,
+    },
+    /// `super(args)` in a constructor. `None` forwards the constructor's own arguments, as an
+    /// implicit constructor does.
+    SuperCall {
+        args: Option<Vec<Box<Expression>>>,
+    },
+    /// `super.name`
+    SuperProperty {
+        name: String,
+    },
+    /// An untagged template literal: `quasis` are the strings around each substitution, so
+    /// there is always one more of them than of `expressions`.
+    Template {
+        quasis: Vec<String>,
+        expressions: Vec<Box<Expression>>,
+    },
+    /// The strings array a tagged template passes to its tag: the cooked strings, frozen, with
+    /// the frozen raw strings as its `raw` property. Built once by the parser, so every
+    /// evaluation of the same template passes the same array.
+    TemplateObject(Literal),
+    /// `function name?(args) { body }` in expression position.
+    Function {
+        name: Option<String>,
+        args: Vec<String>,
+        body: Box<Statement>,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Self { kind, span }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
+pub enum StatementKind {
     Expression(Box<Expression>),
     Return(Box<Expression>),
     Continue,
@@ -163,6 +531,13 @@ pub enum Statement {
     Let {
         name: String,
         value: Box<Expression>,
+    },
+    Throw(Box<Expression>),
+    Try {
+        body: Box<Statement>,
+        catch_param: Option<String>,
+        handler: Option<Box<Statement>>,
+        finalizer: Option<Box<Statement>>,
     }
 }
 
@@ -172,7 +547,7 @@ pub struct AST {
 }
 
 impl AST {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<SpannedToken>) -> Result<Self, Vec<ParseError>> {
         let mut parser = Parser::new(tokens);
         parser.parse()
     }
@@ -0,0 +1,53 @@
+use std::fmt;
+use crate::lexer::{Span, Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// A specific token was required, e.g. `)` to close a call.
+    UnexpectedToken,
+    ExpectedIdentifier,
+    ExpectedExpression,
+    InvalidPropertyKey,
+    /// A malformed escape sequence in a template literal that isn't tagged.
+    InvalidEscape,
+    /// A `Token::Error` from the lexer.
+    InvalidToken,
+    /// `??` alongside `&&` or `||` without parentheses to say which applies first.
+    MixedNullish,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+    /// Human readable description of what the parser was looking for.
+    pub expected: String,
+    pub found: Token,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, span: Span, expected: impl Into<String>, found: Token) -> Self {
+        Self { kind, span, expected: expected.into(), found }
+    }
+
+    /// Formats the error along with the offending line of `source`.
+    pub fn render(&self, source: &str) -> String {
+        format!("error: {}\n --> {}\n{}", self.message(), self.span, self.span.snippet(source))
+    }
+
+    pub fn message(&self) -> String {
+        match &self.found {
+            // The lexer has already said what's wrong with the token.
+            Token::Error(message) => message.clone(),
+            found => format!("expected {}, found {found}", self.expected)
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message())
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,5 +1,8 @@
 mod ast;
+mod error;
+#[allow(clippy::module_inception)]
 mod parser;
 
 pub use ast::*;
+pub use error::*;
 pub use parser::*;
@@ -1,19 +1,31 @@
-use crate::parser::{AST, BinaryOperator, Expression, Literal, Statement, UnaryOperator};
-use crate::lexer::Token;
-use std::cmp::PartialEq;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::parser::{AST, Array, BinaryOperator, ClassMember, Expression, ExpressionKind, Literal, LogicalOperator, MemberKind, ParseError, ParseErrorKind, Properties, Statement, StatementKind, UnaryOperator};
+use crate::lexer::{Span, SpannedToken, Token};
+
+type ParseResult<T> = Result<T, ParseError>;
+/// The cooked strings, raw strings and substitutions of a template literal.
+type TemplateParts = (Vec<Option<String>>, Vec<String>, Vec<Expression>);
+
+/// An infix operator, which either always evaluates both operands or may short-circuit.
+enum Operator {
+    Binary(BinaryOperator),
+    Logical(LogicalOperator),
+}
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     pos: usize,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+    pub fn new(tokens: Vec<SpannedToken>) -> Self {
+        Self { tokens, pos: 0, errors: Vec::new() }
     }
 
     fn done(&self) -> bool {
-        self.tokens[self.pos] == Token::EOF || self.pos >= self.tokens.len()
+        self.pos >= self.tokens.len() || self.tokens[self.pos].token == Token::EOF
     }
 
     fn peek(&self) -> Token {
@@ -21,248 +33,530 @@ impl Parser {
     }
 
     fn peek_by(&self, offset: usize) -> Token {
-        self.tokens[self.pos + offset].clone()
+        let last = self.tokens.len() - 1;
+        self.tokens[(self.pos + offset).min(last)].token.clone()
+    }
+
+    fn peek_span(&self) -> Span {
+        self.tokens[self.pos.min(self.tokens.len() - 1)].span
+    }
+
+    /// Span of the most recently consumed token.
+    fn prev_span(&self) -> Span {
+        self.tokens[self.pos.saturating_sub(1)].span
+    }
+
+    /// Span from `start` up to the end of the most recently consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span())
     }
 
     fn consume(&mut self) -> Token {
         let token = self.peek();
-        self.pos += 1;
+        if !self.done() {
+            self.pos += 1;
+        }
         token
     }
 
-    fn expect(&mut self, token: Token) {
+    fn error<T>(&self, kind: ParseErrorKind, expected: impl Into<String>) -> ParseResult<T> {
+        Err(ParseError::new(kind, self.peek_span(), expected, self.peek()))
+    }
+
+    fn expect(&mut self, token: Token) -> ParseResult<()> {
         if self.peek() == token {
             self.consume();
+            Ok(())
         } else {
-            panic!("Expected {:?}", token);
+            self.error(ParseErrorKind::UnexpectedToken, token.to_string())
+        }
+    }
+
+    /// Whether a line break comes before the next token.
+    fn newline_before(&self) -> bool {
+        self.tokens[self.pos.min(self.tokens.len() - 1)].newline_before
+    }
+
+    /// Whether the statement can end here: at a `;`, or where automatic semicolon insertion
+    /// would put one, which is before a `}`, at the end of input, or at a line break.
+    fn at_semicolon(&self) -> bool {
+        matches!(self.peek(), Token::Semicolon | Token::RightBrace | Token::EOF) || self.newline_before()
+    }
+
+    /// Ends a statement, consuming its `;` if it has one rather than relying on insertion.
+    fn semicolon(&mut self) -> ParseResult<()> {
+        if self.peek() == Token::Semicolon {
+            self.consume();
+            Ok(())
+        } else if self.at_semicolon() {
+            Ok(())
+        } else {
+            self.error(ParseErrorKind::UnexpectedToken, Token::Semicolon.to_string())
+        }
+    }
+
+    fn identifier(&mut self, expected: &str) -> ParseResult<String> {
+        match self.peek() {
+            Token::Identifier(name) => {
+                self.consume();
+                Ok(name)
+            },
+            _ => self.error(ParseErrorKind::ExpectedIdentifier, expected),
+        }
+    }
+
+    /// Panic-mode recovery: skip ahead to the next statement boundary. Always makes progress
+    /// when the failed statement started at `start_pos`, so a bad token can't stall the parser.
+    /// Boundaries only count outside any braces the statement opened, so a mistake in a function
+    /// header or body skips to the `}` that closes the body rather than stopping partway.
+    fn synchronize(&mut self, start_pos: usize) {
+        if self.pos == start_pos {
+            self.consume();
+        }
+
+        let mut depth = self.tokens[start_pos..self.pos].iter().fold(0usize, |depth, token| match token.token {
+            Token::LeftBrace => depth + 1,
+            Token::RightBrace => depth.saturating_sub(1),
+            _ => depth,
+        });
+        // Whether a brace was opened while skipping, whose `}` then ends the statement.
+        let mut skipped_block = false;
+
+        while !self.done() {
+            match self.peek() {
+                Token::LeftBrace => {
+                    self.consume();
+                    depth += 1;
+                    skipped_block = true;
+                },
+                Token::RightBrace if depth > 0 => {
+                    self.consume();
+                    depth -= 1;
+                    if depth == 0 && skipped_block {
+                        if self.peek() == Token::Semicolon {
+                            self.consume();
+                        }
+                        return;
+                    }
+                },
+                _ if depth > 0 => { self.consume(); },
+                Token::Semicolon => {
+                    self.consume();
+                    return;
+                },
+                Token::RightBrace | Token::Let | Token::Var | Token::If | Token::While | Token::For
+                | Token::Do | Token::Function | Token::Return | Token::Break | Token::Continue
+                | Token::Try | Token::Throw | Token::Class => return,
+                _ => { self.consume(); },
+            }
+        }
+    }
+
+    /// Parses a statement, recording the error and recovering if it fails.
+    fn recovering_statement(&mut self) -> Option<Statement> {
+        let start_pos = self.pos;
+        match self.statement() {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.errors.push(err);
+                self.synchronize(start_pos);
+                None
+            }
         }
     }
 
-    fn do_if(&mut self) -> Statement {
+    fn do_if(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
         self.consume(); // if
-        self.expect(Token::LeftParen);
-        let condition = self.expression();
-        self.expect(Token::RightParen);
+        self.expect(Token::LeftParen)?;
+        let condition = self.expression()?;
+        self.expect(Token::RightParen)?;
 
-        let consequence = self.statement();
+        let consequence = self.statement()?;
 
         let alternative = if self.peek() == Token::Else {
             self.consume();
-            Some(self.statement())
+            Some(self.statement()?)
         } else {
             None
         };
 
-        Statement::If {
+        Ok(Statement::new(StatementKind::If {
             condition: condition.into(),
             consequence: consequence.into(),
             alternative: alternative.map(Box::new),
-        }
+        }, self.span_from(start)))
     }
 
-    fn do_let(&mut self) -> Statement {
-        self.expect(Token::Let);
+    fn do_let(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        self.expect(Token::Let)?;
 
-        let name = match self.consume() {
-            Token::Identifier(name) => name,
-            tok => panic!("Expected identifier after let, got {:?}", tok),
-        };
+        let name = self.identifier("variable name")?;
 
-        let value;
-        if self.peek() == Token::Semicolon {
-            value = Expression::Literal(Literal::Undefined);
+        let value = if self.peek() == Token::Equal {
+            self.consume();
+            self.expression()?
         } else {
-            self.expect(Token::Equal);
-            value = self.expression();
-        }
-        self.expect(Token::Semicolon);
+            Expression::new(ExpressionKind::Literal(Literal::Undefined), self.prev_span())
+        };
+        self.semicolon()?;
 
-        Statement::Let {
+        Ok(Statement::new(StatementKind::Let {
             name,
             value: value.into(),
-        }
+        }, self.span_from(start)))
     }
 
-    fn do_while(&mut self) -> Statement {
-        self.expect(Token::While);
-        self.expect(Token::LeftParen);
-        let condition = self.expression();
-        self.expect(Token::RightParen);
-        let body = self.statement();
-        Statement::While {
+    fn do_while(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        self.expect(Token::While)?;
+        self.expect(Token::LeftParen)?;
+        let condition = self.expression()?;
+        self.expect(Token::RightParen)?;
+        let body = self.statement()?;
+        Ok(Statement::new(StatementKind::While {
             condition: condition.into(),
             body: body.into(),
-        }
+        }, self.span_from(start)))
     }
 
-    fn do_for(&mut self) -> Statement {
-        self.expect(Token::For);
-        self.expect(Token::LeftParen);
+    fn do_for(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        self.expect(Token::For)?;
+        self.expect(Token::LeftParen)?;
         let init = if self.peek() != Token::Semicolon {
-            Some(self.statement())
+            let init = self.statement()?;
+            // statement() already handled the semicolon, but semicolons are never inserted
+            // into the header.
+            if self.tokens[self.pos - 1].token != Token::Semicolon {
+                return self.error(ParseErrorKind::UnexpectedToken, Token::Semicolon.to_string());
+            }
+            Some(init)
         } else {
-            self.expect(Token::Semicolon);
+            self.expect(Token::Semicolon)?;
             None
         };
-        // statement() already handled the semicolon.
 
         let condition = if self.peek() != Token::Semicolon {
-            Some(self.expression())
+            Some(self.expression()?)
         } else {
             None
         };
-        self.expect(Token::Semicolon);
+        self.expect(Token::Semicolon)?;
 
         let update = if self.peek() != Token::Semicolon {
-            Some(self.expression())
+            Some(self.expression()?)
         } else {
             None
         };
-        self.expect(Token::RightParen);
+        self.expect(Token::RightParen)?;
 
-        let body = self.statement();
+        let body = self.statement()?;
 
-        Statement::For {
+        Ok(Statement::new(StatementKind::For {
             init: init.map(Box::new),
             condition: condition.map(Box::new),
             update: update.map(Box::new),
             body: body.into(),
-        }
+        }, self.span_from(start)))
     }
 
-    fn do_function(&mut self) -> Statement {
-        self.expect(Token::Function);
-        let name = match self.consume() {
-            Token::Identifier(name) => name,
-            tok => panic!("Expected identifier after function, got {:?}", tok),
-        };
+    fn do_function(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        self.expect(Token::Function)?;
+        let name = self.identifier("function name")?;
+        let (args, body) = self.function_rest()?;
+
+        Ok(Statement::new(StatementKind::Function {
+            name,
+            args,
+            body: body.into(),
+        }, self.span_from(start)))
+    }
 
-        self.expect(Token::LeftParen);
+    /// Parameter list and body shared by function declarations and expressions.
+    fn function_rest(&mut self) -> ParseResult<(Vec<String>, Statement)> {
+        self.expect(Token::LeftParen)?;
         let mut args = Vec::new();
         if self.peek() != Token::RightParen {
             loop {
-                let arg = match self.consume() {
-                    Token::Identifier(name) => name,
-                    tok => panic!("Expected identifier after function, got {:?}", tok),
-                };
-                args.push(arg);
+                args.push(self.identifier("parameter name")?);
 
                 if self.peek() == Token::RightParen {
                     break;
                 }
-                self.expect(Token::Comma);
+                self.expect(Token::Comma)?;
             }
         }
-        self.expect(Token::RightParen);
+        self.expect(Token::RightParen)?;
 
-        let body = Statement::Scope {
-            statements: self.do_scope(),
+        let body = self.do_block()?;
+        Ok((args, body))
+    }
+
+    /// `class Name { ... }` declares `Name` like `let Name = class Name { ... };` would.
+    fn do_class(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        self.expect(Token::Class)?;
+        let name = self.identifier("class name")?;
+        let class = self.class_rest(Some(name.clone()))?;
+
+        Ok(Statement::new(StatementKind::Let {
+            name,
+            value: Expression::new(class, self.span_from(start)).into(),
+        }, self.span_from(start)))
+    }
+
+    /// Everything after the class name, shared by class declarations and expressions.
+    fn class_rest(&mut self, name: Option<String>) -> ParseResult<ExpressionKind> {
+        let parent = if self.peek() == Token::Extends {
+            self.consume();
+            Some(self.postfix()?)
+        } else {
+            None
         };
 
-        Statement::Function {
+        self.expect(Token::LeftBrace)?;
+        let mut constructor = None;
+        let mut members = Vec::new();
+        while self.peek() != Token::RightBrace && !self.done() {
+            if self.peek() == Token::Semicolon {
+                self.consume();
+                continue;
+            }
+
+            let start = self.peek_span();
+            // `static`, `get` and `set` are only modifiers when a member name follows them.
+            let is_static = self.peek() == Token::Identifier("static".into()) && self.peek_by(1) != Token::LeftParen;
+            if is_static {
+                self.consume();
+            }
+
+            let kind = match self.peek() {
+                Token::Identifier(word) if (word == "get" || word == "set")
+                    && matches!(self.peek_by(1), Token::Identifier(_) | Token::StringLiteral(_)) => {
+                    self.consume();
+                    if word == "get" { MemberKind::Getter } else { MemberKind::Setter }
+                },
+                _ => MemberKind::Method,
+            };
+
+            let name = match self.peek() {
+                Token::Identifier(name) | Token::StringLiteral(name) => {
+                    self.consume();
+                    name
+                },
+                _ => return self.error(ParseErrorKind::InvalidPropertyKey, "method name"),
+            };
+
+            if is_static && kind == MemberKind::Method && self.peek() == Token::Equal {
+                self.consume();
+                let value = self.expression()?;
+                self.semicolon()?;
+                members.push(ClassMember { name, kind: MemberKind::Field, is_static, value: value.into() });
+                continue;
+            }
+
+            let (args, body) = self.function_rest()?;
+            let value = Expression::new(ExpressionKind::Function {
+                name: None,
+                args,
+                body: body.into(),
+            }, self.span_from(start));
+
+            if !is_static && kind == MemberKind::Method && name == "constructor" {
+                if constructor.is_some() {
+                    return Err(ParseError::new(ParseErrorKind::UnexpectedToken, start, "a single constructor", Token::Identifier(name)));
+                }
+                constructor = Some(value);
+            } else {
+                members.push(ClassMember { name, kind, is_static, value: value.into() });
+            }
+        }
+
+        let end = self.peek_span();
+        self.expect(Token::RightBrace)?;
+
+        // A derived class without a constructor passes its arguments straight on to the parent.
+        let constructor = constructor.unwrap_or_else(|| {
+            let statements = match parent {
+                Some(_) => vec![Statement::new(StatementKind::Expression(
+                    Expression::new(ExpressionKind::SuperCall { args: None }, end).into()
+                ), end)],
+                None => Vec::new(),
+            };
+
+            Expression::new(ExpressionKind::Function {
+                name: None,
+                args: Vec::new(),
+                body: Statement::new(StatementKind::Scope { statements }, end).into(),
+            }, end)
+        });
+
+        Ok(ExpressionKind::Class {
             name,
-            args,
-            body: body.into(),
+            parent: parent.map(Box::new),
+            constructor: constructor.into(),
+            members,
+        })
+    }
+
+    fn do_try(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        self.expect(Token::Try)?;
+        let body = self.do_block()?;
+
+        let (catch_param, handler) = if self.peek() == Token::Catch {
+            self.consume();
+            // The binding is optional: `catch { ... }`
+            let param = if self.peek() == Token::LeftParen {
+                self.consume();
+                let name = self.identifier("catch parameter")?;
+                self.expect(Token::RightParen)?;
+                Some(name)
+            } else {
+                None
+            };
+            (param, Some(self.do_block()?))
+        } else {
+            (None, None)
+        };
+
+        let finalizer = if self.peek() == Token::Finally {
+            self.consume();
+            Some(self.do_block()?)
+        } else {
+            None
+        };
+
+        if handler.is_none() && finalizer.is_none() {
+            return self.error(ParseErrorKind::UnexpectedToken, "'catch' or 'finally'");
         }
+
+        Ok(Statement::new(StatementKind::Try {
+            body: body.into(),
+            catch_param,
+            handler: handler.map(Box::new),
+            finalizer: finalizer.map(Box::new),
+        }, self.span_from(start)))
     }
 
-    fn do_scope(&mut self) -> Vec<Statement> {
-        self.expect(Token::LeftBrace);
+    /// A braced block as a single scope statement.
+    fn do_block(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
+        let statements = self.do_scope()?;
+        Ok(Statement::new(StatementKind::Scope { statements }, self.span_from(start)))
+    }
+
+    fn do_scope(&mut self) -> ParseResult<Vec<Statement>> {
+        self.expect(Token::LeftBrace)?;
         let mut statements = Vec::new();
         while self.peek() != Token::RightBrace && !self.done() {
-            statements.push(self.statement());
+            if let Some(stmt) = self.recovering_statement() {
+                statements.push(stmt);
+            }
         }
-        self.expect(Token::RightBrace);
+        self.expect(Token::RightBrace)?;
 
-        statements
+        Ok(statements)
     }
 
     // Base case for all statements
-    fn statement(&mut self) -> Statement {
+    fn statement(&mut self) -> ParseResult<Statement> {
+        let start = self.peek_span();
         match self.peek() {
             Token::Return => {
                 self.consume();
 
-                if self.peek() == Token::Semicolon {
-                    Statement::Return(Box::new(Expression::Literal(Literal::Undefined)))
+                // `return` followed by a line break returns nothing, whatever is on the next line.
+                let expr = if self.at_semicolon() {
+                    Expression::new(ExpressionKind::Literal(Literal::Undefined), self.prev_span())
                 } else {
-                    let expr = self.expression();
-                    self.expect(Token::Semicolon);
-                    Statement::Return(Box::new(expr))
-                }
+                    self.expression()?
+                };
+                self.semicolon()?;
+                Ok(Statement::new(StatementKind::Return(Box::new(expr)), self.span_from(start)))
             }
             Token::If => self.do_if(),
             Token::Let => self.do_let(),
             Token::While => self.do_while(),
             Token::For => self.do_for(),
             Token::Function => self.do_function(),
+            Token::Try => self.do_try(),
+            Token::Class => self.do_class(),
+            Token::Throw => {
+                self.consume();
+                if self.newline_before() {
+                    return self.error(ParseErrorKind::UnexpectedToken, "expression on the same line as 'throw'");
+                }
+                let expr = self.expression()?;
+                self.semicolon()?;
+                Ok(Statement::new(StatementKind::Throw(Box::new(expr)), self.span_from(start)))
+            },
             Token::Break => {
                 self.consume();
-                self.expect(Token::Semicolon);
-                Statement::Break
+                self.semicolon()?;
+                Ok(Statement::new(StatementKind::Break, self.span_from(start)))
             },
             Token::Continue => {
                 self.consume();
-                self.expect(Token::Semicolon);
-                Statement::Continue
-            }
-            Token::LeftBrace => {
-                let statements = self.do_scope();
-                Statement::Scope { statements }
+                self.semicolon()?;
+                Ok(Statement::new(StatementKind::Continue, self.span_from(start)))
             }
+            Token::LeftBrace => self.do_block(),
             _ => {
-                let expr = self.expression();
-                self.expect(Token::Semicolon);
-                Statement::Expression(Box::new(expr))
+                let expr = self.expression()?;
+                self.semicolon()?;
+                Ok(Statement::new(StatementKind::Expression(Box::new(expr)), self.span_from(start)))
             }
         }
     }
 
-    fn do_args(&mut self) -> Vec<Expression> {
+    fn do_args(&mut self) -> ParseResult<Vec<Expression>> {
         let mut args = Vec::new();
-        self.expect(Token::LeftParen);
+        self.expect(Token::LeftParen)?;
         if self.peek() != Token::RightParen {
             loop {
-                args.push(self.expression());
+                args.push(self.expression()?);
                 if self.peek() == Token::RightParen {
                     break;
                 }
-                self.expect(Token::Comma);
+                self.expect(Token::Comma)?;
             }
         }
 
-        args
+        Ok(args)
     }
 
-    fn do_array(&mut self) -> Vec<Expression> {
+    fn do_array(&mut self) -> ParseResult<Vec<Expression>> {
         let mut elements = Vec::new();
         if self.peek() != Token::RightBracket {
             loop {
-                elements.push(self.expression());
+                elements.push(self.expression()?);
                 if self.peek() == Token::RightBracket {
                     break;
                 }
-                self.expect(Token::Comma);
+                self.expect(Token::Comma)?;
             }
         }
 
-        elements
+        Ok(elements)
     }
 
-    fn do_object(&mut self) -> Vec<(String, Box<Expression>)> {
+    fn do_object(&mut self) -> ParseResult<Vec<(String, Box<Expression>)>> {
         let mut properties = Vec::new();
         if self.peek() != Token::RightBrace {
             loop {
-                let key = match self.consume() {
-                    Token::StringLiteral(s) => s,
-                    Token::Identifier(s) => s,
-                    tok => panic!(
-                        "Expected string literal or identifier in object literal, got {:?}",
-                        tok
-                    ),
+                let key = match self.peek() {
+                    Token::StringLiteral(s) | Token::Identifier(s) => {
+                        self.consume();
+                        s
+                    },
+                    _ => return self.error(ParseErrorKind::InvalidPropertyKey, "property name"),
                 };
-                self.expect(Token::Colon);
-                let value = self.expression();
+                self.expect(Token::Colon)?;
+                let value = self.expression()?;
 
                 properties.push((key, Box::new(value)));
 
@@ -270,217 +564,397 @@ impl Parser {
                     break;
                 }
 
-                self.expect(Token::Comma);
+                self.expect(Token::Comma)?;
             }
         }
-        self.expect(Token::RightBrace);
+        self.expect(Token::RightBrace)?;
 
-        properties
+        Ok(properties)
     }
 
-    fn match_infix_operators(&mut self) -> Option<BinaryOperator> {
-        match self.peek() {
-            Token::Plus => {
-                self.consume();
-                Some(BinaryOperator::Add)
-            }
-            Token::Minus => {
-                self.consume();
-                Some(BinaryOperator::Sub)
-            }
-            Token::Star => {
-                self.consume();
-                Some(BinaryOperator::Mul)
-            }
-            Token::Slash => {
-                self.consume();
-                Some(BinaryOperator::Div)
-            }
-            Token::Percent => {
-                self.consume();
-                Some(BinaryOperator::Mod)
-            }
-            Token::AmpAmp => {
-                self.consume();
-                Some(BinaryOperator::BinaryAnd)
-            }
-            Token::PipePipe => {
-                self.consume();
-                Some(BinaryOperator::BinaryOr)
-            },
-            Token::EqualEqual => {
-                self.consume();
-                Some(BinaryOperator::Equal)
-            },
-            Token::BangEqual => {
-                self.consume();
-                Some(BinaryOperator::NotEqual)
-            },
-            Token::Greater => {
-                self.consume();
-                Some(BinaryOperator::GreaterThan)
-            },
-            Token::GreaterEqual => {
-                self.consume();
-                Some(BinaryOperator::GreaterThanOrEqual)
-            },
-            Token::Less => {
-                self.consume();
-                Some(BinaryOperator::LessThan)
-            },
-            Token::LessEqual => {
-                self.consume();
-                Some(BinaryOperator::LessThanOrEqual)
-            },
-            Token::PlusEqual => {
-                self.consume();
-                Some(BinaryOperator::PlusEqual)
-            },
-            Token::MinusEqual => {
-                self.consume();
-                Some(BinaryOperator::MinusEqual)
+    /// Binding power and operator for a binary infix token; higher binds tighter. Mirrors JS:
+    /// `||` and `??` < `&&` < equality < relational < additive < multiplicative.
+    fn infix_operator(token: &Token) -> Option<(u8, Operator)> {
+        let (precedence, op) = match token {
+            Token::PipePipe => return Some((1, Operator::Logical(LogicalOperator::Or))),
+            Token::QuestionQuestion => return Some((1, Operator::Logical(LogicalOperator::Nullish))),
+            Token::AmpAmp => return Some((2, Operator::Logical(LogicalOperator::And))),
+            Token::EqualEqual => (3, BinaryOperator::Equal),
+            Token::BangEqual => (3, BinaryOperator::NotEqual),
+            Token::EqualEqualEqual => (3, BinaryOperator::StrictEqual),
+            Token::BangEqualEqual => (3, BinaryOperator::StrictNotEqual),
+            Token::Less => (4, BinaryOperator::LessThan),
+            Token::LessEqual => (4, BinaryOperator::LessThanOrEqual),
+            Token::Greater => (4, BinaryOperator::GreaterThan),
+            Token::GreaterEqual => (4, BinaryOperator::GreaterThanOrEqual),
+            Token::Plus => (5, BinaryOperator::Add),
+            Token::Minus => (5, BinaryOperator::Sub),
+            Token::Star => (6, BinaryOperator::Mul),
+            Token::Slash => (6, BinaryOperator::Div),
+            Token::Percent => (6, BinaryOperator::Mod),
+            _ => return None,
+        };
+        Some((precedence, Operator::Binary(op)))
+    }
+
+    // Base case for all expressions
+    fn expression(&mut self) -> ParseResult<Expression> {
+        self.assignment()
+    }
+
+    /// Assignment binds loosest and is right-associative: `a = b += c` is `a = (b += c)`.
+    fn assignment(&mut self) -> ParseResult<Expression> {
+        let start = self.peek_span();
+        let target = self.conditional()?;
+
+        let op = match self.peek() {
+            Token::Equal => None,
+            Token::PlusEqual => Some(Operator::Binary(BinaryOperator::PlusEqual)),
+            Token::MinusEqual => Some(Operator::Binary(BinaryOperator::MinusEqual)),
+            Token::StarEqual => Some(Operator::Binary(BinaryOperator::MulEqual)),
+            Token::SlashEqual => Some(Operator::Binary(BinaryOperator::DivEqual)),
+            Token::PercentEqual => Some(Operator::Binary(BinaryOperator::ModEqual)),
+            Token::AmpAmpEqual => Some(Operator::Logical(LogicalOperator::AndEqual)),
+            Token::PipePipeEqual => Some(Operator::Logical(LogicalOperator::OrEqual)),
+            Token::QuestionQuestionEqual => Some(Operator::Logical(LogicalOperator::NullishEqual)),
+            _ => return Ok(target),
+        };
+        self.consume();
+        let value = self.assignment()?;
+
+        let kind = match op {
+            None => ExpressionKind::Assignment {
+                target: target.into(),
+                value: value.into(),
             },
-            Token::StarEqual => {
-                self.consume();
-                Some(BinaryOperator::MulEqual)
+            Some(Operator::Binary(op)) => ExpressionKind::BinaryOp {
+                left: target.into(),
+                op,
+                right: value.into(),
             },
-            Token::SlashEqual => {
-                self.consume();
-                Some(BinaryOperator::DivEqual)
+            Some(Operator::Logical(op)) => ExpressionKind::Logical {
+                left: target.into(),
+                op,
+                right: value.into(),
             },
-            _ => None,
+        };
+        Ok(Expression::new(kind, self.span_from(start)))
+    }
+
+    /// `condition ? consequent : alternative`, right-associative, with either branch allowed
+    /// to be an assignment: `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn conditional(&mut self) -> ParseResult<Expression> {
+        let start = self.peek_span();
+        let condition = self.binary(0)?;
+        if self.peek() != Token::Question {
+            return Ok(condition);
         }
+
+        self.consume();
+        let consequent = self.assignment()?;
+        self.expect(Token::Colon)?;
+        let alternative = self.assignment()?;
+        Ok(Expression::new(ExpressionKind::Conditional {
+            condition: condition.into(),
+            consequent: consequent.into(),
+            alternative: alternative.into(),
+        }, self.span_from(start)))
     }
 
-    // Base case for all expressions
-    fn expression(&mut self) -> Expression {
-        let mut expr = match self.consume() {
-            Token::Number(n) => Expression::Literal(Literal::Number(n)),
-            Token::StringLiteral(s) => Expression::Literal(Literal::String(s)),
-            Token::Identifier(name) => {
-                // Function Call
-                if matches!(self.peek(), Token::LeftParen) {
-                    let args = self.do_args().into_iter().map(Box::new).collect();
-                    self.expect(Token::RightParen);
-                    Expression::FunctionCall { callee: Expression::Identifier(name).into() , args }
-                } else {
-                    Expression::Identifier(name)
+    /// Precedence climbing: consumes only operators binding tighter than `min_precedence`.
+    fn binary(&mut self, min_precedence: u8) -> ParseResult<Expression> {
+        let start = self.peek_span();
+        let mut left = self.unary()?;
+        // JS rejects `a ?? b || c` and the like rather than picking an order, so this tracks
+        // the short-circuiting operators already applied at this level.
+        let mut nullish = None;
+
+        while let Some((precedence, op)) = Self::infix_operator(&self.peek())
+            && precedence > min_precedence
+        {
+            if let Operator::Logical(op) = &op {
+                let is_nullish = *op == LogicalOperator::Nullish;
+                if nullish.is_some_and(|seen| seen != is_nullish) {
+                    return self.error(ParseErrorKind::MixedNullish, "parentheses to mix '??' with '&&' or '||'");
                 }
+                nullish = Some(is_nullish);
             }
-            Token::True => Expression::Literal(Literal::Boolean(true)),
-            Token::False => Expression::Literal(Literal::Boolean(false)),
-            Token::Null => Expression::Literal(Literal::Null),
-            Token::Undefined => Expression::Literal(Literal::Undefined),
-            Token::LeftParen => {
-                let expr = self.expression();
-                self.expect(Token::RightParen);
-                expr
-            }
-            Token::LeftBracket => {
-                let exprs = self.do_array().into_iter().map(Box::new).collect();
-                self.expect(Token::RightBracket);
-                Expression::Array { elements: exprs }
-            }
-            Token::LeftBrace => {
-                let properties = self.do_object();
-                Expression::Object { properties }
+            self.consume();
+            // Every binary operator is left-associative, so the right operand may only
+            // contain operators that bind strictly tighter: `1 - 2 - 3` is `(1 - 2) - 3`.
+            // `??` also leaves any `&&` to this loop, which then reports the mix.
+            let right_precedence = match op {
+                Operator::Logical(LogicalOperator::Nullish) => 2,
+                _ => precedence,
+            };
+            let right = self.binary(right_precedence)?;
+            let kind = match op {
+                Operator::Binary(op) => ExpressionKind::BinaryOp {
+                    left: left.into(),
+                    op,
+                    right: right.into(),
+                },
+                Operator::Logical(op) => ExpressionKind::Logical {
+                    left: left.into(),
+                    op,
+                    right: right.into(),
+                },
+            };
+            left = Expression::new(kind, self.span_from(start));
+        }
+
+        Ok(left)
+    }
+
+    /// Prefix operators bind tighter than any binary operator but looser than postfix ones,
+    /// so `-a * b` is `(-a) * b` and `-a.b` is `-(a.b)`.
+    fn unary(&mut self) -> ParseResult<Expression> {
+        let start = self.peek_span();
+        let op = match self.peek() {
+            Token::Minus => UnaryOperator::Negate,
+            Token::Plus => UnaryOperator::Plus,
+            Token::Bang => UnaryOperator::Not,
+            Token::PlusPlus | Token::MinusMinus => {
+                let increment = self.consume() == Token::PlusPlus;
+                let target = self.unary()?.into();
+                let kind = if increment {
+                    ExpressionKind::Increment { target, prefix: true }
+                } else {
+                    ExpressionKind::Decrement { target, prefix: true }
+                };
+                return Ok(Expression::new(kind, self.span_from(start)));
             },
-            Token::Minus => {
-                let expr = self.expression();
-                Expression::UnaryOp {
-                    op: UnaryOperator::Negate,
-                    expr: expr.into(),
-                }
-            }
-            Token::Bang => {
-                let expr = self.expression();
-                Expression::UnaryOp {
-                    op: UnaryOperator::Not,
-                    expr: expr.into(),
-                }
-            }
-            tok => panic!("Unexpected token {:?}", tok),
+            _ => return self.postfix(),
         };
+        self.consume();
+        let expr = self.unary()?;
+
+        Ok(Expression::new(ExpressionKind::UnaryOp {
+            op,
+            expr: expr.into(),
+        }, self.span_from(start)))
+    }
+
+    /// Member access, indexing, calls and postfix `++`/`--`.
+    fn postfix(&mut self) -> ParseResult<Expression> {
+        self.member(true)
+    }
+
+    /// Member access and indexing, plus calls and postfix `++`/`--` if `calls` is set. The
+    /// callee of `new` is parsed without them, so `new a.B(1)` passes `1` to `a.B`.
+    fn member(&mut self, calls: bool) -> ParseResult<Expression> {
+        let start = self.peek_span();
+        let mut expr = self.primary()?;
 
-        // Postfix operators
         loop {
-            match self.peek() {
+            let kind = match self.peek() {
                 Token::LeftBracket => {
                     self.consume();
-                    let index = self.expression();
-                    self.expect(Token::RightBracket);
-                    expr = Expression::Index {
+                    let index = self.expression()?;
+                    self.expect(Token::RightBracket)?;
+                    ExpressionKind::Index {
                         target: expr.into(),
                         index: index.into()
                     }
                 }
                 Token::Dot => {
                     self.consume();
-                    let name = match self.consume() {
-                        Token::Identifier(name) => name,
-                        tok => panic!("Expected identifier after dot, got {:?}", tok),
-                    };
+                    let name = self.identifier("property name")?;
 
-                    expr = Expression::Property {
+                    ExpressionKind::Property {
                         target: expr.into(),
                         name,
-                    };
+                    }
                 },
-                Token::LeftParen => {
-                    let args = self.do_args();
-                    self.expect(Token::RightParen);
-                    expr = Expression::FunctionCall {
+                Token::LeftParen if calls => {
+                    let args = self.do_args()?;
+                    self.expect(Token::RightParen)?;
+                    ExpressionKind::FunctionCall {
                         callee: expr.into(),
                         args: args.into_iter().map(Box::new).collect(),
-                    };
+                    }
                 },
-                Token::PlusPlus => {
+                // A tagged template calls the tag with the strings array, then each substitution.
+                Token::Template { .. } => {
+                    let template_start = self.peek_span();
+                    let first = self.consume();
+                    let (cooked, raw, substitutions) = self.template(first)?;
+
+                    let array = |strings: Vec<Literal>, properties: Properties| Literal::Array(Rc::new(RefCell::new(Array {
+                        elements: strings,
+                        properties,
+                        frozen: true,
+                    })));
+                    let raw = array(raw.into_iter().map(Literal::String).collect(), Vec::new());
+                    let strings = ExpressionKind::TemplateObject(array(
+                        cooked.into_iter().map(|s| s.map_or(Literal::Undefined, Literal::String)).collect(),
+                        vec![("raw".into(), raw.into())],
+                    ));
+
+                    let mut args = vec![Box::new(Expression::new(strings, self.span_from(template_start)))];
+                    args.extend(substitutions.into_iter().map(Box::new));
+                    ExpressionKind::FunctionCall {
+                        callee: expr.into(),
+                        args,
+                    }
+                },
+                // Postfix operators have to be on the same line as their operand; otherwise they
+                // start the next statement as prefix ones.
+                Token::PlusPlus if calls && !self.newline_before() => {
                     self.consume();
-                    expr = Expression::Increment {
-                        target: expr.into()
-                    };
+                    ExpressionKind::Increment {
+                        target: expr.into(),
+                        prefix: false
+                    }
                 },
-                Token::MinusMinus => {
+                Token::MinusMinus if calls && !self.newline_before() => {
                     self.consume();
-                    expr = Expression::Decrement {
-                        target: expr.into()
-                    };
+                    ExpressionKind::Decrement {
+                        target: expr.into(),
+                        prefix: false
+                    }
                 },
                 _ => break,
-            }
+            };
+            expr = Expression::new(kind, self.span_from(start));
         }
 
-        // Assignment
-        if self.peek() == Token::Equal {
-            self.consume();
-            let value = self.expression();
+        Ok(expr)
+    }
 
-            return Expression::Assignment {
-                target: expr.into(),
-                value: value.into(),
-            };
-        }
+    /// The rest of a template literal, given its first piece: each substitution has to be
+    /// followed by the piece of template that its closing `}` starts.
+    fn template(&mut self, first: Token) -> ParseResult<TemplateParts> {
+        let (mut cooked, mut raw, mut expressions) = (Vec::new(), Vec::new(), Vec::new());
 
-        // Infix operators
-        while let Some(op) = self.match_infix_operators() {
-            let rhs = self.expression();
-            expr = Expression::BinaryOp {
-                left: expr.into(),
-                op,
-                right: rhs.into(),
+        let mut part = first;
+        loop {
+            let (Token::Template { cooked: text, raw: raw_text, tail } | Token::TemplateContinuation { cooked: text, raw: raw_text, tail }) = part else {
+                unreachable!("template pieces are checked before they are consumed");
             };
+            cooked.push(text);
+            raw.push(raw_text);
+            if tail {
+                return Ok((cooked, raw, expressions));
+            }
+
+            expressions.push(self.expression()?);
+            if !matches!(self.peek(), Token::TemplateContinuation { .. }) {
+                return self.error(ParseErrorKind::UnexpectedToken, "'}' to close the template substitution");
+            }
+            part = self.consume();
         }
+    }
 
-        expr
+    fn primary(&mut self) -> ParseResult<Expression> {
+        let start = self.peek_span();
+        let kind = match self.consume() {
+            Token::Number(n) => ExpressionKind::Literal(Literal::Number(n)),
+            Token::BigInt(n) => ExpressionKind::Literal(Literal::BigInt(n)),
+            Token::StringLiteral(s) => ExpressionKind::Literal(Literal::String(s)),
+            first @ Token::Template { .. } => {
+                let (cooked, _, expressions) = self.template(first.clone())?;
+                let Some(quasis) = cooked.into_iter().collect() else {
+                    return Err(ParseError::new(ParseErrorKind::InvalidEscape, self.span_from(start), "a valid escape sequence", first));
+                };
+
+                ExpressionKind::Template {
+                    quasis,
+                    expressions: expressions.into_iter().map(Box::new).collect(),
+                }
+            },
+            Token::Identifier(name) => ExpressionKind::Identifier(name),
+            Token::This => ExpressionKind::This,
+            Token::New => {
+                let callee = self.member(false)?;
+                let args = if self.peek() == Token::LeftParen {
+                    let args = self.do_args()?;
+                    self.expect(Token::RightParen)?;
+                    args
+                } else {
+                    Vec::new()
+                };
+
+                ExpressionKind::New {
+                    callee: callee.into(),
+                    args: args.into_iter().map(Box::new).collect(),
+                }
+            },
+            Token::True => ExpressionKind::Literal(Literal::Boolean(true)),
+            Token::False => ExpressionKind::Literal(Literal::Boolean(false)),
+            Token::Null => ExpressionKind::Literal(Literal::Null),
+            Token::Undefined => ExpressionKind::Literal(Literal::Undefined),
+            Token::LeftParen => {
+                let expr = self.expression()?;
+                self.expect(Token::RightParen)?;
+                expr.kind
+            }
+            Token::LeftBracket => {
+                let exprs = self.do_array()?.into_iter().map(Box::new).collect();
+                self.expect(Token::RightBracket)?;
+                ExpressionKind::Array { elements: exprs }
+            }
+            Token::LeftBrace => {
+                let properties = self.do_object()?;
+                ExpressionKind::Object { properties }
+            },
+            Token::Class => {
+                let name = match self.peek() {
+                    Token::Identifier(_) => Some(self.identifier("class name")?),
+                    _ => None,
+                };
+                self.class_rest(name)?
+            },
+            Token::Super => match self.peek() {
+                Token::LeftParen => {
+                    let args = self.do_args()?;
+                    self.expect(Token::RightParen)?;
+                    ExpressionKind::SuperCall { args: Some(args.into_iter().map(Box::new).collect()) }
+                },
+                Token::Dot => {
+                    self.consume();
+                    ExpressionKind::SuperProperty { name: self.identifier("property name")? }
+                },
+                _ => return self.error(ParseErrorKind::UnexpectedToken, "'(' or '.' after 'super'"),
+            },
+            Token::Function => {
+                let name = match self.peek() {
+                    Token::Identifier(_) => Some(self.identifier("function name")?),
+                    _ => None,
+                };
+                let (args, body) = self.function_rest()?;
+                ExpressionKind::Function { name, args, body: body.into() }
+            },
+            tok => return Err(ParseError::new(ParseErrorKind::ExpectedExpression, start, "expression", tok)),
+        };
+
+        Ok(Expression::new(kind, self.span_from(start)))
     }
 
-    pub fn parse(&mut self) -> AST {
+    /// Parses the whole token stream, collecting every syntax error rather than stopping at the first.
+    pub fn parse(&mut self) -> Result<AST, Vec<ParseError>> {
+        // Parsing around a token the lexer gave up on would mostly produce knock-on errors, so
+        // those are reported on their own.
+        let invalid: Vec<ParseError> = self.tokens.iter()
+            .filter(|token| matches!(token.token, Token::Error(_)))
+            .map(|token| ParseError::new(ParseErrorKind::InvalidToken, token.span, "a valid token", token.token.clone()))
+            .collect();
+        if !invalid.is_empty() {
+            return Err(invalid);
+        }
+
         let mut statements = Vec::new();
 
         while !self.done() {
-            statements.push(self.statement());
+            if let Some(stmt) = self.recovering_statement() {
+                statements.push(stmt);
+            }
         }
 
-        AST { statements }
+        if self.errors.is_empty() {
+            Ok(AST { statements })
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 }
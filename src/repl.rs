@@ -0,0 +1,188 @@
+use std::{env, fs};
+use std::path::PathBuf;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use tinyjs::lexer::{Lexer, Token};
+use tinyjs::parser::{AST, Literal};
+use tinyjs::runtime::Interpreter;
+
+const HELP: &str = "\
+.ast <code>   Print the syntax tree of <code> without running it
+.load <file>  Run a script in the current session
+.help         Show this message
+.exit         Leave the REPL (or press Ctrl-D)";
+
+/// Interactive session over a single interpreter, so bindings persist between entries.
+pub struct Repl {
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(AST { statements: Vec::new() }),
+        }
+    }
+
+    fn history_path() -> Option<PathBuf> {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".tinyjs_history"))
+    }
+
+    /// Net count of unclosed `(`, `[`, `{` and template `${`; the entry continues on the next
+    /// line while positive. A template, comment or continued string still open at the end of
+    /// the entry also counts, as the lexer's error for it runs right up to that end.
+    fn open_brackets(source: &str) -> i32 {
+        let tokens = Lexer::new(source).lex();
+        if tokens.iter().any(|token| matches!(token.token, Token::Error(_)) && token.span.end == source.len()) {
+            return 1;
+        }
+
+        tokens.iter().map(|token| match token.token {
+            Token::LeftParen | Token::LeftBracket | Token::LeftBrace => 1,
+            Token::Template { tail: false, .. } => 1,
+            Token::RightParen | Token::RightBracket | Token::RightBrace => -1,
+            Token::TemplateContinuation { tail: true, .. } => -1,
+            _ => 0,
+        }).sum()
+    }
+
+    /// Parses an entry, printing any syntax errors against `source`.
+    fn parse(source: &str) -> Option<AST> {
+        match AST::new(Lexer::new(source).lex()) {
+            Ok(ast) => Some(ast),
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}\n", error.render(source));
+                }
+                None
+            }
+        }
+    }
+
+    fn eval(&mut self, source: &str) {
+        let Some(ast) = Self::parse(source) else {
+            return;
+        };
+
+        match self.interpreter.eval(ast) {
+            Ok(Some(Literal::Undefined) | None) => {},
+            Ok(Some(value)) => println!("{value}"),
+            Err(exception) => eprintln!("{}", exception.render(source)),
+        }
+    }
+
+    /// Handles a `.command`. Returns `false` when the session should end.
+    fn command(&mut self, line: &str) -> bool {
+        let (command, arg) = line.split_once(' ').map_or((line, ""), |(command, arg)| (command, arg.trim()));
+
+        match command {
+            ".exit" => return false,
+            ".help" => println!("{HELP}"),
+            ".load" if !arg.is_empty() => match fs::read_to_string(arg) {
+                Ok(source) => self.eval(&source),
+                Err(err) => eprintln!("Could not read {arg}: {err}"),
+            },
+            ".ast" if !arg.is_empty() => {
+                if let Some(ast) = Self::parse(arg) {
+                    println!("{:#?}", ast.statements);
+                }
+            },
+            ".load" | ".ast" => eprintln!("Usage: {command} <{}>", if command == ".load" { "file" } else { "code" }),
+            _ => eprintln!("Unknown command {command}. Type .help for a list."),
+        }
+
+        true
+    }
+
+    pub fn run(&mut self) -> rustyline::Result<()> {
+        let mut editor = DefaultEditor::new()?;
+        let history = Self::history_path();
+        if let Some(path) = &history {
+            // There's no history yet on first launch.
+            let _ = editor.load_history(path);
+        }
+
+        println!("tinyjs REPL. Type .help for commands.");
+
+        let mut entry = String::new();
+        loop {
+            let prompt = if entry.is_empty() { "> " } else { "... " };
+            let line = match editor.readline(prompt) {
+                Ok(line) => line,
+                // Ctrl-C abandons the current entry, Ctrl-D ends the session.
+                Err(ReadlineError::Interrupted) => {
+                    entry.clear();
+                    continue;
+                },
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err),
+            };
+
+            if entry.is_empty() && line.trim_start().starts_with('.') {
+                editor.add_history_entry(line.as_str())?;
+                if !self.command(line.trim()) {
+                    break;
+                }
+                continue;
+            }
+
+            entry.push_str(&line);
+            entry.push('\n');
+            if Self::open_brackets(&entry) > 0 {
+                continue;
+            }
+
+            let source = std::mem::take(&mut entry);
+            if source.trim().is_empty() {
+                continue;
+            }
+
+            editor.add_history_entry(source.trim_end())?;
+            self.eval(&source);
+        }
+
+        if let Some(path) = &history {
+            let _ = editor.save_history(path);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Repl;
+
+    #[test]
+    fn brackets_continue_the_entry() {
+        assert_eq!(Repl::open_brackets("function f() {\n"), 1);
+        assert_eq!(Repl::open_brackets("f([1, {\n"), 3);
+        assert_eq!(Repl::open_brackets("function f() {\n}\n"), 0);
+        assert_eq!(Repl::open_brackets(")\n"), -1);
+    }
+
+    #[test]
+    fn templates_continue_the_entry() {
+        assert!(Repl::open_brackets("let s = `first\n") > 0);
+        assert_eq!(Repl::open_brackets("let s = `first\nsecond`;\n"), 0);
+        assert!(Repl::open_brackets("`a ${1} b\n") > 0);
+        assert!(Repl::open_brackets("`a ${\n") > 0);
+        assert_eq!(Repl::open_brackets("`a ${\n1} b`\n"), 0);
+    }
+
+    #[test]
+    fn open_comments_and_continued_strings_continue_the_entry() {
+        assert!(Repl::open_brackets("/* a comment\n") > 0);
+        assert_eq!(Repl::open_brackets("/* a comment\n*/\n"), 0);
+        assert!(Repl::open_brackets("let s = \"first \\\n") > 0);
+        assert_eq!(Repl::open_brackets("let s = \"first \\\nsecond\";\n"), 0);
+    }
+
+    #[test]
+    fn other_errors_end_the_entry() {
+        // An unterminated string can't be finished on the next line, so the error is reported.
+        assert_eq!(Repl::open_brackets("let s = \"first\n"), 0);
+        assert_eq!(Repl::open_brackets("let n = 1.2.3;\n"), 0);
+        assert_eq!(Repl::open_brackets("@\n"), 0);
+    }
+}
@@ -1,328 +1,1369 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::ptr::null_mut;
 use std::rc::Rc;
-use std::sync::Arc;
 use rand::Rng;
-use crate::parser::{Literal, NativeFn};
-use crate::runtime::Scope;
+use crate::parser::{Array, ArrayRef, Literal, NativeFn, Object, ObjectRef, Properties, Statement};
+use crate::runtime::{json, ops, Args, Context, Environment, Scope};
+
+/// The longest string, in UTF-16 code units, that a string method will build. V8's limit.
+const MAX_STRING_LENGTH: usize = (1 << 29) - 24;
+
+/// A native method: gets `this` and the call's arguments.
+type Method = fn(Literal, Vec<Literal>) -> Box<Literal>;
+/// A native method that calls back into the engine, or throws.
+type ContextMethod = fn(&mut dyn Context, Args) -> Result<Literal, Literal>;
 
 pub struct Builtins {
     /* Global scope objects */
     funcs: HashMap<String, Literal>,
 
-    /* Type builtins */
-    array_funcs: HashMap<String, Rc<dyn Fn(Box<Literal>, Vec<Box<Literal>>) -> Literal>>,
-    string_funcs: HashMap<String, Rc<dyn Fn(Box<Literal>, Vec<Box<Literal>>) -> Literal>>,
-    number_funcs: HashMap<String, Rc<dyn Fn(Box<Literal>, Vec<Box<Literal>>) -> Literal>>
+    /* Prototypes */
+    object_prototype: ObjectRef,
+    array_prototype: ObjectRef,
+    string_prototype: ObjectRef,
+    number_prototype: ObjectRef,
+    error_prototypes: HashMap<&'static str, ObjectRef>,
 }
 
 impl Builtins {
 
     /* Console */
-    fn console_log(args: Vec<Box<Literal>>) -> Box<Literal> {
-        if args.len() != 1 {
-            panic!("console.log takes exactly one argument");
-        }
-
-        let str_content = match *args[0] {
-            Literal::String(ref s) => s.clone(),
-            Literal::Number(n) => n.to_string(),
-            Literal::Boolean(b) => b.to_string(),
-            Literal::Null => "null".into(),
-            Literal::Undefined => "undefined".into(),
-            Literal::Object(_) => "[object]".into(),
-            Literal::Array(_) => "[array]".into(),
-            Literal::Function { .. } => "[function]".into(),
-            Literal::NativeFunction(_) => "[native function]".into(),
-        };
+    /// Prints its arguments separated by spaces.
+    fn console_log(_: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let line = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(" ");
+        println!("{line}");
 
-        println!("{}", str_content);
+        Ok(Literal::Undefined)
+    }
 
-        Literal::Undefined.into()
+    /* Errors */
+    /// An error object named `name`, inheriting from `prototype`.
+    pub fn make_error(prototype: &ObjectRef, name: &str, message: impl Into<String>) -> Literal {
+        Literal::Object(Object::new(vec![
+            ("name".into(), Literal::String(name.into()).into()),
+            ("message".into(), Literal::String(message.into()).into()),
+        ], Some(Rc::clone(prototype))))
+    }
+
+    /// A `TypeError`, `RangeError` or other builtin error, inheriting from its constructor's
+    /// `prototype`. Unknown names inherit from `Error.prototype`.
+    pub fn error(&self, name: &str, message: impl Into<String>) -> Literal {
+        let prototype = self.error_prototypes.get(name).unwrap_or(&self.error_prototypes["Error"]);
+        Self::make_error(prototype, name, message)
+    }
+
+    /// Links an error a host built with `native::error`, which had no engine to take the
+    /// prototype from, to its constructor's `prototype`. Anything else passes through.
+    pub fn adopt_error(&self, thrown: Literal) -> Literal {
+        if let Literal::Object(obj) = &thrown {
+            let mut obj = obj.borrow_mut();
+            if obj.proto.is_none() && obj.host.is_none()
+                && let Some(Literal::String(name)) = obj.get("name") {
+                obj.proto = self.error_prototypes.get(name.as_str()).map(Rc::clone);
+            }
+        }
+
+        thrown
+    }
+
+    /// `Error` and friends, building errors that inherit from `prototype`.
+    fn error_constructor(name: &'static str, prototype: ObjectRef) -> Literal {
+        let error_prototype = Rc::clone(&prototype);
+        let func = NativeFn::new(name.into(), Rc::new(move |args| {
+            let message = match args.first() {
+                None | Some(Literal::Undefined) => String::new(),
+                Some(message) => message.to_string(),
+            };
+
+            Self::make_error(&error_prototype, name, message).into()
+        }));
+        func.object.borrow_mut().set("prototype", Literal::Object(prototype));
+
+        Literal::NativeFunction(func)
     }
 
     /* Intrinsics */
-    fn intrinsics_dump(args: Vec<Box<Literal>>) -> Box<Literal> {
+    fn intrinsics_dump(args: Vec<Literal>) -> Box<Literal> {
 
         for arg in args {
-            println!("{:#?}", *arg);
+            println!("{:#?}", arg);
         }
 
         Literal::Undefined.into()
     }
 
-    fn intrinsics_typeof(args: Vec<Box<Literal>>) -> Box<Literal> {
+    fn intrinsics_typeof(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
         if args.len() != 1 {
-            panic!("typeof takes exactly one argument");
-        }
-
-        Literal::String(
-            match *args[0] {
-                Literal::String(_) => "string".into(),
-                Literal::Number(_) => "number".into(),
-                Literal::Boolean(_) => "boolean".into(),
-                Literal::Null => "null".into(),
-                Literal::Undefined => "undefined".into(),
-                Literal::Object(_) => "object".into(),
-                Literal::Array(_) => "array".into(),
-                Literal::Function { .. } => "function".into(),
-                Literal::NativeFunction(_) => "native function".into(),
-            }
-        ).into()
+            return Err(cx.error("TypeError", "intrinsics.typeof takes exactly one argument".into()));
+        }
+
+        Ok(Literal::String(args.get(0).type_name().into()))
     }
 
     /* Arrays */
-    fn array_length(arr: Box<Literal>, _args: Vec<Box<Literal>>) -> Literal {
-        let arr = match *arr {
-            Literal::Array(arr) => arr,
-            _ => panic!("array.length called on non-array")
-        };
+    /// `this` for an array method.
+    fn this_array(cx: &dyn Context, args: &Args, method: &str) -> Result<ArrayRef, Literal> {
+        match &args.this {
+            Literal::Array(arr) => Ok(Rc::clone(arr)),
+            other => Err(cx.error("TypeError", format!("Array.prototype.{method} called on {}", other.type_name())))
+        }
+    }
+
+    /// `this` for an array method that changes the array, which a frozen one can't allow.
+    fn this_mutable_array(cx: &dyn Context, args: &Args, method: &str) -> Result<ArrayRef, Literal> {
+        let arr = Self::this_array(cx, args, method)?;
+        if arr.borrow().frozen {
+            return Err(cx.error("TypeError", format!("Array.prototype.{method} called on a frozen array")));
+        }
+
+        Ok(arr)
+    }
+
+    fn new_array(elements: Vec<Literal>) -> Literal {
+        Literal::Array(Array::new(elements))
+    }
+
+    fn array_push(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "push")?;
+        let mut arr = arr.borrow_mut();
+        arr.extend(args.into_values());
+
+        Ok(Literal::Number(arr.len() as f64))
+    }
+
+    fn array_pop(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "pop")?;
+        let last = arr.borrow_mut().pop();
 
-        Literal::Number(arr.borrow().len() as f64).into()
+        Ok(last.map_or(Literal::Undefined, |last| last))
     }
 
-    fn array_push(arr: Box<Literal>, args: Vec<Box<Literal>>) -> Literal {
-        let arr = match *arr {
-            Literal::Array(arr) => arr,
-            _ => panic!("array.push called on non-array")
+    fn array_shift(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "shift")?;
+        let mut arr = arr.borrow_mut();
+        if arr.is_empty() {
+            return Ok(Literal::Undefined);
+        }
+
+        Ok(arr.remove(0))
+    }
+
+    fn array_unshift(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "unshift")?;
+        let mut arr = arr.borrow_mut();
+        arr.splice(0..0, args.into_values());
+
+        Ok(Literal::Number(arr.len() as f64))
+    }
+
+    fn array_slice(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_array(cx, &args, "slice")?;
+        let arr = arr.borrow();
+        let start = Self::relative_index(&args.get(0), arr.len(), 0);
+        let end = Self::relative_index(&args.get(1), arr.len(), arr.len());
+
+        Ok(Self::new_array(arr[start..end.max(start)].to_vec()))
+    }
+
+    /// `splice(start, deleteCount, ...items)`: removes `deleteCount` elements from `start`, puts
+    /// `items` in their place, and evaluates to the removed elements.
+    fn array_splice(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "splice")?;
+        let mut arr = arr.borrow_mut();
+        let start = Self::relative_index(&args.get(0), arr.len(), 0);
+        let delete_count = match args.len() {
+            0 => 0,
+            1 => arr.len() - start,
+            _ => Self::to_integer(&args.get(1)).clamp(0., (arr.len() - start) as f64) as usize
         };
 
-        if args.len() != 1 {
-            panic!("array.push takes exactly one argument");
+        let items = args.into_values().into_iter().skip(2);
+        let removed = arr.splice(start..start + delete_count, items).collect();
+
+        Ok(Self::new_array(removed))
+    }
+
+    /// A new array of `this`'s elements followed by each argument, with array arguments spread.
+    fn array_concat(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let mut elements = Self::this_array(cx, &args, "concat")?.borrow().clone();
+        for arg in args.iter() {
+            match arg {
+                Literal::Array(other) => elements.extend(other.borrow().iter().cloned()),
+                other => elements.push(other.clone())
+            }
         }
 
-        arr.borrow_mut().push(args[0].clone());
-        Literal::Number(arr.borrow().len() as f64).into()
+        Ok(Self::new_array(elements))
     }
 
-    fn array_pop(arr: Box<Literal>, args: Vec<Box<Literal>>) -> Literal {
-        let arr = match *arr {
-            Literal::Array(arr) => arr,
-            _ => panic!("array.push called on non-array")
+    fn array_index_of(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_array(cx, &args, "indexOf")?;
+        let arr = arr.borrow();
+        let search = args.get(0);
+        let from = Self::relative_index(&args.get(1), arr.len(), 0);
+
+        let found = (from..arr.len()).find(|&i| arr[i] == search);
+        Ok(Literal::Number(found.map_or(-1., |index| index as f64)))
+    }
+
+    fn array_last_index_of(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_array(cx, &args, "lastIndexOf")?;
+        let arr = arr.borrow();
+        let search = args.get(0);
+
+        // Unlike `relative_index`, a position past either end leaves nothing to search.
+        let from = match args.get(1) {
+            Literal::Undefined => arr.len() as f64 - 1.,
+            from => {
+                let from = Self::to_integer(&from);
+                if from < 0. { arr.len() as f64 + from } else { from.min(arr.len() as f64 - 1.) }
+            }
         };
 
-        if args.len() != 1 {
-            panic!("array.push takes exactly one argument");
+        let found = (from >= 0.).then(|| (0..=from as usize).rev().find(|&i| arr[i] == search)).flatten();
+        Ok(Literal::Number(found.map_or(-1., |index| index as f64)))
+    }
+
+    /// Like `indexOf`, except that `NaN` finds `NaN`.
+    fn array_includes(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_array(cx, &args, "includes")?;
+        let arr = arr.borrow();
+        let search = args.get(0);
+        let from = Self::relative_index(&args.get(1), arr.len(), 0);
+
+        let same = |element: &Literal| match (element, &search) {
+            (Literal::Number(a), Literal::Number(b)) if a.is_nan() && b.is_nan() => true,
+            (element, search) => element == search
+        };
+
+        Ok(Literal::Boolean(arr[from..].iter().any(same)))
+    }
+
+    fn array_fill(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "fill")?;
+        {
+            let mut elements = arr.borrow_mut();
+            let start = Self::relative_index(&args.get(1), elements.len(), 0);
+            let end = Self::relative_index(&args.get(2), elements.len(), elements.len());
+
+            let value = args.get(0);
+            for element in elements.iter_mut().take(end).skip(start) {
+                *element = value.clone();
+            }
         }
 
+        Ok(Literal::Array(arr))
+    }
 
-        let lit = arr.borrow_mut().pop().unwrap_or_else(|| panic!("Array.pop called on empty array."));
-        *lit
+    fn flatten_into(flat: &mut Vec<Literal>, elements: &[Literal], depth: f64) {
+        for element in elements {
+            match element {
+                Literal::Array(inner) if depth >= 1. => Self::flatten_into(flat, &inner.borrow(), depth - 1.),
+                _ => flat.push(element.clone())
+            }
+        }
     }
 
-    fn array_join(arr: Box<Literal>, args: Vec<Box<Literal>>) -> Literal {
-        let arr = match *arr {
-            Literal::Array(arr) => arr,
-            _ => panic!("array.join called on non-array")
+    /// A new array with nested arrays spread into it, `depth` levels deep (1 by default).
+    fn array_flat(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_array(cx, &args, "flat")?;
+        let depth = match args.get(0) {
+            Literal::Undefined => 1.,
+            depth => Self::to_integer(&depth)
         };
 
-        let delim = match args.len() {
-            0 => ",".into(),
-            1 => {
-                let delim = args[0].clone();
-                match *delim {
-                    Literal::String(delim) => delim,
-                    _ => panic!("array.join expects a string as the delimiter")
-                }
+        let mut flat = Vec::new();
+        Self::flatten_into(&mut flat, &arr.borrow(), depth);
+        Ok(Self::new_array(flat))
+    }
+
+    /// Joins with `separator`, stringifying each element. `null` and `undefined` become empty,
+    /// nested arrays are joined with commas, and an array met again inside itself is empty too.
+    fn join_elements(cx: &mut dyn Context, arr: &ArrayRef, separator: &str, joining: &mut Vec<*const RefCell<Array>>) -> Result<String, Literal> {
+        if joining.contains(&Rc::as_ptr(arr)) {
+            return Ok(String::new());
+        }
+        joining.push(Rc::as_ptr(arr));
+
+        // Copied out, since an element's `toString` may change the array.
+        let elements = arr.borrow().clone();
+        let mut parts = Vec::with_capacity(elements.len());
+        for element in elements {
+            parts.push(match element {
+                Literal::Null | Literal::Undefined => String::new(),
+                Literal::Array(inner) => Self::join_elements(cx, &inner, ",", joining)?,
+                other => cx.to_js_string(other)?
+            });
+        }
+
+        joining.pop();
+        Ok(parts.join(separator))
+    }
+
+    fn array_join(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_array(cx, &args, "join")?;
+        let separator = match args.get(0) {
+            Literal::Undefined => ",".into(),
+            separator => cx.to_js_string(separator)?
+        };
+
+        Ok(Literal::String(Self::join_elements(cx, &arr, &separator, &mut Vec::new())?))
+    }
+
+    /// Arrays convert to strings the way `join` does with its default separator.
+    fn array_to_string(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Self::array_join(cx, Args::new(args.this, Vec::new()))
+    }
+
+    fn array_reverse(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = Self::this_mutable_array(cx, &args, "reverse")?;
+        arr.borrow_mut().reverse();
+
+        Ok(Literal::Array(arr))
+    }
+
+    fn array_is_array(args: Vec<Literal>) -> Box<Literal> {
+        let is_array = matches!(args.first(), Some(Literal::Array(_)));
+        Literal::Boolean(is_array).into()
+    }
+
+    fn array_of(args: Vec<Literal>) -> Box<Literal> {
+        Self::new_array(args).into()
+    }
+
+    /// `Array.from(items, mapFn)`: copies an array, splits a string into characters, or reads
+    /// `0..length` from an array-like object, then maps each element if given a function.
+    fn array_from(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let elements: Vec<Literal> = match args.get(0) {
+            Literal::Array(arr) => arr.borrow().iter().cloned().collect(),
+            Literal::String(str) => str.chars().map(|c| Literal::String(c.into())).collect(),
+            Literal::Object(obj) => {
+                let obj = obj.borrow();
+                let len = obj.get("length").map_or(0., |len| Self::to_integer(&len)).max(0.) as usize;
+                (0..len).map(|i| obj.get(&i.to_string()).unwrap_or(Literal::Undefined)).collect()
+            },
+            Literal::Null | Literal::Undefined => {
+                return Err(cx.error("TypeError", format!("{} is not iterable", args.get(0))));
             },
-            _ => panic!("array.join takes at most one argument")
+            _ => Vec::new()
         };
 
-        let mut str = String::new();
-        for (i, item) in arr.borrow().iter().enumerate() {
-            if i > 0 {
-                str.push_str(&delim);
+        let elements = match args.get(1) {
+            Literal::Undefined => elements,
+            func @ (Literal::Function { .. } | Literal::NativeFunction(_)) => elements.into_iter().enumerate()
+                .map(|(i, element)| cx.call(&func, Literal::Undefined, vec![element, Literal::Number(i as f64)]))
+                .collect::<Result<_, _>>()?,
+            other => return Err(cx.error("TypeError", format!("{other} is not a function")))
+        };
+
+        Ok(Self::new_array(elements))
+    }
+
+    /// The array a callback-taking method was called on, and the callback.
+    fn array_and_callback(cx: &dyn Context, args: &Args, method: &str) -> Result<(ArrayRef, Literal), Literal> {
+        let arr = Self::this_array(cx, args, method)?;
+
+        match args.get(0) {
+            callback @ (Literal::Function { .. } | Literal::NativeFunction(_)) => Ok((arr, callback)),
+            other => Err(cx.error("TypeError", format!("{other} is not a function")))
+        }
+    }
+
+    /// Calls `callback(element, index, array)` for each element, with the method's second
+    /// argument as `this`, until `visit` returns `Some`. The array may change under the callback,
+    /// so elements are read one at a time and anything appended is skipped.
+    fn array_scan<T>(cx: &mut dyn Context, args: &Args, method: &str, mut visit: impl FnMut(Literal, usize, Literal) -> Option<T>) -> Result<Option<T>, Literal> {
+        let (arr, callback) = Self::array_and_callback(cx, args, method)?;
+        let this = args.get(1);
+
+        let len = arr.borrow().len();
+        for index in 0..len {
+            let Some(element) = arr.borrow().get(index).cloned() else {
+                break;
+            };
+
+            let result = cx.call(&callback, this.clone(), vec![element.clone(), Literal::Number(index as f64), Literal::Array(Rc::clone(&arr))])?;
+            if let Some(found) = visit(element, index, result) {
+                return Ok(Some(found));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn array_for_each(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Self::array_scan(cx, &args, "forEach", |_, _, _| None::<()>)?;
+        Ok(Literal::Undefined)
+    }
+
+    fn array_map(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let mut mapped = Vec::new();
+        Self::array_scan(cx, &args, "map", |_, _, result| {
+            mapped.push(result);
+            None::<()>
+        })?;
+
+        Ok(Literal::Array(Array::new(mapped)))
+    }
+
+    fn array_filter(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let mut kept = Vec::new();
+        Self::array_scan(cx, &args, "filter", |element, _, result| {
+            if result.truthy() {
+                kept.push(element);
             }
+            None::<()>
+        })?;
+
+        Ok(Literal::Array(Array::new(kept)))
+    }
+
+    fn array_find(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let found = Self::array_scan(cx, &args, "find", |element, _, result| result.truthy().then_some(element))?;
+        Ok(found.unwrap_or(Literal::Undefined))
+    }
 
-            if let Literal::String(s) = *item.clone() {
-                str.push_str(s.as_ref());
-            } else {
-                panic!("array.join expects all elements to be strings");
+    fn array_find_index(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let found = Self::array_scan(cx, &args, "findIndex", |_, index, result| result.truthy().then_some(index))?;
+        Ok(Literal::Number(found.map_or(-1., |index| index as f64)))
+    }
+
+    fn array_some(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let found = Self::array_scan(cx, &args, "some", |_, _, result| result.truthy().then_some(()))?;
+        Ok(Literal::Boolean(found.is_some()))
+    }
+
+    fn array_every(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let failed = Self::array_scan(cx, &args, "every", |_, _, result| (!result.truthy()).then_some(()))?;
+        Ok(Literal::Boolean(failed.is_none()))
+    }
+
+    /// `reduce` and `reduceRight`: folds with `callback(accumulator, element, index, array)`,
+    /// starting from the second argument or, failing that, the first element visited.
+    fn array_fold(cx: &mut dyn Context, args: Args, method: &str, from_right: bool) -> Result<Literal, Literal> {
+        let (arr, callback) = Self::array_and_callback(cx, &args, method)?;
+
+        let len = arr.borrow().len();
+        let mut indices: Vec<usize> = (0..len).collect();
+        if from_right {
+            indices.reverse();
+        }
+
+        let mut indices = indices.into_iter();
+        let mut accumulator = if args.len() >= 2 {
+            args.get(1)
+        } else {
+            match indices.next() {
+                Some(first) => arr.borrow()[first].clone(),
+                None => return Err(cx.error("TypeError", "Reduce of empty array with no initial value".into()))
             }
+        };
+
+        for index in indices {
+            let Some(element) = arr.borrow().get(index).cloned() else {
+                continue;
+            };
+
+            accumulator = cx.call(&callback, Literal::Undefined, vec![accumulator, element, Literal::Number(index as f64), Literal::Array(Rc::clone(&arr))])?;
         }
 
-        Literal::String(str).into()
+        Ok(accumulator)
+    }
+
+    fn array_reduce(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Self::array_fold(cx, args, "reduce", false)
+    }
+
+    fn array_reduce_right(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Self::array_fold(cx, args, "reduceRight", true)
     }
 
-    fn array_reverse(arr: Box<Literal>, _args: Vec<Box<Literal>>) -> Literal {
-        let arr = match *arr {
-            Literal::Array(elems) => elems,
-            _ => panic!("Array.reverse() called on non-array.")
+    /// Sorts in place with `comparator(a, b)`, which orders `a` first when negative. Without one,
+    /// elements compare as strings and `undefined` goes last.
+    fn array_sort(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let arr = &Self::this_mutable_array(cx, &args, "sort")?;
+
+        let comparator = match args.get(0) {
+            Literal::Undefined => None,
+            func @ (Literal::Function { .. } | Literal::NativeFunction(_)) => Some(func),
+            other => return Err(cx.error("TypeError", format!("The comparison function must be either a function or undefined, not {other}")))
         };
 
-        arr.borrow_mut().reverse();
-        Literal::Array(arr).into()
+        // Once the comparator throws, the rest of the sort just keeps the current order.
+        let mut thrown = None;
+        let mut compare = |a: &Literal, b: &Literal| match (&comparator, a, b) {
+            _ if thrown.is_some() => 0.,
+            (_, Literal::Undefined, Literal::Undefined) => 0.,
+            (_, Literal::Undefined, _) => 1.,
+            (_, _, Literal::Undefined) => -1.,
+            (Some(func), a, b) => match cx.call(func, Literal::Undefined, vec![a.clone(), b.clone()]) {
+                Ok(order) => ops::to_number(&order),
+                Err(err) => {
+                    thrown = Some(err);
+                    0.
+                }
+            },
+            // Without a comparator, elements sort as strings, by UTF-16 code units.
+            (None, a, b) => match (cx.to_js_string(a.clone()), cx.to_js_string(b.clone())) {
+                (Ok(a), Ok(b)) => match a.encode_utf16().cmp(b.encode_utf16()) {
+                    std::cmp::Ordering::Less => -1.,
+                    std::cmp::Ordering::Equal => 0.,
+                    std::cmp::Ordering::Greater => 1.
+                },
+                (Err(err), _) | (_, Err(err)) => {
+                    thrown = Some(err);
+                    0.
+                }
+            }
+        };
+
+        // Taken out while sorting, so the comparator sees an empty array rather than a borrowed one.
+        let elements = std::mem::take(&mut arr.borrow_mut().elements);
+        arr.borrow_mut().elements = Self::merge_sort(elements, &mut compare);
+
+        match thrown {
+            Some(err) => Err(err),
+            None => Ok(Literal::Array(Rc::clone(arr)))
+        }
+    }
+
+    /// A stable merge sort. Unlike `slice::sort_by`, it copes with comparators that aren't
+    /// consistent, which scripts are free to write.
+    fn merge_sort(mut elements: Vec<Literal>, compare: &mut dyn FnMut(&Literal, &Literal) -> f64) -> Vec<Literal> {
+        if elements.len() <= 1 {
+            return elements;
+        }
+
+        let right = elements.split_off(elements.len() / 2);
+        let left = Self::merge_sort(elements, compare);
+        let right = Self::merge_sort(right, compare);
+
+        let mut merged = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+            let next = if compare(a, b) > 0. { right.next() } else { left.next() };
+            merged.extend(next);
+        }
+        merged.extend(left.chain(right));
+
+        merged
     }
 
     /* Strings */
-    fn string_split(str: Box<Literal>, args: Vec<Box<Literal>>) -> Literal {
-        let str = match *str {
-            Literal::String(str) => str,
-            _ => panic!("string.split called on non-string")
+
+    // JS strings index by UTF-16 code unit, so positions go through these rather than byte or
+    // char offsets. Lone surrogates can't be represented in a Rust string and become U+FFFD.
+    fn units(str: &str) -> Vec<u16> {
+        str.encode_utf16().collect()
+    }
+
+    fn from_units(units: &[u16]) -> Literal {
+        Literal::String(String::from_utf16_lossy(units))
+    }
+
+    /// ToIntegerOrInfinity: NaN becomes 0 and fractions are truncated.
+    fn to_integer(value: &Literal) -> f64 {
+        let num = ops::to_number(value);
+        if num.is_nan() { 0. } else { num.trunc() }
+    }
+
+    /// A position for `slice` and friends: negative values count back from `len`, and the result
+    /// is clamped to `0..=len`. `undefined` gives `default`.
+    fn relative_index(value: &Literal, len: usize, default: usize) -> usize {
+        if let Literal::Undefined = value {
+            return default;
+        }
+
+        let index = Self::to_integer(value);
+        let index = if index < 0. { len as f64 + index } else { index };
+        index.clamp(0., len as f64) as usize
+    }
+
+    /// A position clamped to `0..=len`, as `substring` and the search methods take them.
+    fn clamped_index(value: &Literal, len: usize, default: usize) -> usize {
+        match value {
+            Literal::Undefined => default,
+            value => Self::to_integer(value).clamp(0., len as f64) as usize
+        }
+    }
+
+    /// `this` for a string method, converted to a string.
+    fn this_string(cx: &dyn Context, args: &Args, method: &str) -> Result<String, Literal> {
+        match &args.this {
+            Literal::String(str) => Ok(str.clone()),
+            Literal::Null | Literal::Undefined => {
+                Err(cx.error("TypeError", format!("String.prototype.{method} called on {}", args.this.type_name())))
+            },
+            other => Ok(other.to_string())
+        }
+    }
+
+    /// The first index at or after `from` where `needle` occurs in `haystack`.
+    fn find_units(haystack: &[u16], needle: &[u16], from: usize) -> Option<usize> {
+        (from..=haystack.len().checked_sub(needle.len())?).find(|&i| haystack[i..].starts_with(needle))
+    }
+
+    fn string_char_at(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "charAt")?);
+        let index = Self::to_integer(&args.get(0));
+
+        Ok(match units.get(index as usize) {
+            Some(&unit) if index >= 0. => Self::from_units(&[unit]),
+            _ => Literal::String(String::new())
+        })
+    }
+
+    fn string_char_code_at(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "charCodeAt")?);
+        let index = Self::to_integer(&args.get(0));
+
+        Ok(Literal::Number(match units.get(index as usize) {
+            Some(&unit) if index >= 0. => unit as f64,
+            _ => f64::NAN
+        }))
+    }
+
+    fn string_code_point_at(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "codePointAt")?);
+        let index = Self::to_integer(&args.get(0));
+        if index < 0. || index as usize >= units.len() {
+            return Ok(Literal::Undefined);
+        }
+
+        let index = index as usize;
+        let code_point = match char::decode_utf16(units[index..].iter().copied()).next() {
+            Some(Ok(c)) => c as u32,
+            _ => units[index] as u32
+        };
+
+        Ok(Literal::Number(code_point as f64))
+    }
+
+    fn string_index_of(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "indexOf")?);
+        let needle = Self::units(&cx.to_js_string(args.get(0))?);
+        let from = Self::clamped_index(&args.get(1), units.len(), 0);
+
+        let found = Self::find_units(&units, &needle, from);
+        Ok(Literal::Number(found.map_or(-1., |index| index as f64)))
+    }
+
+    fn string_last_index_of(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "lastIndexOf")?);
+        let needle = Self::units(&cx.to_js_string(args.get(0))?);
+
+        // A missing or NaN position searches from the very end.
+        let from = match ops::to_number(&args.get(1)) {
+            num if num.is_nan() => units.len(),
+            num => num.trunc().clamp(0., units.len() as f64) as usize
+        };
+
+        let last_start = units.len().checked_sub(needle.len());
+        let found = last_start.and_then(|last| (0..=from.min(last)).rev().find(|&i| units[i..].starts_with(&needle)));
+        Ok(Literal::Number(found.map_or(-1., |index| index as f64)))
+    }
+
+    fn string_includes(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "includes")?);
+        let needle = Self::units(&cx.to_js_string(args.get(0))?);
+        let from = Self::clamped_index(&args.get(1), units.len(), 0);
+
+        Ok(Literal::Boolean(Self::find_units(&units, &needle, from).is_some()))
+    }
+
+    fn string_starts_with(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "startsWith")?);
+        let needle = Self::units(&cx.to_js_string(args.get(0))?);
+        let start = Self::clamped_index(&args.get(1), units.len(), 0);
+
+        Ok(Literal::Boolean(units[start..].starts_with(&needle)))
+    }
+
+    fn string_ends_with(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "endsWith")?);
+        let needle = Self::units(&cx.to_js_string(args.get(0))?);
+        let end = Self::clamped_index(&args.get(1), units.len(), units.len());
+
+        Ok(Literal::Boolean(units[..end].ends_with(&needle)))
+    }
+
+    fn string_slice(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "slice")?);
+        let start = Self::relative_index(&args.get(0), units.len(), 0);
+        let end = Self::relative_index(&args.get(1), units.len(), units.len());
+
+        Ok(Self::from_units(&units[start..end.max(start)]))
+    }
+
+    fn string_substring(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "substring")?);
+        let start = Self::clamped_index(&args.get(0), units.len(), 0);
+        let end = Self::clamped_index(&args.get(1), units.len(), units.len());
+
+        Ok(Self::from_units(&units[start.min(end)..start.max(end)]))
+    }
+
+    fn string_to_upper_case(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Ok(Literal::String(Self::this_string(cx, &args, "toUpperCase")?.to_uppercase()))
+    }
+
+    fn string_to_lower_case(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Ok(Literal::String(Self::this_string(cx, &args, "toLowerCase")?.to_lowercase()))
+    }
+
+    fn string_trim(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Ok(Literal::String(Self::this_string(cx, &args, "trim")?.trim_matches(ops::is_js_whitespace).into()))
+    }
+
+    fn string_trim_start(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Ok(Literal::String(Self::this_string(cx, &args, "trimStart")?.trim_start_matches(ops::is_js_whitespace).into()))
+    }
+
+    fn string_trim_end(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Ok(Literal::String(Self::this_string(cx, &args, "trimEnd")?.trim_end_matches(ops::is_js_whitespace).into()))
+    }
+
+    /// The filler `padStart`/`padEnd` add to reach the target length, or `None` if no padding is needed.
+    fn padding(cx: &mut dyn Context, args: &Args, units: &[u16]) -> Result<Option<Vec<u16>>, Literal> {
+        let target = Self::to_integer(&args.get(0));
+        let filler = match args.get(1) {
+            Literal::Undefined => vec![b' ' as u16],
+            filler => Self::units(&cx.to_js_string(filler)?)
+        };
+
+        if target <= units.len() as f64 || filler.is_empty() {
+            return Ok(None);
+        }
+        if target > MAX_STRING_LENGTH as f64 {
+            return Err(cx.error("RangeError", "Invalid string length".into()));
+        }
+
+        let needed = target as usize - units.len();
+        Ok(Some(filler.iter().copied().cycle().take(needed).collect()))
+    }
+
+    fn string_pad_start(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "padStart")?);
+        Ok(match Self::padding(cx, &args, &units)? {
+            Some(padding) => Self::from_units(&[padding, units].concat()),
+            None => Self::from_units(&units)
+        })
+    }
+
+    fn string_pad_end(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let units = Self::units(&Self::this_string(cx, &args, "padEnd")?);
+        Ok(match Self::padding(cx, &args, &units)? {
+            Some(padding) => Self::from_units(&[units, padding].concat()),
+            None => Self::from_units(&units)
+        })
+    }
+
+    fn string_repeat(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let str = Self::this_string(cx, &args, "repeat")?;
+        let count = Self::to_integer(&args.get(0));
+        if count < 0. || count.is_infinite() {
+            return Err(cx.error("RangeError", format!("Invalid count value: {count}")));
+        }
+        if count * str.encode_utf16().count() as f64 > MAX_STRING_LENGTH as f64 {
+            return Err(cx.error("RangeError", "Invalid string length".into()));
+        }
+
+        Ok(Literal::String(str.repeat(count as usize)))
+    }
+
+    /// Expands the `$$`, `$&`, `` $` `` and `$'` patterns in a string replacement for the match
+    /// at byte offset `at`.
+    fn expand_replacement(template: &str, str: &str, matched: &str, at: usize) -> String {
+        let mut expanded = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                expanded.push(c);
+                continue;
+            }
+
+            match chars.peek() {
+                Some('$') => expanded.push('$'),
+                Some('&') => expanded.push_str(matched),
+                Some('`') => expanded.push_str(&str[..at]),
+                Some('\'') => expanded.push_str(&str[at + matched.len()..]),
+                _ => {
+                    expanded.push('$');
+                    continue;
+                }
+            }
+            chars.next();
+        }
+
+        expanded
+    }
+
+    /// `replace` and `replaceAll`. The replacement is either a string, with `$` patterns, or a
+    /// function called with the match, its position and the whole string.
+    fn string_replace_matches(cx: &mut dyn Context, args: Args, method: &str, all: bool) -> Result<Literal, Literal> {
+        let str = Self::this_string(cx, &args, method)?;
+        let pattern = cx.to_js_string(args.get(0))?;
+        let replacement = args.get(1);
+        // A replacement that isn't a function is converted once, up front.
+        let template = match &replacement {
+            Literal::Function { .. } | Literal::NativeFunction(_) => None,
+            template => Some(cx.to_js_string(template.clone())?)
+        };
+
+        let matches: Vec<usize> = if all {
+            str.match_indices(pattern.as_str()).map(|(at, _)| at).collect()
+        } else {
+            str.find(pattern.as_str()).into_iter().collect()
         };
 
-        let delim = match args.len() {
-            0 => " ".into(),
-            1 => {
-                let delim = args[0].clone();
-                match *delim {
-                    Literal::String(delim) => delim,
-                    _ => panic!("string.split expects a string as the delimiter")
+        let mut replaced = String::new();
+        let mut last = 0;
+        for at in matches {
+            replaced.push_str(&str[last..at]);
+            let expansion = match &template {
+                Some(template) => Self::expand_replacement(template, &str, &pattern, at),
+                None => {
+                    let position = Literal::Number(str[..at].encode_utf16().count() as f64);
+                    let args = vec![Literal::String(pattern.clone()), position, Literal::String(str.clone())];
+                    let result = cx.call(&replacement, Literal::Undefined, args)?;
+                    cx.to_js_string(result)?
                 }
+            };
+            replaced.push_str(&expansion);
+            last = at + pattern.len();
+        }
+        replaced.push_str(&str[last..]);
+
+        Ok(Literal::String(replaced))
+    }
+
+    fn string_replace(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Self::string_replace_matches(cx, args, "replace", false)
+    }
+
+    fn string_replace_all(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Self::string_replace_matches(cx, args, "replaceAll", true)
+    }
+
+    fn string_concat(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let mut str = Self::this_string(cx, &args, "concat")?;
+        for arg in args.iter() {
+            str.push_str(&cx.to_js_string(arg.clone())?);
+        }
+
+        Ok(Literal::String(str))
+    }
+
+    fn string_split(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let str = Self::this_string(cx, &args, "split")?;
+        let limit = match args.get(1) {
+            Literal::Undefined => usize::MAX,
+            limit => Self::to_integer(&limit).max(0.) as usize
+        };
+
+        let separator = match args.get(0) {
+            Literal::Undefined => None,
+            separator => Some(cx.to_js_string(separator)?)
+        };
+
+        let parts: Vec<Literal> = match separator {
+            None => vec![Literal::String(str)],
+            // An empty separator splits into code units.
+            Some(separator) if separator.is_empty() => {
+                Self::units(&str).chunks(1).map(Self::from_units).collect()
             },
-            _ => panic!("string.split takes at most one argument")
+            Some(separator) => str.split(separator.as_str()).map(Literal::from).collect()
         };
 
-        let chars = str.split(delim.as_str()).map(|s| s.to_owned()).collect::<Vec<_>>();
+        let parts = parts.into_iter().take(limit).collect();
+        Ok(Literal::Array(Array::new(parts)))
+    }
+
+    /// `String.fromCharCode(...codes)`, each code truncated to 16 bits.
+    fn string_from_char_code(args: Vec<Literal>) -> Box<Literal> {
+        let units: Vec<u16> = args.iter().map(|code| {
+            let code = ops::to_number(code);
+            if code.is_finite() { code.trunc().rem_euclid(65536.) as u16 } else { 0 }
+        }).collect();
 
-        Literal::Array(Rc::new(RefCell::new(
-            chars.into_iter().map(|s| Box::new(Literal::String(s))).collect()
-        )))
+        Self::from_units(&units).into()
     }
 
-    /* Number */
-    fn number_tostring(num: Box<Literal>, _args: Vec<Box<Literal>>) -> Literal {
-        let num = match *num {
-            Literal::Number(n) => n,
-            _ => panic!("Number.toString() called on non-number.")
+    /// `String.raw(strings, ...substitutions)`: interleaves `strings.raw` with the substitutions,
+    /// which leaves a tagged template's escapes as they were written.
+    fn string_raw(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let raw = match args.get(0) {
+            Literal::Array(strings) => strings.borrow().get_own("raw"),
+            Literal::Object(obj) => obj.borrow().get("raw"),
+            other => return Err(cx.error("TypeError", format!("Cannot convert {} to object", other.type_name())))
+        };
+        let Some(Literal::Array(raw)) = raw else {
+            return Err(cx.error("TypeError", "String.raw needs an object with a raw array".into()));
         };
 
-        Literal::String(num.to_string()).into()
+        let mut result = String::new();
+        for (i, part) in raw.borrow().iter().enumerate() {
+            if i > 0 && i < args.len() {
+                result.push_str(&args.get(i).to_string());
+            }
+            result.push_str(&part.to_string());
+        }
+
+        Ok(Literal::String(result))
     }
 
-    /* Objects */
-    fn object_keys(args: Vec<Box<Literal>>) -> Box<Literal> {
-        if args.len() != 1 {
-            panic!("object.keys takes exactly one argument");
+    /* Number */
+    fn number_tostring(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        match args.this {
+            num @ Literal::Number(_) => Ok(Literal::String(num.to_string())),
+            other => Err(cx.error("TypeError", format!("Number.prototype.toString called on {}", other.type_name())))
         }
+    }
 
-        let obj = args[0].clone();
-        let obj = match *obj {
+    /* Objects */
+    fn object_keys(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let obj = match args.get(0) {
             Literal::Object(obj) => obj,
-            _ => panic!("object.keys called on non-object")
+            other => return Err(cx.error("TypeError", format!("Object.keys called on {}", other.type_name())))
         };
 
-        let keys = obj.iter().map(|(k, _)| Box::new(Literal::String(k.clone()))).collect();
+        let keys = obj.borrow().properties.iter().map(|(k, _)| Literal::String(k.clone())).collect();
 
-        Literal::Array(Rc::new(RefCell::new(keys))).into()
+        Ok(Self::new_array(keys))
     }
 
-    /* Math */
-    fn math_sqrt(args: Vec<Box<Literal>>) -> Box<Literal> {
-        if args.len() != 1 {
-            panic!("Math.sqrt takes exactly one argument");
+    fn object_create(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        let proto = match args.get(0) {
+            Literal::Object(proto) => Some(proto),
+            Literal::Null => None,
+            other => return Err(cx.error("TypeError", format!("Object prototype may only be an object or null, not {}", other.type_name())))
+        };
+
+        Ok(Literal::Object(Object::new(Vec::new(), proto)))
+    }
+
+    fn object_get_prototype_of(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        match args.get(0) {
+            Literal::Object(obj) => Ok(obj.borrow().proto.clone().map_or(Literal::Null, Literal::Object)),
+            other => Err(cx.error("TypeError", format!("Object.getPrototypeOf called on {}", other.type_name())))
         }
+    }
+
+    fn object_has_own_property(obj: Literal, args: Vec<Literal>) -> Box<Literal> {
+        let name = args.first().map_or_else(|| "undefined".into(), |arg| arg.to_string());
+        let own = match obj {
+            Literal::Object(obj) => obj.borrow().get_own(&name).is_some(),
+            Literal::Function { object, .. } => object.borrow().get_own(&name).is_some(),
+            Literal::NativeFunction(f) => f.object.borrow().get_own(&name).is_some(),
+            _ => false
+        };
+
+        Literal::Boolean(own).into()
+    }
 
-        let num = args[0].clone();
-        let num = match *num {
-            Literal::Number(n) => n,
-            _ => panic!("Math.sqrt called on non-number")
+    /// `"[object Type]"`, which is what objects convert to unless they say otherwise.
+    fn object_to_string(obj: Literal, _: Vec<Literal>) -> Box<Literal> {
+        let tag = match obj {
+            Literal::Array(_) => "Array",
+            Literal::Function { .. } | Literal::NativeFunction(_) => "Function",
+            Literal::Null => "Null",
+            Literal::Undefined => "Undefined",
+            Literal::String(_) => "String",
+            Literal::Number(_) => "Number",
+            Literal::BigInt(_) => "BigInt",
+            Literal::Boolean(_) => "Boolean",
+            _ => "Object"
         };
 
-        Literal::Number(num.sqrt()).into()
+        Literal::String(format!("[object {tag}]")).into()
+    }
+
+    fn object_value_of(obj: Literal, _: Vec<Literal>) -> Box<Literal> {
+        obj.into()
+    }
+
+    /* Constructors */
+    /// `Object(value)` passes objects through; anything else gets a new empty object.
+    fn object_constructor(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        Ok(match args.get(0) {
+            obj @ (Literal::Object(_) | Literal::Array(_) | Literal::Function { .. } | Literal::NativeFunction(_)) => obj,
+            _ => cx.new_object(Vec::new())
+        })
+    }
+
+    /// `Array(a, b, ...)` holds its arguments, but `Array(n)` is `n` empty slots.
+    fn array_constructor(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+        if let (1, Literal::Number(len)) = (args.len(), args.get(0)) {
+            if len < 0. || len.fract() != 0. || len > u32::MAX as f64 {
+                return Err(cx.error("RangeError", "Invalid array length".into()));
+            }
+
+            return Ok(Self::new_array(vec![Literal::Undefined; len as usize]));
+        }
+
+        Ok(Self::new_array(args.into_values()))
+    }
+
+    fn string_constructor(args: Vec<Literal>) -> Box<Literal> {
+        let str = args.first().map_or_else(String::new, |arg| arg.to_string());
+        Literal::String(str).into()
+    }
+
+    fn number_constructor(args: Vec<Literal>) -> Box<Literal> {
+        let num = args.first().map_or(0., ops::to_number);
+        Literal::Number(num).into()
+    }
+
+    /* Math */
+
+    /// The argument at `index` converted as unary `+` would, calling `valueOf` on objects;
+    /// missing arguments are `NaN`.
+    fn number_arg(cx: &mut dyn Context, args: &Args, index: usize) -> Result<f64, Literal> {
+        cx.to_js_number(args.get(index))
+    }
+
+    /// ToUint32: wraps the integer part modulo 2^32, with `NaN` and infinities becoming 0.
+    fn to_uint32(num: f64) -> u32 {
+        if num.is_finite() { num.trunc().rem_euclid(4294967296.) as u32 } else { 0 }
+    }
+
+    /// A `Math` function of one number.
+    fn math_unary(name: &str, func: fn(f64) -> f64) -> (String, Box<Literal>) {
+        let native = NativeFn::host(format!("Math.{name}"), Rc::new(move |cx, args| {
+            Ok(Literal::Number(func(Self::number_arg(cx, &args, 0)?)))
+        }));
+
+        (name.into(), Literal::NativeFunction(native).into())
+    }
+
+    /// A `Math` function of two numbers.
+    fn math_binary(name: &str, func: fn(f64, f64) -> f64) -> (String, Box<Literal>) {
+        let native = NativeFn::host(format!("Math.{name}"), Rc::new(move |cx, args| {
+            let a = Self::number_arg(cx, &args, 0)?;
+            Ok(Literal::Number(func(a, Self::number_arg(cx, &args, 1)?)))
+        }));
+
+        (name.into(), Literal::NativeFunction(native).into())
+    }
+
+    /// A `Math` function of any number of numbers, all converted before any is compared.
+    fn math_variadic(name: &str, func: fn(Vec<f64>) -> f64) -> (String, Box<Literal>) {
+        let native = NativeFn::host(format!("Math.{name}"), Rc::new(move |cx, args| {
+            let nums = args.into_values().into_iter()
+                .map(|arg| cx.to_js_number(arg))
+                .collect::<Result<_, _>>()?;
+            Ok(Literal::Number(func(nums)))
+        }));
+
+        (name.into(), Literal::NativeFunction(native).into())
+    }
+
+    /// Rounds halves up towards +Infinity, unlike `f64::round`, keeping the sign of zero.
+    fn math_round(num: f64) -> f64 {
+        if !num.is_finite() || num.fract() == 0. {
+            return num;
+        }
+        if (-0.5..0.).contains(&num) {
+            return -0.;
+        }
+
+        let floor = num.floor();
+        if num - floor >= 0.5 { floor + 1. } else { floor }
+    }
+
+    /// `NaN` for `NaN`, the same zero for either zero, and otherwise +/-1.
+    fn math_sign(num: f64) -> f64 {
+        if num.is_nan() || num == 0. { num } else { num.signum() }
+    }
+
+    /// Like `f64::powf`, except that a `NaN` exponent always gives `NaN`, as does +/-1 to an
+    /// infinite power.
+    fn math_pow(base: f64, exponent: f64) -> f64 {
+        if exponent.is_nan() || (base.abs() == 1. && exponent.is_infinite()) {
+            return f64::NAN;
+        }
+
+        base.powf(exponent)
+    }
+
+    /// The largest argument. `NaN` wins over everything, +0 beats -0, and no arguments give -Infinity.
+    fn math_max(nums: Vec<f64>) -> f64 {
+        nums.into_iter().fold(f64::NEG_INFINITY, |max, num| match (max, num) {
+            _ if max.is_nan() || num.is_nan() => f64::NAN,
+            (0., 0.) if max.is_sign_negative() => num,
+            _ if num > max => num,
+            _ => max
+        })
+    }
+
+    /// The smallest argument. `NaN` wins over everything, -0 beats +0, and no arguments give Infinity.
+    fn math_min(nums: Vec<f64>) -> f64 {
+        nums.into_iter().fold(f64::INFINITY, |min, num| match (min, num) {
+            _ if min.is_nan() || num.is_nan() => f64::NAN,
+            (0., 0.) if min.is_sign_positive() => num,
+            _ if num < min => num,
+            _ => min
+        })
+    }
+
+    /// The square root of the sum of squares. An infinite argument wins even over `NaN`.
+    fn math_hypot(nums: Vec<f64>) -> f64 {
+        if nums.iter().any(|num| num.is_infinite()) {
+            return f64::INFINITY;
+        }
+
+        nums.into_iter().fold(0., f64::hypot)
+    }
+
+    fn math_clz32(num: f64) -> f64 {
+        Self::to_uint32(num).leading_zeros() as f64
+    }
+
+    /// 32-bit integer multiplication, wrapping like C.
+    fn math_imul(a: f64, b: f64) -> f64 {
+        (Self::to_uint32(a) as i32).wrapping_mul(Self::to_uint32(b) as i32) as f64
+    }
+
+    fn math_fround(num: f64) -> f64 {
+        num as f32 as f64
     }
 
-    fn math_random(_args: Vec<Box<Literal>>) -> Box<Literal> {
+    fn math_random(_args: Vec<Literal>) -> Box<Literal> {
         let mut rng = rand::rng();
         let val: f64 = rng.random_range(0.0..1.0);
 
         Literal::Number(val).into()
     }
 
-    fn math_max(args: Vec<Box<Literal>>) -> Box<Literal> {
-        if args.len() <= 1 {
-            panic!("Math.max takes at least two arguments");
-        }
+    fn math() -> Properties {
+        use std::f64::consts;
+
+        let mut properties: Properties = [
+            ("E", consts::E),
+            ("LN10", consts::LN_10),
+            ("LN2", consts::LN_2),
+            ("LOG10E", consts::LOG10_E),
+            ("LOG2E", consts::LOG2_E),
+            ("PI", consts::PI),
+            ("SQRT1_2", consts::FRAC_1_SQRT_2),
+            ("SQRT2", consts::SQRT_2),
+        ].into_iter().map(|(name, value)| (name.to_string(), Box::new(Literal::Number(value)))).collect();
+
+        properties.extend([
+            Self::math_unary("abs", f64::abs),
+            Self::math_unary("acos", f64::acos),
+            Self::math_unary("acosh", f64::acosh),
+            Self::math_unary("asin", f64::asin),
+            Self::math_unary("asinh", f64::asinh),
+            Self::math_unary("atan", f64::atan),
+            Self::math_unary("atanh", f64::atanh),
+            Self::math_unary("cbrt", f64::cbrt),
+            Self::math_unary("ceil", f64::ceil),
+            Self::math_unary("clz32", Self::math_clz32),
+            Self::math_unary("cos", f64::cos),
+            Self::math_unary("cosh", f64::cosh),
+            Self::math_unary("exp", f64::exp),
+            Self::math_unary("expm1", f64::exp_m1),
+            Self::math_unary("floor", f64::floor),
+            Self::math_unary("fround", Self::math_fround),
+            Self::math_unary("log", f64::ln),
+            Self::math_unary("log1p", f64::ln_1p),
+            Self::math_unary("log10", f64::log10),
+            Self::math_unary("log2", f64::log2),
+            Self::math_unary("round", Self::math_round),
+            Self::math_unary("sign", Self::math_sign),
+            Self::math_unary("sin", f64::sin),
+            Self::math_unary("sinh", f64::sinh),
+            Self::math_unary("sqrt", f64::sqrt),
+            Self::math_unary("tan", f64::tan),
+            Self::math_unary("tanh", f64::tanh),
+            Self::math_unary("trunc", f64::trunc),
+            Self::math_binary("atan2", f64::atan2),
+            Self::math_binary("imul", Self::math_imul),
+            Self::math_binary("pow", Self::math_pow),
+            Self::math_variadic("hypot", Self::math_hypot),
+            Self::math_variadic("max", Self::math_max),
+            Self::math_variadic("min", Self::math_min),
+            ("random".into(), Literal::NativeFunction(NativeFn::new("Math.random".into(), Rc::new(Self::math_random))).into()),
+        ]);
 
-        let nums: Vec<f64> = args.iter().map(|n| {
-            match **n {
-                Literal::Number(n) => n,
-                _ => panic!("Math.max called on non-number")
-            }
-        }).collect();
+        properties
+    }
 
+    /// A `(name, method)` property for a prototype.
+    fn method(owner: &str, name: &str, func: Method) -> (String, Box<Literal>) {
+        (name.into(), Literal::NativeFunction(NativeFn::method(format!("{owner}.prototype.{name}"), func)).into())
+    }
 
-        Literal::Number(nums.into_iter().reduce(f64::max).unwrap()).into()
+    /// Like `method`, for methods that can throw or call back into the engine.
+    fn context_method(owner: &str, name: &str, func: ContextMethod) -> (String, Box<Literal>) {
+        (name.into(), Literal::NativeFunction(NativeFn::host(format!("{owner}.prototype.{name}"), Rc::new(func))).into())
+    }
+
+    /// A global constructor function with `prototype` and any static members as properties.
+    fn constructor(func: NativeFn, prototype: &ObjectRef, statics: Properties) -> Literal {
+        {
+            let mut object = func.object.borrow_mut();
+            object.set("prototype", Literal::Object(Rc::clone(prototype)));
+            object.properties.extend(statics);
+        }
+
+        Literal::NativeFunction(func)
     }
 
     pub fn new() -> Self {
+        let object_prototype = Object::new(vec![
+            Self::method("Object", "hasOwnProperty", Self::object_has_own_property),
+            Self::method("Object", "toString", Self::object_to_string),
+            Self::method("Object", "valueOf", Self::object_value_of),
+        ], None);
+        let inherit = |properties: Properties| Object::new(properties, Some(Rc::clone(&object_prototype)));
+        let namespace = |properties: Properties| Literal::Object(inherit(properties));
+
+        let array_prototype = inherit(vec![
+            Self::context_method("Array", "push", Self::array_push),
+            Self::context_method("Array", "pop", Self::array_pop),
+            Self::context_method("Array", "shift", Self::array_shift),
+            Self::context_method("Array", "unshift", Self::array_unshift),
+            Self::context_method("Array", "slice", Self::array_slice),
+            Self::context_method("Array", "splice", Self::array_splice),
+            Self::context_method("Array", "concat", Self::array_concat),
+            Self::context_method("Array", "indexOf", Self::array_index_of),
+            Self::context_method("Array", "lastIndexOf", Self::array_last_index_of),
+            Self::context_method("Array", "includes", Self::array_includes),
+            Self::context_method("Array", "fill", Self::array_fill),
+            Self::context_method("Array", "flat", Self::array_flat),
+            Self::context_method("Array", "join", Self::array_join),
+            Self::context_method("Array", "toString", Self::array_to_string),
+            Self::context_method("Array", "reverse", Self::array_reverse),
+            Self::context_method("Array", "forEach", Self::array_for_each),
+            Self::context_method("Array", "map", Self::array_map),
+            Self::context_method("Array", "filter", Self::array_filter),
+            Self::context_method("Array", "find", Self::array_find),
+            Self::context_method("Array", "findIndex", Self::array_find_index),
+            Self::context_method("Array", "some", Self::array_some),
+            Self::context_method("Array", "every", Self::array_every),
+            Self::context_method("Array", "reduce", Self::array_reduce),
+            Self::context_method("Array", "reduceRight", Self::array_reduce_right),
+            Self::context_method("Array", "sort", Self::array_sort),
+        ]);
+
+        let string_prototype = inherit(vec![
+            Self::context_method("String", "charAt", Self::string_char_at),
+            Self::context_method("String", "charCodeAt", Self::string_char_code_at),
+            Self::context_method("String", "codePointAt", Self::string_code_point_at),
+            Self::context_method("String", "indexOf", Self::string_index_of),
+            Self::context_method("String", "lastIndexOf", Self::string_last_index_of),
+            Self::context_method("String", "includes", Self::string_includes),
+            Self::context_method("String", "startsWith", Self::string_starts_with),
+            Self::context_method("String", "endsWith", Self::string_ends_with),
+            Self::context_method("String", "slice", Self::string_slice),
+            Self::context_method("String", "substring", Self::string_substring),
+            Self::context_method("String", "toUpperCase", Self::string_to_upper_case),
+            Self::context_method("String", "toLowerCase", Self::string_to_lower_case),
+            Self::context_method("String", "trim", Self::string_trim),
+            Self::context_method("String", "trimStart", Self::string_trim_start),
+            Self::context_method("String", "trimEnd", Self::string_trim_end),
+            Self::context_method("String", "padStart", Self::string_pad_start),
+            Self::context_method("String", "padEnd", Self::string_pad_end),
+            Self::context_method("String", "repeat", Self::string_repeat),
+            Self::context_method("String", "replace", Self::string_replace),
+            Self::context_method("String", "replaceAll", Self::string_replace_all),
+            Self::context_method("String", "concat", Self::string_concat),
+            Self::context_method("String", "split", Self::string_split),
+        ]);
+
+        let number_prototype = inherit(vec![
+            Self::context_method("Number", "toString", Self::number_tostring),
+        ]);
+
         let mut funcs = HashMap::new();
 
-        funcs.insert("console".into(), Literal::Object(vec![
-            ("log".into(), Literal::NativeFunction(NativeFn::new("console.log".into(), Rc::new(Self::console_log))).into())
+        funcs.insert("console".into(), namespace(vec![
+            ("log".into(), Literal::NativeFunction(NativeFn::host("console.log".into(), Rc::new(Self::console_log))).into())
         ]));
 
-        funcs.insert("intrinsics".into(), Literal::Object(vec![
+        funcs.insert("intrinsics".into(), namespace(vec![
             ("dump".into(), Literal::NativeFunction(NativeFn::new("intrinsics.dump".into(), Rc::new(Self::intrinsics_dump))).into()),
-            ("typeof".into(), Literal::NativeFunction(NativeFn::new("intrinsics.typeof".into(), Rc::new(Self::intrinsics_typeof))).into())
+            ("typeof".into(), Literal::NativeFunction(NativeFn::host("intrinsics.typeof".into(), Rc::new(Self::intrinsics_typeof))).into())
         ]));
 
-        funcs.insert("Object".into(), Literal::Object(vec![
-            ("keys".into(), Literal::NativeFunction(NativeFn::new("Object.keys".into(), Rc::new(Self::object_keys))).into())
+        funcs.insert("Object".into(), Self::constructor(NativeFn::host("Object".into(), Rc::new(Self::object_constructor)), &object_prototype, vec![
+            ("keys".into(), Literal::NativeFunction(NativeFn::host("Object.keys".into(), Rc::new(Self::object_keys))).into()),
+            ("create".into(), Literal::NativeFunction(NativeFn::host("Object.create".into(), Rc::new(Self::object_create))).into()),
+            ("getPrototypeOf".into(), Literal::NativeFunction(NativeFn::host("Object.getPrototypeOf".into(), Rc::new(Self::object_get_prototype_of))).into()),
         ]));
-
-        funcs.insert("Math".into(), Literal::Object(vec![
-            ("sqrt".into(), Literal::NativeFunction(NativeFn::new("Math.sqrt".into(), Rc::new(Self::math_sqrt))).into()),
-            ("max".into(), Literal::NativeFunction(NativeFn::new("Math.max".into(), Rc::new(Self::math_max))).into()),
-            ("random".into(), Literal::NativeFunction(NativeFn::new("Math.random".into(), Rc::new(Self::math_random))).into()),
+        funcs.insert("Array".into(), Self::constructor(NativeFn::host("Array".into(), Rc::new(Self::array_constructor)), &array_prototype, vec![
+            ("isArray".into(), Literal::NativeFunction(NativeFn::new("Array.isArray".into(), Rc::new(Self::array_is_array))).into()),
+            ("of".into(), Literal::NativeFunction(NativeFn::new("Array.of".into(), Rc::new(Self::array_of))).into()),
+            ("from".into(), Literal::NativeFunction(NativeFn::host("Array.from".into(), Rc::new(Self::array_from))).into()),
+        ]));
+        funcs.insert("String".into(), Self::constructor(NativeFn::new("String".into(), Rc::new(Self::string_constructor)), &string_prototype, vec![
+            ("fromCharCode".into(), Literal::NativeFunction(NativeFn::new("String.fromCharCode".into(), Rc::new(Self::string_from_char_code))).into()),
+            ("raw".into(), Literal::NativeFunction(NativeFn::host("String.raw".into(), Rc::new(Self::string_raw))).into()),
         ]));
+        funcs.insert("Number".into(), Self::constructor(NativeFn::new("Number".into(), Rc::new(Self::number_constructor)), &number_prototype, vec![]));
 
-        let mut array_funcs: HashMap<String, Rc<dyn Fn(Box<Literal>, Vec<Box<Literal>>) -> Literal>> = HashMap::new();
-        array_funcs.insert("length".into(), Rc::new(Self::array_length));
-        array_funcs.insert("push".into(), Rc::new(Self::array_push));
-        array_funcs.insert("pop".into(), Rc::new(Self::array_pop));
-        array_funcs.insert("join".into(), Rc::new(Self::array_join));
-        array_funcs.insert("reverse".into(), Rc::new(Self::array_reverse));
+        funcs.insert("Math".into(), namespace(Self::math()));
+        funcs.insert("NaN".into(), Literal::Number(f64::NAN));
+        funcs.insert("Infinity".into(), Literal::Number(f64::INFINITY));
 
-        let mut string_funcs: HashMap<String, Rc<dyn Fn(Box<Literal>, Vec<Box<Literal>>) -> Literal>> = HashMap::new();
-        string_funcs.insert("split".into(), Rc::new(Self::string_split));
+        funcs.insert("JSON".into(), namespace(vec![
+            ("parse".into(), Literal::NativeFunction(NativeFn::host("JSON.parse".into(), Rc::new(json::parse))).into()),
+            ("stringify".into(), Literal::NativeFunction(NativeFn::host("JSON.stringify".into(), Rc::new(json::stringify))).into()),
+        ]));
 
-        let mut number_funcs: HashMap<String, Rc<dyn Fn(Box<Literal>, Vec<Box<Literal>>) -> Literal>> = HashMap::new();
-        number_funcs.insert("toString".into(), Rc::new(Self::number_tostring));
+        // Each error's prototype inherits from `Error.prototype`, which inherits from `Object.prototype`.
+        let error_prototype = inherit(vec![]);
+        let mut error_prototypes = HashMap::new();
+        for name in ["Error", "TypeError", "RangeError", "ReferenceError", "SyntaxError"] {
+            let prototype = match name {
+                "Error" => Rc::clone(&error_prototype),
+                _ => Object::new(Vec::new(), Some(Rc::clone(&error_prototype)))
+            };
+            funcs.insert(name.into(), Self::error_constructor(name, Rc::clone(&prototype)));
+            error_prototypes.insert(name, prototype);
+        }
 
         Self {
             funcs,
-            array_funcs,
-            string_funcs,
-            number_funcs
+            object_prototype,
+            array_prototype,
+            string_prototype,
+            number_prototype,
+            error_prototypes,
         }
     }
 
-    pub fn load(&mut self, scope: &mut Scope) {
+    pub fn load(&self, scope: &mut Scope) {
         for (name, func) in self.funcs.iter() {
             scope.set(name, func.clone());
         }
     }
 
-    pub fn array_builtin(&self, arr: Box<Literal>, name: String) -> Box<Literal> {
-        let func = self.array_funcs.get(&name).unwrap_or_else(|| panic!("Array.{} not found", name));
-        let func = Rc::clone(func);
-
-
-        Literal::NativeFunction(NativeFn::new(format!("Array.{name}").into(), Rc::new(move |args| {
-            let arr = arr.clone();
-            func(arr, args).into()
-        }))).into()
+    /// A new plain object inheriting from `Object.prototype`.
+    pub fn new_object(&self, properties: Properties) -> Literal {
+        Literal::Object(Object::new(properties, Some(Rc::clone(&self.object_prototype))))
     }
 
-    pub fn string_builtin(&self, str: Box<Literal>, name: String) -> Box<Literal> {
-        let func = self.string_funcs.get(&name).unwrap_or_else(|| panic!("String.{} not found", name));
-        let func = Rc::clone(func);
-
-        Literal::NativeFunction(NativeFn::new(format!("String.{name}").into(), Rc::new(move |args| {
-            let str = str.clone();
-            func(str, args).into()
-        }))).into()
+    /// A new function closing over `env`, with an empty `prototype` for `new` to use.
+    pub fn new_function(&self, args: Vec<String>, body: Rc<Statement>, env: Environment) -> Literal {
+        let prototype = self.new_object(Vec::new());
+        Literal::Function { args, body, env, object: Object::new(vec![("prototype".into(), prototype.into())], None) }
     }
 
-    pub fn number_builtin(&self, num: Box<Literal>, name: String) -> Box<Literal> {
-        let func = self.number_funcs.get(&name).unwrap_or_else(|| panic!("Number.{} not found", name));
-        let func = Rc::clone(func);
+    /// The prototype primitives and arrays look their methods up on.
+    pub fn prototype_of(&self, value: &Literal) -> Option<&ObjectRef> {
+        match value {
+            Literal::Array(_) => Some(&self.array_prototype),
+            Literal::String(_) => Some(&self.string_prototype),
+            Literal::Number(_) => Some(&self.number_prototype),
+            _ => None
+        }
+    }
 
-        Literal::NativeFunction(NativeFn::new(format!("Number.{name}").into(), Rc::new(move |args| {
-            let str = num.clone();
-            func(str, args).into()
-        }))).into()
+    pub fn object_prototype(&self) -> &ObjectRef {
+        &self.object_prototype
     }
-}
\ No newline at end of file
+}
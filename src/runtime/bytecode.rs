@@ -1,6 +1,101 @@
+use std::rc::Rc;
+use crate::lexer::Span;
+use crate::parser::{BinaryOperator, Literal, MemberKind, Statement, UnaryOperator};
 
+/// Slot in a call frame's register file. Registers only hold temporaries; variables live in
+/// the scope chain so closures can share them.
+pub type Register = usize;
+/// Index into a chunk's constant or name pool, or into the program's function table.
+pub type Index = usize;
+/// Instruction offset within a chunk.
+pub type Label = usize;
 
-
+#[derive(Debug, Clone)]
 pub enum Bytecode {
-    StoreGlobal()
-}
\ No newline at end of file
+    /// `dst = constants[constant]`
+    LoadConst { dst: Register, constant: Index },
+    /// `dst = <variable names[name]>`, throwing a ReferenceError if it is unbound.
+    GetVar { dst: Register, name: Index },
+    /// Assigns the nearest binding of `names[name]`, creating one in the innermost scope if there is none.
+    SetVar { name: Index, src: Register },
+    /// Binds `names[name]` in the innermost scope, shadowing any outer binding.
+    DeclareVar { name: Index, src: Register },
+    EnterScope,
+    ExitScope,
+    /// Replaces the innermost scope with a copy of itself; see `Scope::renew`.
+    RenewScope,
+    Binary { op: BinaryOperator, dst: Register, left: Register, right: Register },
+    Unary { op: UnaryOperator, dst: Register, src: Register },
+    /// `dst = src` as a number, throwing a TypeError for anything else.
+    ToNumber { dst: Register, src: Register },
+    /// `dst = src` converted to a string, as a template literal's substitutions are.
+    ToString { dst: Register, src: Register },
+    /// `dst = [start, start + 1, ..., start + count - 1]`
+    NewArray { dst: Register, start: Register, count: usize },
+    NewObject { dst: Register },
+    GetIndex { dst: Register, target: Register, index: Register },
+    SetIndex { target: Register, index: Register, src: Register },
+    GetProperty { dst: Register, target: Register, name: Index },
+    SetProperty { target: Register, name: Index, src: Register },
+    /// `dst = functions[func]`, closing over the current scope.
+    Closure { dst: Register, func: Index },
+    /// Calls `callee` with the arguments in `args .. args + argc`, and `this` bound to the value
+    /// in `this` if there is one. `name` is the callee's readable name, for the error if it
+    /// isn't callable.
+    Call { dst: Register, callee: Register, this: Option<Register>, args: Register, argc: usize, name: Index },
+    /// `dst = new callee(...)`, with arguments as for `Call`.
+    New { dst: Register, callee: Register, args: Register, argc: usize, name: Index },
+    /// `dst = this`, or `undefined` outside of a call.
+    LoadThis { dst: Register },
+    /// Links the new class constructor in `class` to `parent` and stores the class's prototype
+    /// in `dst`; see `ops::make_class`.
+    MakeClass { dst: Register, class: Register, parent: Option<Register> },
+    /// Defines the method or accessor `names[name]` on `target`; see `ops::define_method`.
+    DefineMethod { target: Register, name: Index, src: Register, kind: MemberKind },
+    /// `dst = super(...)`, with arguments as for `Call`, or `None` to forward the running
+    /// function's own arguments.
+    SuperCall { dst: Register, args: Option<(Register, usize)> },
+    /// `dst = super.names[name]`
+    GetSuper { dst: Register, name: Index },
+    Jump { target: Label },
+    JumpIfFalse { cond: Register, target: Label },
+    JumpIfTrue { cond: Register, target: Label },
+    /// Jumps unless `cond` is `null` or `undefined`, for `??`.
+    JumpIfNotNullish { cond: Register, target: Label },
+    Return { src: Register },
+    Throw { src: Register },
+    /// Throws `src` again from where the enclosing frame last caught an exception.
+    Rethrow { src: Register },
+    /// Routes exceptions to `handler`, storing the thrown value in `exception`, until the
+    /// matching `LeaveTry`. Handlers nest.
+    EnterTry { handler: Label, exception: Register },
+    LeaveTry,
+}
+
+/// Bytecode for one function body, or for the top level of a script.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub name: String,
+    pub params: Vec<String>,
+    pub code: Vec<Bytecode>,
+    /// Source span of each instruction, for error reporting.
+    pub spans: Vec<Span>,
+    pub constants: Vec<Literal>,
+    pub names: Vec<String>,
+    /// Size of the register file a call frame needs.
+    pub registers: usize,
+}
+
+#[derive(Debug)]
+pub struct CompiledFunction {
+    /// The source body, shared with every `Literal::Function` created from this chunk so the VM
+    /// can find the chunk again when the function is called.
+    pub body: Rc<Statement>,
+    pub chunk: Rc<Chunk>,
+}
+
+#[derive(Debug)]
+pub struct Program {
+    pub main: Rc<Chunk>,
+    pub functions: Vec<CompiledFunction>,
+}
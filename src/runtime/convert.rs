@@ -0,0 +1,108 @@
+//! Conversions between Rust values and `Literal`, for hosts passing data in and out of scripts.
+
+use crate::parser::{Array, Literal};
+use crate::runtime::embed::Error;
+
+macro_rules! number_conversions {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Literal {
+                fn from(n: $ty) -> Self {
+                    Literal::Number(n as f64)
+                }
+            }
+        )*
+    };
+}
+
+number_conversions!(f64, f32, i32, i64, u32, u64, usize);
+
+impl From<bool> for Literal {
+    fn from(b: bool) -> Self {
+        Literal::Boolean(b)
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(s: &str) -> Self {
+        Literal::String(s.into())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(s: String) -> Self {
+        Literal::String(s)
+    }
+}
+
+impl From<()> for Literal {
+    fn from(_: ()) -> Self {
+        Literal::Undefined
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<Literal>> From<Option<T>> for Literal {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Literal::Null, Into::into)
+    }
+}
+
+/// A new JS array holding the converted elements.
+impl<T: Into<Literal>> From<Vec<T>> for Literal {
+    fn from(values: Vec<T>) -> Self {
+        let elements = values.into_iter().map(Into::into).collect();
+        Literal::Array(Array::new(elements))
+    }
+}
+
+fn mismatch<T>(expected: &'static str, found: &Literal) -> Result<T, Error> {
+    Err(Error::Type { expected, found: found.type_name() })
+}
+
+impl TryFrom<Literal> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Literal) -> Result<Self, Error> {
+        match value {
+            Literal::Number(n) => Ok(n),
+            other => mismatch("number", &other)
+        }
+    }
+}
+
+impl TryFrom<Literal> for bool {
+    type Error = Error;
+
+    fn try_from(value: Literal) -> Result<Self, Error> {
+        match value {
+            Literal::Boolean(b) => Ok(b),
+            other => mismatch("boolean", &other)
+        }
+    }
+}
+
+impl TryFrom<Literal> for String {
+    type Error = Error;
+
+    fn try_from(value: Literal) -> Result<Self, Error> {
+        match value {
+            Literal::String(s) => Ok(s),
+            other => mismatch("string", &other)
+        }
+    }
+}
+
+/// Copies the array's elements out, converting each one.
+impl<T> TryFrom<Literal> for Vec<T> where T: TryFrom<Literal>, Error: From<T::Error> {
+    type Error = Error;
+
+    fn try_from(value: Literal) -> Result<Self, Error> {
+        match value {
+            Literal::Array(elements) => elements.borrow().iter()
+                .map(|element| Ok(T::try_from(element.clone())?))
+                .collect(),
+            other => mismatch("array", &other)
+        }
+    }
+}
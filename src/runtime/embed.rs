@@ -0,0 +1,141 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::rc::Rc;
+use crate::lexer::Lexer;
+use crate::parser::{Literal, NativeFn, ParseError, AST};
+use crate::runtime::interpreter::{Interpreter, UncaughtException};
+use crate::runtime::native::{Args, Context, HostObject};
+
+/// Anything that can go wrong running JS from Rust.
+#[derive(Debug)]
+pub enum Error {
+    /// The source didn't parse.
+    Syntax(Vec<ParseError>),
+    /// The script threw and nothing caught it.
+    Exception(UncaughtException),
+    /// A value couldn't be converted to the Rust type asked for.
+    Type {
+        expected: &'static str,
+        found: &'static str
+    }
+}
+
+impl Error {
+    /// Formats the error along with the offending line of `source`.
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            Error::Syntax(errors) => errors.iter().map(|error| error.render(source)).collect::<Vec<_>>().join("\n\n"),
+            Error::Exception(exception) => exception.render(source),
+            Error::Type { .. } => self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(errors) => match errors.as_slice() {
+                [error] => write!(f, "{error}"),
+                errors => write!(f, "{} syntax errors, the first at {}", errors.len(), errors[0])
+            },
+            Error::Exception(exception) => write!(f, "{exception}"),
+            Error::Type { expected, found } => write!(f, "expected {expected}, found {found}")
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<UncaughtException> for Error {
+    fn from(exception: UncaughtException) -> Self {
+        Error::Exception(exception)
+    }
+}
+
+// Lets conversions that can't fail, such as `Literal` to itself, sit alongside ones that can.
+impl From<Infallible> for Error {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+/// A JS engine for embedding. Globals persist from one `eval` to the next, so a host can load a
+/// script once and then call into it.
+///
+/// ```
+/// use tinyjs::Runtime;
+///
+/// let mut js = Runtime::new();
+/// js.eval("function discount(total) { if (total > 100) { return total * 0.1; } return 0; }").unwrap();
+///
+/// let discount: f64 = js.call("discount", vec![250.0.into()]).unwrap().try_into().unwrap();
+/// assert_eq!(discount, 25.0);
+/// ```
+pub struct Runtime {
+    interpreter: Interpreter
+}
+
+impl Runtime {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(AST { statements: Vec::new() })
+        }
+    }
+
+    /// Runs `source` in the global scope, evaluating to the value of its final statement if that
+    /// is an expression, and `undefined` otherwise.
+    pub fn eval(&mut self, source: &str) -> Result<Literal, Error> {
+        // Not optimized: constant propagation would bake in globals the host may reassign later.
+        let ast = AST::new(Lexer::new(source).lex()).map_err(Error::Syntax)?;
+        let value = self.interpreter.eval(ast)?;
+
+        Ok(value.unwrap_or(Literal::Undefined))
+    }
+
+    /// Calls the global function `name`.
+    pub fn call(&mut self, name: &str, args: Vec<Literal>) -> Result<Literal, Error> {
+        let func = self.get(name).unwrap_or(Literal::Undefined);
+        Ok(self.interpreter.call_function(func, args, name)?)
+    }
+
+    /// The value of the global `name`, if it is bound.
+    pub fn get(&self, name: &str) -> Option<Literal> {
+        self.interpreter.global(name)
+    }
+
+    /// Binds the global `name`, replacing any existing value.
+    pub fn set(&mut self, name: &str, value: impl Into<Literal>) {
+        self.interpreter.set_global(name, value.into());
+    }
+
+    /// A native function value. Return `Err` from `func` to throw, e.g. with `native::error`.
+    pub fn function<F>(name: &str, func: F) -> Literal
+    where F: Fn(&mut dyn Context, Args) -> Result<Literal, Literal> + 'static {
+        Literal::NativeFunction(NativeFn::host(name.into(), Rc::new(func)))
+    }
+
+    /// Makes `func` callable from scripts as the global `name`.
+    pub fn register<F>(&mut self, name: &str, func: F)
+    where F: Fn(&mut dyn Context, Args) -> Result<Literal, Literal> + 'static {
+        self.set(name, Self::function(name, func));
+    }
+
+    /// Binds the global `name` to a plain object holding `members`, like `Math` or `console`.
+    pub fn register_namespace(&mut self, name: &str, members: Vec<(&str, Literal)>) {
+        let properties = members.into_iter().map(|(key, value)| (key.to_string(), Box::new(value))).collect();
+        let namespace = self.interpreter.new_object(properties);
+        self.set(name, namespace);
+    }
+
+    /// Binds the global `name` to an object whose properties are read and written through `host`.
+    pub fn register_object(&mut self, name: &str, host: impl HostObject + 'static) {
+        let object = self.interpreter.new_host_object(Rc::new(host));
+        self.set(name, object);
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
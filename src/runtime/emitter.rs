@@ -1,464 +1,743 @@
-use std::cell::RefCell;
+use std::fmt;
 use std::rc::Rc;
-use crate::parser::{BinaryOperator, Expression, Literal, Statement, UnaryOperator, AST};
-use crate::runtime::builtins::Builtins;
-use crate::runtime::scope::Scope;
-
-pub struct BytecodeEmitter {
-    pub scope: Scope,
-    builtins: Builtins,
-    ast: AST
+use crate::lexer::Span;
+use crate::parser::{BinaryOperator, ClassMember, Expression, ExpressionKind, Literal, LogicalOperator, MemberKind, Statement, StatementKind, AST};
+use crate::runtime::bytecode::{Bytecode, Chunk, CompiledFunction, Index, Label, Program, Register};
+use crate::runtime::{ops, Interpreter};
+
+/// A construct the emitter can't lower, such as `break` outside a loop.
+#[derive(Debug)]
+pub struct CompileError {
+    pub message: String,
+    pub span: Span,
 }
 
-impl BytecodeEmitter {
-    pub(crate) fn new(ast: AST) -> Self {
+impl CompileError {
+    /// Formats the error along with the offending line of `source`.
+    pub fn render(&self, source: &str) -> String {
+        format!("error: {}\n --> {}\n{}", self.message, self.span, self.span.snippet(source))
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+type EmitResult<T> = Result<T, CompileError>;
+
+/// Something enclosing the code being emitted that a `break`, `continue` or `return` has to
+/// leave properly.
+enum Block<'a> {
+    Scope,
+    Try { finalizer: Option<&'a Statement> },
+    Loop { breaks: Vec<Label>, continues: Vec<Label> },
+}
+
+/// Where an assignment or update writes to, with its operands already evaluated.
+enum Place {
+    Variable(Index),
+    Element(Register, Register),
+    Property(Register, Index),
+}
+
+/// The chunk currently being emitted, plus its bookkeeping.
+struct ChunkBuilder<'a> {
+    chunk: Chunk,
+    next_register: Register,
+    blocks: Vec<Block<'a>>,
+}
+
+impl ChunkBuilder<'_> {
+    fn new(name: &str, params: &[String]) -> Self {
         Self {
-            scope: Scope::new(),
-            builtins: Builtins::new(),
-            ast
+            chunk: Chunk { name: name.to_string(), params: params.to_vec(), ..Chunk::default() },
+            next_register: 0,
+            blocks: Vec::new(),
         }
     }
+}
 
-    fn do_expression(&mut self, expr: Expression) -> Literal {
-        match expr {
-            Expression::Identifier(name) => self.scope.get(name.clone()).expect(format!("Unknown identifier '{}'", name.clone()).as_str()).clone(),
-            Expression::Literal(lit) => lit,
-            Expression::BinaryOp {
-                left,
-                op,
-                right
-            } => {
-                let left = self.do_expression(*left);
-                let right = self.do_expression(*right);
-
-                match op {
-                    BinaryOperator::Add => {
-                        match (left, right) {
-                            (Literal::Number(l), Literal::Number(r)) => Literal::Number(l + r),
-                            (Literal::String(l), Literal::String(r)) => Literal::String(l + &r),
-                            (Literal::String(l), Literal::Number(r)) => Literal::String(format!("{}{}", l, r)),
-                            (Literal::Number(l), Literal::String(r)) => Literal::String(format!("{}{}", l, r)),
-                            (l, r) => panic!("Unsupported operands for Add: {:?} and {:?}", l, r),
-                        }
-                    },
-                    BinaryOperator::Sub => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+/// Lowers an `AST` to register bytecode, one chunk per function.
+#[derive(Default)]
+pub struct Emitter<'a> {
+    builders: Vec<ChunkBuilder<'a>>,
+    functions: Vec<CompiledFunction>,
+}
 
-                        Literal::Number(left - right)
-                    },
-                    BinaryOperator::Mul => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+impl<'a> Emitter<'a> {
+    pub fn new() -> Self {
+        Self {
+            builders: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
 
-                        Literal::Number(left * right)
-                    },
-                    BinaryOperator::Div => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+    fn builder(&mut self) -> &mut ChunkBuilder<'a> {
+        self.builders.last_mut().expect("no chunk being emitted")
+    }
 
-                        Literal::Number(left / right)
-                    },
-                    BinaryOperator::Equal => {
-                        Literal::Boolean(left == right)
-                    },
-                    BinaryOperator::NotEqual => {
-                        Literal::Boolean(left != right)
-                    },
-                    BinaryOperator::GreaterThan => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+    fn emit(&mut self, instruction: Bytecode, span: Span) -> Label {
+        let chunk = &mut self.builder().chunk;
+        chunk.code.push(instruction);
+        chunk.spans.push(span);
+        chunk.code.len() - 1
+    }
 
-                        Literal::Boolean(left > right)
-                    },
-                    BinaryOperator::GreaterThanOrEqual => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+    fn here(&mut self) -> Label {
+        self.builder().chunk.code.len()
+    }
 
-                        Literal::Boolean(left >= right)
-                    },
-                    BinaryOperator::LessThan => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+    /// Points the jump at `at` to `target`.
+    fn patch_to(&mut self, at: Label, to: Label) {
+        match &mut self.builder().chunk.code[at] {
+            Bytecode::Jump { target }
+            | Bytecode::JumpIfFalse { target, .. }
+            | Bytecode::JumpIfTrue { target, .. }
+            | Bytecode::JumpIfNotNullish { target, .. } => *target = to,
+            Bytecode::EnterTry { handler, .. } => *handler = to,
+            other => unreachable!("{:?} is not a jump", other),
+        }
+    }
 
-                        Literal::Boolean(left < right)
-                    }
-                    BinaryOperator::LessThanOrEqual => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+    /// Points the jump at `at` to the next instruction to be emitted.
+    fn patch(&mut self, at: Label) {
+        let here = self.here();
+        self.patch_to(at, here);
+    }
 
-                        Literal::Boolean(left <= right)
-                    },
-                    BinaryOperator::BinaryOr => {
-                        let left = left.truthy();
-                        let right = right.truthy();
-
-                        Literal::Boolean(left || right)
-                    },
-                    BinaryOperator::BinaryAnd => {
-                        let left = left.truthy();
-                        let right = right.truthy();
-
-                        Literal::Boolean(left && right)
-                    },
-                    BinaryOperator::Mod => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
+    fn alloc(&mut self) -> Register {
+        let builder = self.builder();
+        let register = builder.next_register;
+        builder.next_register += 1;
+        builder.chunk.registers = builder.chunk.registers.max(builder.next_register);
+        register
+    }
+
+    /// Runs `f`, then frees every register it allocated.
+    fn scratch(&mut self, f: impl FnOnce(&mut Self) -> EmitResult<()>) -> EmitResult<()> {
+        let mark = self.builder().next_register;
+        let result = f(self);
+        self.builder().next_register = mark;
+        result
+    }
+
+    fn constant(&mut self, value: Literal) -> Index {
+        // Numbers are matched bit for bit, so that 0 and -0 stay distinct.
+        let same = |c: &Literal| match (c, &value) {
+            (Literal::Number(a), Literal::Number(b)) => a.to_bits() == b.to_bits(),
+            (c, value) => c == value
+        };
+
+        let constants = &mut self.builder().chunk.constants;
+        match constants.iter().position(same) {
+            Some(index) => index,
+            None => {
+                constants.push(value);
+                constants.len() - 1
+            }
+        }
+    }
+
+    fn name(&mut self, name: &str) -> Index {
+        let names = &mut self.builder().chunk.names;
+        match names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        }
+    }
 
-                        Literal::Number(left % right)
+    fn load(&mut self, value: Literal, dst: Register, span: Span) {
+        let constant = self.constant(value);
+        self.emit(Bytecode::LoadConst { dst, constant }, span);
+    }
+
+    fn in_block(&mut self, block: Block<'a>, f: impl FnOnce(&mut Self) -> EmitResult<()>) -> EmitResult<Block<'a>> {
+        self.builder().blocks.push(block);
+        let result = f(self);
+        let block = self.builder().blocks.pop().expect("block stack underflow");
+        result.map(|_| block)
+    }
+
+    /// Emits the exits from every block above `depth`, innermost first: closing scopes, dropping
+    /// exception handlers and running `finally` blocks inline.
+    fn unwind(&mut self, depth: usize, span: Span) -> EmitResult<()> {
+        for i in (depth..self.builder().blocks.len()).rev() {
+            match self.builder().blocks[i] {
+                Block::Scope => { self.emit(Bytecode::ExitScope, span); },
+                Block::Loop { .. } => {},
+                Block::Try { finalizer } => {
+                    self.emit(Bytecode::LeaveTry, span);
+                    if let Some(finalizer) = finalizer {
+                        // The finalizer only sees the blocks around its own `try`.
+                        let inner = self.builder().blocks.split_off(i);
+                        let result = self.statement(finalizer);
+                        self.builder().blocks.extend(inner);
+                        result?;
                     }
                 }
-            },
-            Expression::Array {
-                elements
-            } => {
-                Literal::Array(Rc::new(RefCell::new(elements.iter().map(|el| self.do_expression(*el.clone()).into() ).collect())))
-            },
-            Expression::Assignment {
-                target,
-                value
-            } => {
-                match *target {
-                    Expression::Identifier(name) => {
-                        let res = self.do_expression(*value);
-                        self.scope.set(name, res.clone());
-                        res
-                    },
-                    Expression::Index {
-                        target,
-                        index
-                    } => {
-                        match *target {
-                            Expression::Identifier(name) => {
-                                let res = self.do_expression(*value);
-                                let arr = self.scope.get(name.clone()).expect(format!("Unknown identifier '{}'", name.clone()).as_str()).clone();
-                                let arr = match arr {
-                                    Literal::Array(arr) => arr,
-                                    _ => panic!("Expected array, got {:?}", arr)
-                                };
-                                let index = self.do_expression(*index).clone();
-                                let index = match index {
-                                    Literal::Number(index) => index as usize,
-                                    _ => panic!("Expected number, got {:?}", index)
-                                };
-
-                                arr.borrow_mut()[index] = res.into();
-                                self.scope.set(name.clone(), Literal::Array(arr.clone()));
-                                Literal::Array(arr)
-                            },
-                            _ => panic!("Expected identifier, got {:?}", target)
-                        }
-                    },
-                    Expression::Property {
-                        target,
-                        name
-                    } => {
-                        match *target {
-                            Expression::Identifier(obj_name) => {
-                                let res = self.do_expression(*value);
-                                let obj = self.scope.get(obj_name.clone()).expect(format!("Unknown identifier '{}'", obj_name.clone()).as_str()).clone();
-                                let mut obj = match obj {
-                                    Literal::Object(obj) => obj,
-                                    _ => panic!("Expected object, got {:?}", obj)
-                                };
-
-                                for i in 0..obj.len() {
-                                    if obj[i].0 == name {
-                                        obj[i] = (name.clone(), res.clone().into());
-                                        self.scope.set(obj_name.clone(), Literal::Object(obj.clone()));
-                                        return Literal::Object(obj);
-                                    }
-                                }
-
-                                // Not found, add it
-                                obj.push((name.clone(), res.clone().into()));
-                                self.scope.set(obj_name.clone(), Literal::Object(obj.clone()));
-                                res
-                            }
-                            _ => panic!("Expected identifier, got {:?}", target)
-                        }
-                    },
-                    _ => panic!("Expected identifier, got {:?}", target)
-                }
-            },
-            Expression::FunctionCall {
-                callee,
-                args
-            } => {
-                let func = self.do_expression(*callee);
-
-                match func {
-                    Literal::Function {
-                        args: func_args,
-                        body
-                    } => {
-                        if func_args.len() != args.len() {
-                            panic!("Expected {} arguments, got {}", func_args.len(), args.len());
-                        }
+            }
+        }
 
-                        self.scope.enter();
+        Ok(())
+    }
 
-                        for (param_name, param_expr) in func_args.iter().zip(args.iter()) {
-                            let val = self.do_expression(*param_expr.clone());
-                            self.scope.set(param_name.clone(), val);
-                        }
+    /// Emits a `break` or `continue` jump out of the innermost loop.
+    fn loop_exit(&mut self, is_break: bool, span: Span) -> EmitResult<()> {
+        let Some(depth) = self.builder().blocks.iter().rposition(|block| matches!(block, Block::Loop { .. })) else {
+            let keyword = if is_break { "break" } else { "continue" };
+            return Err(CompileError { message: format!("'{keyword}' outside of a loop"), span });
+        };
+
+        self.unwind(depth + 1, span)?;
+        let jump = self.emit(Bytecode::Jump { target: 0 }, span);
+        if let Block::Loop { breaks, continues } = &mut self.builder().blocks[depth] {
+            if is_break { breaks.push(jump) } else { continues.push(jump) }
+        }
 
-                        let ret = self.do_statement(*body);
+        Ok(())
+    }
 
-                        self.scope.exit();
+    /// Emits a loop body, pointing its `continue`s at whatever `continue_target` emits and
+    /// returning its `break` jumps for the caller to patch to the end of the loop.
+    fn loop_body(&mut self, body: &'a Statement, continue_target: impl FnOnce(&mut Self) -> EmitResult<Label>) -> EmitResult<Vec<Label>> {
+        let block = Block::Loop { breaks: Vec::new(), continues: Vec::new() };
+        let Block::Loop { breaks, continues } = self.in_block(block, |this| this.statement(body))? else {
+            unreachable!("loop block replaced")
+        };
 
-                        ret.unwrap_or(Literal::Undefined)
-                    },
-                    Literal::NativeFunction(f) => {
-                        let args = args.into_iter().map(|arg| self.do_expression(*arg).into()).collect::<Vec<_>>();
+        let target = continue_target(self)?;
+        continues.into_iter().for_each(|jump| self.patch_to(jump, target));
 
-                        *(f.func)(args)
-                    }
-                    _ => panic!("Expected function, got {:?}", func)
-                }
-            },
-            Expression::Index {
-                target,
-                index
-            } => {
-                let index = self.do_expression(*index);
-                let target = match *target {
-                    Expression::Identifier(name) => self.scope.get(name.clone()).expect(format!("Unknown identifier '{}'", name.clone()).as_str()).clone(),
-                    _ => panic!("Expected identifier, got {:?}", target)
-                };
-                let index = match index {
-                    Literal::Number(index) => index as usize,
-                    _ => panic!("Expected number, got {:?}", index)
-                };
+        Ok(breaks)
+    }
 
-                let arr = match target {
-                    Literal::Array(arr) => arr,
-                    _ => panic!("Expected array, got {:?}", target)
-                };
+    fn function(&mut self, name: &str, args: &[String], body: &'a Statement) -> EmitResult<Index> {
+        self.builders.push(ChunkBuilder::new(name, args));
+
+        let result = self.statement(body).map(|_| {
+            let dst = self.alloc();
+            self.load(Literal::Undefined, dst, body.span);
+            self.emit(Bytecode::Return { src: dst }, body.span);
+        });
+        let builder = self.builders.pop().expect("no chunk being emitted");
+        result?;
+
+        self.functions.push(CompiledFunction {
+            body: Rc::new(body.clone()),
+            chunk: Rc::new(builder.chunk),
+        });
+        Ok(self.functions.len() - 1)
+    }
 
-                if index >= arr.borrow().len() {
-                    panic!("Index out of bounds: {index}");
-                }
-                *arr.borrow()[index].clone()
+    fn place(&mut self, target: &'a Expression) -> EmitResult<Place> {
+        match &target.kind {
+            ExpressionKind::Identifier(name) => Ok(Place::Variable(self.name(name))),
+            ExpressionKind::Index { target, index } => {
+                let target_reg = self.alloc();
+                let index_reg = self.alloc();
+                self.expression(target, target_reg)?;
+                self.expression(index, index_reg)?;
+                Ok(Place::Element(target_reg, index_reg))
             },
-            Expression::Object {
-                properties
-            } => {
-                Literal::Object(properties.into_iter().map(|(name, val)| {
-                    (name, self.do_expression(*val).into())
-                }).collect())
+            ExpressionKind::Property { target, name } => {
+                let target_reg = self.alloc();
+                self.expression(target, target_reg)?;
+                Ok(Place::Property(target_reg, self.name(name)))
             },
-            Expression::UnaryOp {
-                op,
-                expr
-            } => {
-                match op {
-                    UnaryOperator::Negate => {
-                        let expr = self.do_expression(*expr);
-                        match expr {
-                            Literal::Number(num) => Literal::Number(-num),
-                            _ => panic!("Expected number, got {:?}", expr)
+            _ => Err(CompileError { message: "Invalid left-hand side in assignment".into(), span: target.span }),
+        }
+    }
+
+    fn read_place(&mut self, place: &Place, dst: Register, span: Span) {
+        let instruction = match *place {
+            Place::Variable(name) => Bytecode::GetVar { dst, name },
+            Place::Element(target, index) => Bytecode::GetIndex { dst, target, index },
+            Place::Property(target, name) => Bytecode::GetProperty { dst, target, name },
+        };
+        self.emit(instruction, span);
+    }
+
+    fn write_place(&mut self, place: &Place, src: Register, span: Span) {
+        let instruction = match *place {
+            Place::Variable(name) => Bytecode::SetVar { name, src },
+            Place::Element(target, index) => Bytecode::SetIndex { target, index, src },
+            Place::Property(target, name) => Bytecode::SetProperty { target, name, src },
+        };
+        self.emit(instruction, span);
+    }
+
+    /// Evaluates `args` into consecutive fresh registers, returning the first of them.
+    fn arguments(&mut self, args: &'a [Box<Expression>]) -> EmitResult<Register> {
+        let start = self.builder().next_register;
+        let registers: Vec<Register> = args.iter().map(|_| self.alloc()).collect();
+        for (arg, &register) in args.iter().zip(&registers) {
+            self.expression(arg, register)?;
+        }
+
+        Ok(start)
+    }
+
+    /// Emits code leaving the value of `expr` in `dst`. Temporaries are allocated above `dst`
+    /// and freed again afterwards.
+    fn expression(&mut self, expr: &'a Expression, dst: Register) -> EmitResult<()> {
+        let span = expr.span;
+        self.scratch(|this| {
+            match &expr.kind {
+                ExpressionKind::Literal(lit) => this.load(lit.clone(), dst, span),
+                ExpressionKind::Identifier(name) => {
+                    let name = this.name(name);
+                    this.emit(Bytecode::GetVar { dst, name }, span);
+                },
+                ExpressionKind::BinaryOp { left, op, right } if op.is_assignment() => {
+                    let place = this.place(left)?;
+                    this.read_place(&place, dst, span);
+                    let right_reg = this.alloc();
+                    this.expression(right, right_reg)?;
+                    this.emit(Bytecode::Binary { op: op.clone(), dst, left: dst, right: right_reg }, span);
+                    this.write_place(&place, dst, span);
+                },
+                ExpressionKind::BinaryOp { left, op, right } => {
+                    this.expression(left, dst)?;
+                    let right_reg = this.alloc();
+                    this.expression(right, right_reg)?;
+                    this.emit(Bytecode::Binary { op: op.clone(), dst, left: dst, right: right_reg }, span);
+                },
+                ExpressionKind::UnaryOp { op, expr } => {
+                    this.expression(expr, dst)?;
+                    this.emit(Bytecode::Unary { op: op.clone(), dst, src: dst }, span);
+                },
+                ExpressionKind::Logical { left, op, right } => {
+                    // `dst` holds the left operand, which is the result unless the right one
+                    // has to be evaluated, and only then is a logical assignment's target written.
+                    let place = if op.is_assignment() { Some(this.place(left)?) } else { None };
+                    match &place {
+                        Some(place) => this.read_place(place, dst, span),
+                        None => this.expression(left, dst)?,
+                    }
+                    let skip = match op {
+                        LogicalOperator::And | LogicalOperator::AndEqual => Bytecode::JumpIfFalse { cond: dst, target: 0 },
+                        LogicalOperator::Or | LogicalOperator::OrEqual => Bytecode::JumpIfTrue { cond: dst, target: 0 },
+                        LogicalOperator::Nullish | LogicalOperator::NullishEqual => Bytecode::JumpIfNotNullish { cond: dst, target: 0 },
+                    };
+                    let to_end = this.emit(skip, span);
+                    this.expression(right, dst)?;
+                    if let Some(place) = &place {
+                        this.write_place(place, dst, span);
+                    }
+                    this.patch(to_end);
+                },
+                ExpressionKind::Conditional { condition, consequent, alternative } => {
+                    let cond = this.alloc();
+                    this.expression(condition, cond)?;
+                    let to_else = this.emit(Bytecode::JumpIfFalse { cond, target: 0 }, span);
+                    this.expression(consequent, dst)?;
+                    let to_end = this.emit(Bytecode::Jump { target: 0 }, span);
+                    this.patch(to_else);
+                    this.expression(alternative, dst)?;
+                    this.patch(to_end);
+                },
+                ExpressionKind::Increment { target, prefix } | ExpressionKind::Decrement { target, prefix } => {
+                    let delta = if matches!(expr.kind, ExpressionKind::Increment { .. }) { 1. } else { -1. };
+                    let place = this.place(target)?;
+                    this.read_place(&place, dst, span);
+                    this.emit(Bytecode::ToNumber { dst, src: dst }, span);
+
+                    // A prefix update leaves the new value in `dst`, a postfix one the old value.
+                    let step = this.alloc();
+                    this.load(Literal::Number(delta), step, span);
+                    let updated = if *prefix { dst } else { this.alloc() };
+                    this.emit(Bytecode::Binary { op: BinaryOperator::Add, dst: updated, left: dst, right: step }, span);
+                    this.write_place(&place, updated, span);
+                },
+                ExpressionKind::Assignment { target, value } => {
+                    let place = this.place(target)?;
+                    this.expression(value, dst)?;
+                    this.write_place(&place, dst, span);
+                },
+                ExpressionKind::Array { elements } => {
+                    let registers: Vec<Register> = elements.iter().map(|_| this.alloc()).collect();
+                    for (element, &register) in elements.iter().zip(&registers) {
+                        this.expression(element, register)?;
+                    }
+                    let start = registers.first().copied().unwrap_or(dst);
+                    this.emit(Bytecode::NewArray { dst, start, count: elements.len() }, span);
+                },
+                ExpressionKind::Object { properties } => {
+                    this.emit(Bytecode::NewObject { dst }, span);
+                    let value = this.alloc();
+                    for (name, expr) in properties {
+                        this.expression(expr, value)?;
+                        let name = this.name(name);
+                        this.emit(Bytecode::SetProperty { target: dst, name, src: value }, span);
+                    }
+                },
+                ExpressionKind::FunctionCall { callee, args } => {
+                    let callee_reg = this.alloc();
+                    // Calling a property passes the object it was read from as `this`.
+                    let receiver = match &callee.kind {
+                        ExpressionKind::Property { target, name } => {
+                            let receiver = this.alloc();
+                            this.expression(target, receiver)?;
+                            let name = this.name(name);
+                            this.emit(Bytecode::GetProperty { dst: callee_reg, target: receiver, name }, callee.span);
+                            Some(receiver)
+                        },
+                        ExpressionKind::SuperProperty { name } => {
+                            let receiver = this.alloc();
+                            this.emit(Bytecode::LoadThis { dst: receiver }, callee.span);
+                            let name = this.name(name);
+                            this.emit(Bytecode::GetSuper { dst: callee_reg, name }, callee.span);
+                            Some(receiver)
+                        },
+                        _ => {
+                            this.expression(callee, callee_reg)?;
+                            None
+                        },
+                    };
+
+                    let start = this.arguments(args)?;
+                    let name = this.name(&Interpreter::callee_name(callee));
+                    this.emit(Bytecode::Call { dst, callee: callee_reg, this: receiver, args: start, argc: args.len(), name }, span);
+                },
+                ExpressionKind::New { callee, args } => {
+                    let callee_reg = this.alloc();
+                    this.expression(callee, callee_reg)?;
+
+                    let start = this.arguments(args)?;
+                    let name = this.name(&Interpreter::callee_name(callee));
+                    this.emit(Bytecode::New { dst, callee: callee_reg, args: start, argc: args.len(), name }, span);
+                },
+                ExpressionKind::This => { this.emit(Bytecode::LoadThis { dst }, span); },
+                ExpressionKind::Class { name, parent, constructor, members } => {
+                    this.class(name.as_deref(), parent.as_deref(), constructor, members, dst, span)?;
+                },
+                ExpressionKind::SuperCall { args } => {
+                    let args = match args {
+                        Some(args) => Some((this.arguments(args)?, args.len())),
+                        None => None,
+                    };
+                    this.emit(Bytecode::SuperCall { dst, args }, span);
+                },
+                ExpressionKind::SuperProperty { name } => {
+                    let name = this.name(name);
+                    this.emit(Bytecode::GetSuper { dst, name }, span);
+                },
+                ExpressionKind::Index { target, index } => {
+                    this.expression(target, dst)?;
+                    let index_reg = this.alloc();
+                    this.expression(index, index_reg)?;
+                    this.emit(Bytecode::GetIndex { dst, target: dst, index: index_reg }, span);
+                },
+                ExpressionKind::Property { target, name } => {
+                    this.expression(target, dst)?;
+                    let name = this.name(name);
+                    this.emit(Bytecode::GetProperty { dst, target: dst, name }, span);
+                },
+                ExpressionKind::Template { quasis, expressions } => {
+                    this.load(Literal::String(quasis[0].clone()), dst, span);
+                    let part = this.alloc();
+                    for (expr, quasi) in expressions.iter().zip(&quasis[1..]) {
+                        this.expression(expr, part)?;
+                        this.emit(Bytecode::ToString { dst: part, src: part }, span);
+                        this.emit(Bytecode::Binary { op: BinaryOperator::Add, dst, left: dst, right: part }, span);
+                        if !quasi.is_empty() {
+                            this.load(Literal::String(quasi.clone()), part, span);
+                            this.emit(Bytecode::Binary { op: BinaryOperator::Add, dst, left: dst, right: part }, span);
                         }
-                    },
-                    UnaryOperator::Not => {
-                        let expr = self.do_expression(*expr);
-                        Literal::Boolean(!expr.truthy())
                     }
-                }
+                },
+                ExpressionKind::TemplateObject(strings) => this.load(strings.clone(), dst, span),
+                ExpressionKind::Function { name, args, body } => {
+                    let func = this.function(name.as_deref().unwrap_or("anonymous"), args, body)?;
+                    match name {
+                        // Bind the name in a scope of its own so the function can call itself.
+                        Some(name) => {
+                            this.emit(Bytecode::EnterScope, span);
+                            this.emit(Bytecode::Closure { dst, func }, span);
+                            let name = this.name(name);
+                            this.emit(Bytecode::DeclareVar { name, src: dst }, span);
+                            this.emit(Bytecode::ExitScope, span);
+                        },
+                        None => { this.emit(Bytecode::Closure { dst, func }, span); },
+                    }
+                },
+            }
+
+            Ok(())
+        })
+    }
+
+    /// ```text
+    ///     <parent>
+    ///     EnterScope                      ; binds the parent for `super(...)`, and the class name
+    ///     EnterScope                      ; binds the prototype as the home object
+    ///     Closure constructor
+    ///     MakeClass
+    ///     <instance methods>
+    ///     ExitScope
+    ///     EnterScope                      ; binds the class as the home object and `this`
+    ///     <static members>
+    ///     ExitScope
+    ///     ExitScope
+    /// ```
+    fn class(&mut self, name: Option<&str>, parent: Option<&'a Expression>, constructor: &'a Expression, members: &'a [ClassMember], dst: Register, span: Span) -> EmitResult<()> {
+        let parent_reg = match parent {
+            Some(parent) => {
+                let register = self.alloc();
+                self.expression(parent, register)?;
+                Some(register)
             },
-            Expression::Property {
-                target,
-                name
-            } => {
-                let target = self.do_expression(*target);
-                match target {
-                    Literal::Object(properties) => {
-                        let mut output = Literal::Undefined;
-                        for (prop_name, val) in properties {
-                            if *prop_name == name {
-                                output = *val.clone();
-                                break;
-                            }
-                        }
+            None => None,
+        };
 
-                        output
-                    },
-                    Literal::Array(arr) => {
-                        let func = self.builtins.array_builtin(
-                            Literal::Array(arr).into(),
-                            name.clone()
-                        );
-
-                        *func
-                    },
-                    Literal::String(str) => {
-                        let func = self.builtins.string_builtin(
-                            Literal::String(str).into(),
-                            name.clone()
-                        );
-
-                        *func
-                    },
-                    Literal::Number(n) => {
-                        let func = self.builtins.number_builtin(
-                            Literal::Number(n).into(),
-                            name.clone()
-                        );
-
-                        *func
-                    },
-                    _ => panic!("Expected object, got {:?}", target)
-                }
-            }
+        self.emit(Bytecode::EnterScope, span);
+        if let Some(src) = parent_reg {
+            let name = self.name(ops::PARENT_CLASS);
+            self.emit(Bytecode::DeclareVar { name, src }, span);
         }
-    }
 
-    fn do_statement(&mut self, stmt: Statement) -> Option<Literal> {
-        match stmt {
-            Statement::For {
-                init,
-                condition,
-                update,
-                body
-            } => {
-                // Enter scope for the for header
-                self.scope.enter();
-                if let Some(init) = init {
-                    self.do_statement(*init);
-                };
+        let prototype = self.alloc();
+        let value = self.alloc();
+        let home = self.name(ops::HOME_OBJECT);
+
+        self.emit(Bytecode::EnterScope, span);
+        self.expression(constructor, dst)?;
+        self.emit(Bytecode::MakeClass { dst: prototype, class: dst, parent: parent_reg }, span);
+        self.emit(Bytecode::DeclareVar { name: home, src: prototype }, span);
+        for member in members.iter().filter(|member| !member.is_static) {
+            self.expression(&member.value, value)?;
+            let name = self.name(&member.name);
+            self.emit(Bytecode::DefineMethod { target: prototype, name, src: value, kind: member.kind }, member.value.span);
+        }
+        self.emit(Bytecode::ExitScope, span);
+
+        self.emit(Bytecode::EnterScope, span);
+        self.emit(Bytecode::DeclareVar { name: home, src: dst }, span);
+        let this_name = self.name("this");
+        self.emit(Bytecode::DeclareVar { name: this_name, src: dst }, span);
+        for member in members.iter().filter(|member| member.is_static) {
+            self.expression(&member.value, value)?;
+            let name = self.name(&member.name);
+            let instruction = match member.kind {
+                MemberKind::Field => Bytecode::SetProperty { target: dst, name, src: value },
+                kind => Bytecode::DefineMethod { target: dst, name, src: value, kind },
+            };
+            self.emit(instruction, member.value.span);
+        }
+        self.emit(Bytecode::ExitScope, span);
 
-                loop {
-                    if let Some(condition) = &condition {
-                        if !self.do_expression(*condition.clone()).truthy() {
-                            break;
-                        }
-                    }
+        if let Some(name) = name {
+            let name = self.name(name);
+            self.emit(Bytecode::DeclareVar { name, src: dst }, span);
+        }
+        self.emit(Bytecode::ExitScope, span);
 
-                    self.do_statement(*body.clone());
+        Ok(())
+    }
 
-                    if let Some(update) = &update {
-                        self.do_expression(*update.clone());
+    fn statement(&mut self, stmt: &'a Statement) -> EmitResult<()> {
+        let span = stmt.span;
+        self.scratch(|this| {
+            match &stmt.kind {
+                StatementKind::Expression(expr) => {
+                    let dst = this.alloc();
+                    this.expression(expr, dst)?;
+                },
+                StatementKind::Let { name, value } => {
+                    let src = this.alloc();
+                    this.expression(value, src)?;
+                    let name = this.name(name);
+                    this.emit(Bytecode::DeclareVar { name, src }, span);
+                },
+                StatementKind::Function { name, args, body } => {
+                    let func = this.function(name, args, body)?;
+                    let dst = this.alloc();
+                    this.emit(Bytecode::Closure { dst, func }, span);
+                    let name = this.name(name);
+                    this.emit(Bytecode::DeclareVar { name, src: dst }, span);
+                },
+                StatementKind::Return(expr) => {
+                    let src = this.alloc();
+                    this.expression(expr, src)?;
+                    this.unwind(0, span)?;
+                    this.emit(Bytecode::Return { src }, span);
+                },
+                StatementKind::Break => this.loop_exit(true, span)?,
+                StatementKind::Continue => this.loop_exit(false, span)?,
+                StatementKind::Throw(expr) => {
+                    let src = this.alloc();
+                    this.expression(expr, src)?;
+                    this.emit(Bytecode::Throw { src }, span);
+                },
+                StatementKind::Scope { statements } => {
+                    this.emit(Bytecode::EnterScope, span);
+                    this.in_block(Block::Scope, |this| {
+                        statements.iter().try_for_each(|stmt| this.statement(stmt))
+                    })?;
+                    this.emit(Bytecode::ExitScope, span);
+                },
+                StatementKind::If { condition, consequence, alternative } => {
+                    let cond = this.alloc();
+                    this.expression(condition, cond)?;
+                    let to_else = this.emit(Bytecode::JumpIfFalse { cond, target: 0 }, span);
+                    this.statement(consequence)?;
+
+                    match alternative {
+                        Some(alternative) => {
+                            let to_end = this.emit(Bytecode::Jump { target: 0 }, span);
+                            this.patch(to_else);
+                            this.statement(alternative)?;
+                            this.patch(to_end);
+                        },
+                        None => this.patch(to_else),
                     }
-                }
+                },
+                StatementKind::While { condition, body } => {
+                    let start = this.here();
+                    let cond = this.alloc();
+                    this.expression(condition, cond)?;
+                    let to_end = this.emit(Bytecode::JumpIfFalse { cond, target: 0 }, span);
+
+                    let breaks = this.loop_body(body, |_| Ok(start))?;
+                    this.emit(Bytecode::Jump { target: start }, span);
+
+                    this.patch(to_end);
+                    breaks.into_iter().for_each(|jump| this.patch(jump));
+                },
+                StatementKind::For { init, condition, update, body } => {
+                    // The header gets a scope of its own, renewed on every iteration.
+                    this.emit(Bytecode::EnterScope, span);
+                    this.in_block(Block::Scope, |this| {
+                        if let Some(init) = init {
+                            this.statement(init)?;
+                        }
 
-                self.scope.exit();
-            }
-            Statement::Scope {
-                statements
-            } => {
-                self.scope.enter();
-                for stmt in statements {
-                    let res = self.do_statement(stmt);
-                    if res.is_some() {
-                        self.scope.exit();
-                        return res;
-                    }
-                }
-                self.scope.exit()
-            }
-            Statement::If {
-                condition,
-                alternative,
-                consequence,
-            } => {
-                if self.do_expression(*condition).truthy() {
-                    self.do_statement(*consequence);
-                } else if let Some(alternative) = alternative {
-                    self.do_statement(*alternative);
-                }
-            }
-            Statement::Function {
-                name,
-                args,
-                body
-            } => {
-                self.scope.set(name, Literal::Function {
-                    args,
-                    body
-                });
-            }
-            Statement::Expression(expr) => {
-                self.do_expression(*expr);
-            }
-            Statement::Let {
-                name,
-                value
-            } => {
-                let res = self.do_expression(*value);
-                self.scope.set(name, res);
-            }
-            Statement::Return(expr) => {
-                // FIXME: Right now we don't verify that this is in a function.
-                let val = self.do_expression(*expr);
-                return Some(val);
+                        let start = this.here();
+                        let to_end = match condition {
+                            Some(condition) => {
+                                let cond = this.alloc();
+                                this.expression(condition, cond)?;
+                                Some(this.emit(Bytecode::JumpIfFalse { cond, target: 0 }, span))
+                            },
+                            None => None,
+                        };
+
+                        let breaks = this.loop_body(body, |this| {
+                            let target = this.here();
+                            this.emit(Bytecode::RenewScope, span);
+                            if let Some(update) = update {
+                                let dst = this.alloc();
+                                this.expression(update, dst)?;
+                            }
+                            Ok(target)
+                        })?;
+                        this.emit(Bytecode::Jump { target: start }, span);
+
+                        to_end.into_iter().chain(breaks).for_each(|jump| this.patch(jump));
+                        Ok(())
+                    })?;
+                    this.emit(Bytecode::ExitScope, span);
+                },
+                StatementKind::Try { body, catch_param, handler, finalizer } => {
+                    this.do_try(body, catch_param.as_deref(), handler.as_deref(), finalizer.as_deref(), span)?;
+                },
             }
-            Statement::While {
-                condition,
-                body
-            } => {
-                while self.do_expression(*condition.clone()).truthy() {
-                    self.do_statement(*body.clone());
+
+            Ok(())
+        })
+    }
+
+    /// ```text
+    ///     EnterTry catch          ; `rethrow` when there's no catch clause
+    ///     <body>
+    ///     LeaveTry
+    ///     Jump finally
+    /// catch:
+    ///     EnterTry rethrow        ; only with a finally clause
+    ///     <handler>
+    ///     LeaveTry
+    /// finally:
+    ///     <finalizer>
+    ///     Jump end
+    /// rethrow:
+    ///     <finalizer>
+    ///     Throw exception
+    /// end:
+    /// ```
+    fn do_try(&mut self, body: &'a Statement, catch_param: Option<&'a str>, handler: Option<&'a Statement>, finalizer: Option<&'a Statement>, span: Span) -> EmitResult<()> {
+        let exception = self.alloc();
+        let mut rethrows = Vec::new();
+
+        let enter = self.emit(Bytecode::EnterTry { handler: 0, exception }, span);
+        self.in_block(Block::Try { finalizer }, |this| this.statement(body))?;
+        self.emit(Bytecode::LeaveTry, span);
+        let to_finally = self.emit(Bytecode::Jump { target: 0 }, span);
+
+        match handler {
+            Some(handler) => {
+                self.patch(enter);
+                let catch = |this: &mut Self| {
+                    this.emit(Bytecode::EnterScope, span);
+                    this.in_block(Block::Scope, |this| {
+                        if let Some(param) = catch_param {
+                            let name = this.name(param);
+                            this.emit(Bytecode::DeclareVar { name, src: exception }, span);
+                        }
+                        this.statement(handler)
+                    })?;
+                    this.emit(Bytecode::ExitScope, span);
+                    Ok(())
+                };
+
+                if finalizer.is_some() {
+                    rethrows.push(self.emit(Bytecode::EnterTry { handler: 0, exception }, span));
+                    self.in_block(Block::Try { finalizer }, catch)?;
+                    self.emit(Bytecode::LeaveTry, span);
+                } else {
+                    catch(self)?;
                 }
-            }
+            },
+            None => rethrows.push(enter),
         }
 
-        None
+        self.patch(to_finally);
+        if let Some(finalizer) = finalizer {
+            self.statement(finalizer)?;
+            let to_end = self.emit(Bytecode::Jump { target: 0 }, span);
+
+            rethrows.into_iter().for_each(|jump| self.patch(jump));
+            self.statement(finalizer)?;
+            self.emit(Bytecode::Rethrow { src: exception }, span);
+            self.patch(to_end);
+        }
+
+        Ok(())
     }
 
-    pub fn run(&mut self) {
-        let stmts = self.ast.statements.iter().cloned().collect::<Vec<_>>();
-        self.builtins.load(&mut self.scope);
+    /// Lowers a whole script, compiling every function it contains up front.
+    pub fn emit_program(mut self, ast: &'a AST) -> EmitResult<Program> {
+        self.builders.push(ChunkBuilder::new("main", &[]));
 
-        for stmt in stmts {
-            self.do_statement(stmt);
+        for stmt in &ast.statements {
+            self.statement(stmt)?;
         }
+
+        let end = ast.statements.last().map_or_else(Span::default, |stmt| stmt.span);
+        let dst = self.alloc();
+        self.load(Literal::Undefined, dst, end);
+        self.emit(Bytecode::Return { src: dst }, end);
+
+        let builder = self.builders.pop().expect("no chunk being emitted");
+        Ok(Program {
+            main: Rc::new(builder.chunk),
+            functions: self.functions,
+        })
     }
-}
\ No newline at end of file
+}
@@ -1,614 +1,710 @@
-use std::cell::RefCell;
+use std::fmt;
 use std::rc::Rc;
 use log::trace;
-use crate::parser::{BinaryOperator, Expression, Literal, Statement, UnaryOperator, AST};
+use crate::lexer::Span;
+use crate::parser::{Array, ClassMember, Expression, ExpressionKind, Literal, MemberKind, Properties, Statement, StatementKind, AST};
 use crate::runtime::builtins::Builtins;
-use crate::runtime::scope::Scope;
+use crate::runtime::{ops, Context};
+use crate::runtime::scope::{Environment, Scope};
 
 pub enum ControlFlow {
     Return(Box<Literal>),
     Continue,
-    Break
+    Break,
+    Throw(Box<Literal>, Span)
+}
+
+/// Abrupt completions travel up the `Err` side, so `?` propagates `throw`, `return` and friends.
+pub type EvalResult<T> = Result<T, ControlFlow>;
+
+/// A thrown value that reached the top level without being caught.
+#[derive(Debug)]
+pub struct UncaughtException {
+    pub value: Literal,
+    pub span: Span
+}
+
+impl UncaughtException {
+    /// Formats the exception along with the line of `source` that threw it.
+    pub fn render(&self, source: &str) -> String {
+        format!("{}\n --> {}\n{}", self, self.span, self.span.snippet(source))
+    }
+}
+
+impl fmt::Display for UncaughtException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Literal::Object(obj) = &self.value {
+            let obj = obj.borrow();
+            let get = |key: &str| obj.get(key).map(|val| val.to_string());
+
+            match (get("name"), get("message")) {
+                (Some(name), Some(message)) if !message.is_empty() => return write!(f, "Uncaught {name}: {message}"),
+                (Some(name), _) => return write!(f, "Uncaught {name}"),
+                _ => {}
+            }
+        }
+
+        write!(f, "Uncaught {}", self.value)
+    }
+}
+
+/// A resolved assignment target, so compound assignments evaluate their operands only once.
+enum Place {
+    Variable(String),
+    Element(Literal, Literal),
+    Property(Literal, String)
 }
 
 pub struct Interpreter {
     pub scope: Scope,
-    builtins: Builtins,
+    /// Shared so operators can look up conversion methods while calling back into `self`.
+    builtins: Rc<Builtins>,
     ast: AST
 }
 
 impl Interpreter {
-    pub(crate) fn new(ast: AST) -> Self {
+    pub fn new(ast: AST) -> Self {
+        let mut scope = Scope::new();
+        let builtins = Rc::new(Builtins::new());
+        builtins.load(&mut scope);
+
         Self {
-            scope: Scope::new(),
-            builtins: Builtins::new(),
+            scope,
+            builtins,
             ast
         }
     }
 
-    fn do_expression(&mut self, expr: Expression) -> Literal {
-        match expr {
-            Expression::Identifier(name) => self.scope.get(name.clone()).expect(format!("Unknown identifier '{}'", name.clone()).as_str()).clone(),
-            Expression::Literal(lit) => lit,
-            Expression::BinaryOp {
-                left: left_expr,
-                op,
-                right
+    /// Runs `f` inside a fresh block scope, leaving it again however `f` completes.
+    fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> EvalResult<T>) -> EvalResult<T> {
+        self.scope.enter();
+        let result = f(self);
+        self.scope.exit();
+        result
+    }
+
+    /// Runs `f` in a fresh frame on top of `env` instead of the caller's scope, as a call does.
+    fn in_env<T>(&mut self, env: Environment, f: impl FnOnce(&mut Self) -> EvalResult<T>) -> EvalResult<T> {
+        let caller = self.scope.replace(env.child());
+        let result = f(self);
+        self.scope.replace(caller);
+        result
+    }
+
+    /// Readable name for the callee of a failed call, e.g. `console.log`.
+    pub(crate) fn callee_name(expr: &Expression) -> String {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => name.clone(),
+            ExpressionKind::Property { target, name } => format!("{}.{}", Self::callee_name(target), name),
+            ExpressionKind::SuperProperty { name } => format!("super.{name}"),
+            _ => "expression".into()
+        }
+    }
+
+    fn lookup(&self, name: &str, span: Span) -> EvalResult<Literal> {
+        match self.scope.get(name) {
+            Some(val) => Ok(val),
+            None => ops::throw(&self.builtins, "ReferenceError", format!("{name} is not defined"), span)
+        }
+    }
+
+    fn resolve_place(&mut self, target: &Expression, span: Span) -> EvalResult<Place> {
+        match &target.kind {
+            ExpressionKind::Identifier(name) => Ok(Place::Variable(name.clone())),
+            ExpressionKind::Index {
+                target,
+                index
             } => {
-                let left = self.do_expression(*left_expr.clone());
-                let right = self.do_expression(*right);
-
-                match op {
-                    BinaryOperator::Add => {
-                        match (left, right) {
-                            (Literal::Number(l), Literal::Number(r)) => Literal::Number(l + r),
-                            (Literal::String(l), Literal::String(r)) => Literal::String(l + &r),
-                            (Literal::String(l), Literal::Number(r)) => Literal::String(format!("{}{}", l, r)),
-                            (Literal::Number(l), Literal::String(r)) => Literal::String(format!("{}{}", l, r)),
-                            (l, r) => panic!("Unsupported operands for Add: {:?} and {:?}", l, r),
-                        }
-                    },
-                    BinaryOperator::Sub => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Number(left - right)
-                    },
-                    BinaryOperator::Mul => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Number(left * right)
-                    },
-                    BinaryOperator::Div => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Number(left / right)
-                    },
-                    BinaryOperator::Equal => {
-                        Literal::Boolean(left == right)
-                    },
-                    BinaryOperator::NotEqual => {
-                        Literal::Boolean(left != right)
-                    },
-                    BinaryOperator::GreaterThan => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Boolean(left > right)
-                    },
-                    BinaryOperator::GreaterThanOrEqual => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Boolean(left >= right)
-                    },
-                    BinaryOperator::LessThan => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Boolean(left < right)
-                    }
-                    BinaryOperator::LessThanOrEqual => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Boolean(left <= right)
-                    },
-                    BinaryOperator::BinaryOr => {
-                        let left = left.truthy();
-                        let right = right.truthy();
+                let target = self.do_expression(target)?;
+                let index = self.do_expression(index)?;
+                Ok(Place::Element(target, index))
+            },
+            ExpressionKind::Property {
+                target,
+                name
+            } => Ok(Place::Property(self.do_expression(target)?, name.clone())),
+            _ => ops::throw(&self.builtins, "ReferenceError", "Invalid left-hand side in assignment", span)
+        }
+    }
 
-                        Literal::Boolean(left || right)
-                    },
-                    BinaryOperator::BinaryAnd => {
-                        let left = left.truthy();
-                        let right = right.truthy();
+    fn read_place(&mut self, place: &Place, span: Span) -> EvalResult<Literal> {
+        match place {
+            Place::Variable(name) => self.lookup(name, span),
+            Place::Element(target, index) => ops::get_index(&self.builtins, target.clone(), index.clone(), span),
+            Place::Property(target, name) => self.read_property(target.clone(), name.clone(), target.clone(), span)
+        }
+    }
 
-                        Literal::Boolean(left && right)
-                    },
-                    BinaryOperator::Mod => {
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        Literal::Number(left % right)
+    fn write_place(&mut self, place: Place, value: Literal, span: Span) -> EvalResult<()> {
+        match place {
+            Place::Variable(name) => {
+                self.scope.set(name, value);
+                Ok(())
+            },
+            Place::Element(target, index) => ops::set_index(&self.builtins, target, index, value, span),
+            Place::Property(target, name) => self.write_property(target, name, value, span)
+        }
+    }
+
+    /// Reads `target.name`, running the getter with `receiver` as `this` if it's an accessor.
+    fn read_property(&mut self, target: Literal, name: String, receiver: Literal, span: Span) -> EvalResult<Literal> {
+        match ops::get_property(&self.builtins, target, name, span)? {
+            Literal::Accessor { get: Some(getter), .. } => self.call(*getter, receiver, Vec::new(), "getter", span),
+            Literal::Accessor { get: None, .. } => Ok(Literal::Undefined),
+            value => Ok(value)
+        }
+    }
+
+    fn write_property(&mut self, target: Literal, name: String, value: Literal, span: Span) -> EvalResult<()> {
+        if let Some(setter) = ops::set_property(&self.builtins, target.clone(), name, value.clone(), span)? {
+            self.call(setter, target, vec![value], "setter", span)?;
+        }
+
+        Ok(())
+    }
+
+    /// `super.name`: looked up past the running method's home object, but with the current `this`.
+    fn read_super(&mut self, name: String, span: Span) -> EvalResult<Literal> {
+        let base = ops::super_base(&self.builtins, self.scope.get(ops::HOME_OBJECT), span)?;
+        let receiver = self.scope.get("this").unwrap_or(Literal::Undefined);
+        self.read_property(base, name, receiver, span)
+    }
+
+    /// Creates a class. Methods close over a scope binding their home object, which is the
+    /// prototype for instance members and the class itself for static ones.
+    fn do_class(&mut self, name: Option<&String>, parent: Option<Literal>, constructor: &Expression, members: &[ClassMember], span: Span) -> EvalResult<Literal> {
+        self.in_scope(|this| {
+            if let Some(parent) = &parent {
+                this.scope.declare(ops::PARENT_CLASS, parent.clone());
+            }
+
+            let (statics, members): (Vec<_>, Vec<_>) = members.iter().partition(|member| member.is_static);
+            let class = this.in_scope(|this| {
+                let class = this.do_expression(constructor)?;
+                let prototype = ops::make_class(&this.builtins, &class, parent, span)?;
+                this.scope.declare(ops::HOME_OBJECT, prototype.clone());
+
+                for member in members {
+                    let func = this.do_expression(&member.value)?;
+                    ops::define_method(&prototype, member.name.clone(), func, member.kind);
+                }
+
+                Ok(class)
+            })?;
+
+            // Static field initializers see the class as `this`.
+            this.in_scope(|this| {
+                this.scope.declare(ops::HOME_OBJECT, class.clone());
+                this.scope.declare("this", class.clone());
+
+                for member in statics {
+                    let value = this.do_expression(&member.value)?;
+                    match member.kind {
+                        MemberKind::Field => this.write_property(class.clone(), member.name.clone(), value, span)?,
+                        kind => ops::define_method(&class, member.name.clone(), value, kind)
                     }
-                    BinaryOperator::PlusEqual => {
-                        let var = match *left_expr.clone() {
-                            Expression::Identifier(name) => name,
-                            _ => panic!("Unable to assign to non-lvalue.")
-                        };
-
-                        let val = match (left, right) {
-                            (Literal::Number(l), Literal::Number(r)) => Literal::Number(l + r),
-                            (Literal::String(l), Literal::String(r)) => Literal::String(l + &r),
-                            (Literal::String(l), Literal::Number(r)) => Literal::String(format!("{}{}", l, r)),
-                            (Literal::Number(l), Literal::String(r)) => Literal::String(format!("{}{}", l, r)),
-                            (l, r) => panic!("Unsupported operands for Add: {:?} and {:?}", l, r),
-                        };
-
-                        self.scope.set(var, val.clone());
-
-                        val.clone()
-                    },
-                    BinaryOperator::MinusEqual => {
-                        let var = match *left_expr.clone() {
-                            Expression::Identifier(name) => name,
-                            _ => panic!("Unable to assign to non-lvalue.")
-                        };
-
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        let lit = Literal::Number(left - right);
-                        self.scope.set(var, lit.clone());
-                        lit
-                    },
-                    BinaryOperator::MulEqual => {
-                        let var = match *left_expr.clone() {
-                            Expression::Identifier(name) => name,
-                            _ => panic!("Unable to assign to non-lvalue.")
-                        };
-
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        let lit = Literal::Number(left * right);
-                        self.scope.set(var, lit.clone());
-                        lit
-                    },
-                    BinaryOperator::DivEqual => {
-                        let var = match *left_expr.clone() {
-                            Expression::Identifier(name) => name,
-                            _ => panic!("Unable to assign to non-lvalue.")
-                        };
-
-                        let left = match left {
-                            Literal::Number(left) => left,
-                            _ => panic!("Expected number, got {:?}", left)
-                        };
-                        let right = match right {
-                            Literal::Number(right) => right,
-                            _ => panic!("Expected number, got {:?}", right)
-                        };
-
-                        let lit = Literal::Number(left / right);
-                        self.scope.set(var, lit.clone());
-                        lit
+                }
+
+                Ok(())
+            })?;
+
+            if let Some(name) = name {
+                this.scope.declare(name, class.clone());
+            }
+            Ok(class)
+        })
+    }
+
+    fn do_args(&mut self, args: &[Box<Expression>]) -> EvalResult<Vec<Literal>> {
+        args.iter()
+            .map(|arg| self.do_expression(arg))
+            .collect()
+    }
+
+    /// Calls `func` with `receiver` bound to `this`. Class constructors throw here, as only
+    /// `new` and `super(...)` may run them, through `construct`.
+    fn call(&mut self, func: Literal, receiver: Literal, args: Vec<Literal>, name: &str, span: Span) -> EvalResult<Literal> {
+        ops::check_callable(&self.builtins, &func, name, span)?;
+        self.construct(func, receiver, args, name, span)
+    }
+
+    /// Runs `func` with `receiver` bound to `this`, whatever kind of function it is.
+    fn construct(&mut self, func: Literal, receiver: Literal, args: Vec<Literal>, name: &str, span: Span) -> EvalResult<Literal> {
+        match func {
+            Literal::Function {
+                args: func_args,
+                body,
+                env,
+                ..
+            } => {
+                let result = self.in_env(env, |this| {
+                    this.scope.declare("this", receiver);
+                    this.scope.declare("arguments", Literal::Array(Array::new(args.clone())));
+                    let mut args = args.into_iter();
+                    for param_name in func_args {
+                        let val = args.next().map_or(Literal::Undefined, |arg| arg);
+                        this.scope.declare(param_name, val);
                     }
+
+                    this.do_statement(&body)
+                });
+
+                match result {
+                    Err(ControlFlow::Return(val)) => Ok(*val),
+                    Err(throw @ ControlFlow::Throw(..)) => Err(throw),
+                    _ => Ok(Literal::Undefined)
                 }
             },
-            Expression::Array {
-                elements
+            Literal::NativeFunction(f) => ops::call_native(&Rc::clone(&self.builtins), &f, self, receiver, args, span),
+            _ => ops::throw(&self.builtins, "TypeError", format!("{name} is not a function"), span)
+        }
+    }
+
+    /// Prefix or postfix `++`/`--`: stores `target + delta` and evaluates to the new value if
+    /// `prefix`, or to the old one, converted to a number, otherwise.
+    fn do_update(&mut self, target: &Expression, delta: f64, prefix: bool, span: Span) -> EvalResult<Literal> {
+        let place = self.resolve_place(target, span)?;
+        let val = self.read_place(&place, span)?;
+        let val = ops::coerce_number(&Rc::clone(&self.builtins), self, val, span)?;
+
+        self.write_place(place, Literal::Number(val + delta), span)?;
+        Ok(Literal::Number(if prefix { val + delta } else { val }))
+    }
+
+    fn do_expression(&mut self, expr: &Expression) -> EvalResult<Literal> {
+        let span = expr.span;
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.lookup(name, span),
+            ExpressionKind::Literal(lit) => Ok(lit.clone()),
+            ExpressionKind::BinaryOp {
+                left,
+                op,
+                right
             } => {
-                Literal::Array(Rc::new(RefCell::new(elements.iter().map(|el| self.do_expression(*el.clone()).into() ).collect())))
+                if op.is_assignment() {
+                    let place = self.resolve_place(left, span)?;
+                    let left = self.read_place(&place, span)?;
+                    let right = self.do_expression(right)?;
+
+                    let val = ops::binary_op(&Rc::clone(&self.builtins), self, op, left, right, span)?;
+                    self.write_place(place, val.clone(), span)?;
+                    return Ok(val);
+                }
+
+                let left = self.do_expression(left)?;
+                let right = self.do_expression(right)?;
+                ops::binary_op(&Rc::clone(&self.builtins), self, op, left, right, span)
             },
-            Expression::Increment {
-                target
+            ExpressionKind::Logical {
+                left,
+                op,
+                right
             } => {
-                let var_name = match *target.clone() {
-                    Expression::Identifier(name) => name,
-                    _ => panic!("Expected identifier, got {:?}", target)
-                };
+                if op.is_assignment() {
+                    let place = self.resolve_place(left, span)?;
+                    let left = self.read_place(&place, span)?;
+                    if op.short_circuits(&left) {
+                        return Ok(left);
+                    }
 
-                let val = self.do_expression(*target.clone());
-                let val = match val {
-                    Literal::Number(num) => num,
-                    _ => panic!("Expected number to increment, got {:?}", val)
-                };
+                    let val = self.do_expression(right)?;
+                    self.write_place(place, val.clone(), span)?;
+                    return Ok(val);
+                }
 
-                self.scope.set(var_name, Literal::Number(val + 1.0));
-                Literal::Number(val)
+                let left = self.do_expression(left)?;
+                if op.short_circuits(&left) {
+                    Ok(left)
+                } else {
+                    self.do_expression(right)
+                }
             },
-            Expression::Decrement {
-                target
+            ExpressionKind::Conditional {
+                condition,
+                consequent,
+                alternative
             } => {
-                let var_name = match *target.clone() {
-                    Expression::Identifier(name) => name,
-                    _ => panic!("Expected identifier, got {:?}", target)
-                };
-
-                let val = self.do_expression(*target.clone());
-                let val = match val {
-                    Literal::Number(num) => num,
-                    _ => panic!("Expected number to decrement, got {:?}", val)
-                };
+                if self.do_expression(condition)?.truthy() {
+                    self.do_expression(consequent)
+                } else {
+                    self.do_expression(alternative)
+                }
+            },
+            ExpressionKind::Array {
+                elements
+            } => {
+                let elements = elements.iter()
+                    .map(|el| self.do_expression(el))
+                    .collect::<EvalResult<Vec<_>>>()?;
 
-                self.scope.set(var_name, Literal::Number(val - 1.0));
-                Literal::Number(val)
+                Ok(Literal::Array(Array::new(elements)))
             },
-            Expression::Assignment {
+            ExpressionKind::Increment {
+                target,
+                prefix
+            } => self.do_update(target, 1.0, *prefix, span),
+            ExpressionKind::Decrement {
+                target,
+                prefix
+            } => self.do_update(target, -1.0, *prefix, span),
+            ExpressionKind::Assignment {
                 target,
                 value
             } => {
-                match *target {
-                    Expression::Identifier(name) => {
-                        let res = self.do_expression(*value);
-                        self.scope.set(name, res.clone());
-                        res
-                    },
-                    Expression::Index {
+                let place = self.resolve_place(target, span)?;
+                let res = self.do_expression(value)?;
+                self.write_place(place, res.clone(), span)?;
+                Ok(res)
+            },
+            ExpressionKind::FunctionCall {
+                callee,
+                args
+            } => {
+                let callee_name = Self::callee_name(callee);
+                // Calling a property passes the object it was read from as `this`.
+                let (func, receiver) = match &callee.kind {
+                    ExpressionKind::Property {
                         target,
-                        index
+                        name
                     } => {
-                        match *target {
-                            Expression::Identifier(name) => {
-                                let res = self.do_expression(*value);
-                                let arr = self.scope.get(name.clone()).expect(format!("Unknown identifier '{}'", name.clone()).as_str()).clone();
-                                let arr = match arr {
-                                    Literal::Array(arr) => arr,
-                                    _ => panic!("Expected array, got {:?}", arr)
-                                };
-                                let index = self.do_expression(*index).clone();
-                                let index = match index {
-                                    Literal::Number(index) => index as usize,
-                                    _ => panic!("Expected number, got {:?}", index)
-                                };
-
-                                arr.borrow_mut()[index] = res.into();
-                                self.scope.set(name.clone(), Literal::Array(arr.clone()));
-                                Literal::Array(arr)
-                            },
-                            _ => panic!("Expected identifier, got {:?}", target)
-                        }
+                        let target = self.do_expression(target)?;
+                        (self.read_property(target.clone(), name.clone(), target.clone(), callee.span)?, target)
                     },
-                    Expression::Property {
-                        target,
+                    ExpressionKind::SuperProperty {
                         name
                     } => {
-                        match *target {
-                            Expression::Identifier(obj_name) => {
-                                let res = self.do_expression(*value);
-                                let obj = self.scope.get(obj_name.clone()).expect(format!("Unknown identifier '{}'", obj_name.clone()).as_str()).clone();
-                                let mut obj = match obj {
-                                    Literal::Object(obj) => obj,
-                                    _ => panic!("Expected object, got {:?}", obj)
-                                };
-
-                                for i in 0..obj.len() {
-                                    if obj[i].0 == name {
-                                        obj[i] = (name.clone(), res.clone().into());
-                                        self.scope.set(obj_name.clone(), Literal::Object(obj.clone()));
-                                        return Literal::Object(obj);
-                                    }
-                                }
-
-                                // Not found, add it
-                                obj.push((name.clone(), res.clone().into()));
-                                self.scope.set(obj_name.clone(), Literal::Object(obj.clone()));
-                                res
-                            }
-                            _ => panic!("Expected identifier, got {:?}", target)
-                        }
+                        let receiver = self.scope.get("this").unwrap_or(Literal::Undefined);
+                        (self.read_super(name.clone(), callee.span)?, receiver)
                     },
-                    _ => panic!("Expected identifier, got {:?}", target)
-                }
+                    _ => (self.do_expression(callee)?, Literal::Undefined)
+                };
+                let args = self.do_args(args)?;
+
+                self.call(func, receiver, args, &callee_name, span)
             },
-            Expression::FunctionCall {
+            ExpressionKind::New {
                 callee,
                 args
             } => {
-                let func = self.do_expression(*callee);
+                let callee_name = Self::callee_name(callee);
+                let func = self.do_expression(callee)?;
+                let args = self.do_args(args)?;
 
-                match func {
-                    Literal::Function {
-                        args: func_args,
-                        body
-                    } => {
-                        if func_args.len() != args.len() {
-                            panic!("Expected {} arguments, got {}", func_args.len(), args.len());
-                        }
-
-                        self.scope.enter();
-
-                        for (param_name, param_expr) in func_args.iter().zip(args.iter()) {
-                            let val = self.do_expression(*param_expr.clone());
-                            self.scope.set(param_name.clone(), val);
-                        }
-
-                        let ret = match self.do_statement(*body) {
-                            Some(ControlFlow::Return(val)) => Some(*val),
-                            _ => None,
-                        };
-
-                        self.scope.exit();
-
-                        ret.unwrap_or(Literal::Undefined)
-                    },
-                    Literal::NativeFunction(f) => {
-                        let args = args.into_iter().map(|arg| self.do_expression(*arg).into()).collect::<Vec<_>>();
-
-                        *(f.func)(args)
-                    }
-                    _ => panic!("Expected function, got {:?}", func)
-                }
+                let instance = ops::new_instance(&self.builtins, &func, &callee_name, span)?;
+                let result = self.construct(func, instance.clone(), args, &callee_name, span)?;
+                Ok(ops::constructed(instance, result))
             },
-            Expression::Index {
-                target,
-                index
+            ExpressionKind::This => Ok(self.scope.get("this").unwrap_or(Literal::Undefined)),
+            ExpressionKind::Class {
+                name,
+                parent,
+                constructor,
+                members
             } => {
-                let index = self.do_expression(*index);
-                let target = match *target {
-                    Expression::Identifier(name) => self.scope.get(name.clone()).expect(format!("Unknown identifier '{}'", name.clone()).as_str()).clone(),
-                    _ => panic!("Expected identifier, got {:?}", target)
-                };
-                let index = match index {
-                    Literal::Number(index) => index as usize,
-                    _ => panic!("Expected number, got {:?}", index)
+                let parent = parent.as_ref().map(|parent| self.do_expression(parent)).transpose()?;
+                self.do_class(name.as_ref(), parent, constructor, members, span)
+            },
+            ExpressionKind::SuperCall {
+                args
+            } => {
+                let Some(parent) = self.scope.get(ops::PARENT_CLASS) else {
+                    return ops::throw(&self.builtins, "SyntaxError", "'super' keyword unexpected here", span);
                 };
-
-                let arr = match target {
-                    Literal::Array(arr) => arr,
-                    _ => panic!("Expected array, got {:?}", target)
+                let args = match args {
+                    Some(args) => self.do_args(args)?,
+                    None => match self.scope.get("arguments") {
+                        Some(Literal::Array(args)) => args.borrow().clone(),
+                        _ => Vec::new()
+                    }
                 };
 
-                if index >= arr.borrow().len() {
-                    panic!("Index out of bounds: {index}");
-                }
-                *arr.borrow()[index].clone()
+                let receiver = self.scope.get("this").unwrap_or(Literal::Undefined);
+                let result = self.construct(parent.clone(), receiver.clone(), args, "super", span)?;
+                ops::adopt_super_result(&parent, &receiver, result);
+                Ok(Literal::Undefined)
+            },
+            ExpressionKind::SuperProperty {
+                name
+            } => self.read_super(name.clone(), span),
+            ExpressionKind::Index {
+                target,
+                index
+            } => {
+                let target = self.do_expression(target)?;
+                let index = self.do_expression(index)?;
+                ops::get_index(&self.builtins, target, index, span)
             },
-            Expression::Object {
+            ExpressionKind::Object {
                 properties
             } => {
-                Literal::Object(properties.into_iter().map(|(name, val)| {
-                    (name, self.do_expression(*val).into())
-                }).collect())
+                let obj = self.builtins.new_object(Vec::new());
+                for (name, val) in properties {
+                    let val = self.do_expression(val)?;
+                    self.write_property(obj.clone(), name.clone(), val, span)?;
+                }
+
+                Ok(obj)
             },
-            Expression::UnaryOp {
+            ExpressionKind::UnaryOp {
                 op,
                 expr
             } => {
-                match op {
-                    UnaryOperator::Negate => {
-                        let expr = self.do_expression(*expr);
-                        match expr {
-                            Literal::Number(num) => Literal::Number(-num),
-                            _ => panic!("Expected number, got {:?}", expr)
-                        }
-                    },
-                    UnaryOperator::Not => {
-                        let expr = self.do_expression(*expr);
-                        Literal::Boolean(!expr.truthy())
-                    }
-                }
+                let val = self.do_expression(expr)?;
+                ops::unary_op(&Rc::clone(&self.builtins), self, op, val, span)
             },
-            Expression::Property {
+            ExpressionKind::Property {
                 target,
                 name
             } => {
-                let target = self.do_expression(*target);
-                match target {
-                    Literal::Object(properties) => {
-                        let mut output = Literal::Undefined;
-                        for (prop_name, val) in properties {
-                            if *prop_name == name {
-                                output = *val.clone();
-                                break;
-                            }
-                        }
-
-                        output
-                    },
-                    Literal::Array(arr) => {
-                        let func = self.builtins.array_builtin(
-                            Literal::Array(arr).into(),
-                            name.clone()
-                        );
-
-                        *func
-                    },
-                    Literal::String(str) => {
-                        let func = self.builtins.string_builtin(
-                            Literal::String(str).into(),
-                            name.clone()
-                        );
+                let target = self.do_expression(target)?;
+                self.read_property(target.clone(), name.clone(), target, span)
+            },
+            ExpressionKind::Template {
+                quasis,
+                expressions
+            } => {
+                let mut text = quasis[0].clone();
+                for (expr, quasi) in expressions.iter().zip(&quasis[1..]) {
+                    let value = self.do_expression(expr)?;
+                    text += &ops::to_string(&Rc::clone(&self.builtins), self, value, span)?;
+                    text += quasi;
+                }
 
-                        *func
-                    },
-                    Literal::Number(n) => {
-                        let func = self.builtins.number_builtin(
-                            Literal::Number(n).into(),
-                            name.clone()
-                        );
+                Ok(Literal::String(text))
+            },
+            ExpressionKind::TemplateObject(strings) => Ok(strings.clone()),
+            ExpressionKind::Function {
+                name,
+                args,
+                body
+            } => {
+                let body = Rc::new((**body).clone());
+                let Some(name) = name else {
+                    return Ok(self.builtins.new_function(args.clone(), body, self.scope.capture()));
+                };
 
-                        *func
-                    },
-                    _ => panic!("Expected object, got {:?}", target)
-                }
+                // A named function expression can call itself, but its name doesn't leak into the enclosing scope.
+                let env = self.scope.capture().child();
+                let func = self.builtins.new_function(args.clone(), body, env.clone());
+                env.declare(name.clone(), func.clone());
+                Ok(func)
             }
         }
     }
 
-    fn do_statement(&mut self, stmt: Statement) -> Option<ControlFlow> {
-        match stmt {
-            Statement::For {
+    fn do_statement(&mut self, stmt: &Statement) -> EvalResult<()> {
+        let span = stmt.span;
+        match &stmt.kind {
+            StatementKind::For {
                 init,
                 condition,
                 update,
                 body
             } => {
                 // Enter scope for the for header
-                self.scope.enter();
-                if let Some(init) = init {
-                    self.do_statement(*init);
-                };
-
-                loop {
-                    if let Some(condition) = &condition {
-                        if !self.do_expression(*condition.clone()).truthy() {
+                self.in_scope(|this| {
+                    if let Some(init) = init {
+                        this.do_statement(init)?;
+                    };
+
+                    loop {
+                        if let Some(condition) = &condition
+                            && !this.do_expression(condition)?.truthy() {
                             break;
                         }
-                    }
 
-                    match self.do_statement(*body.clone()) {
-                        Some(ControlFlow::Return(val)) => { return Some(ControlFlow::Return(val)); }
-                        Some(ControlFlow::Continue) => { continue; }
-                        Some(ControlFlow::Break) => { break; }
-                        None => {}
-                    }
+                        match this.do_statement(body) {
+                            Err(ControlFlow::Break) => break,
+                            Ok(()) | Err(ControlFlow::Continue) => {},
+                            Err(other) => return Err(other)
+                        }
 
-                    if let Some(update) = &update {
-                        self.do_expression(*update.clone());
+                        // Closures from this iteration keep its bindings; the next one gets a copy.
+                        this.scope.renew();
+
+                        if let Some(update) = &update {
+                            this.do_expression(update)?;
+                        }
                     }
-                }
 
-                self.scope.exit();
+                    Ok(())
+                })
             }
-            Statement::Scope {
+            StatementKind::Scope {
                 statements
             } => {
-                self.scope.enter();
-                for stmt in statements {
-                    let res = self.do_statement(stmt);
-                    match res {
-                        Some(ControlFlow::Return(val)) => {
-                            self.scope.exit();
-                            return Some(ControlFlow::Return(val));
-                        },
-                        Some(ControlFlow::Continue) => {
-                            return Some(ControlFlow::Continue);
-                        },
-                        Some(ControlFlow::Break) => {
-                            self.scope.exit();
-                            return Some(ControlFlow::Break);
-                        },
-                        _ => {}
+                self.in_scope(|this| {
+                    for stmt in statements {
+                        this.do_statement(stmt)?;
                     }
-                }
-                self.scope.exit()
+
+                    Ok(())
+                })
             }
-            Statement::If {
+            StatementKind::If {
                 condition,
                 alternative,
                 consequence,
             } => {
-                if self.do_expression(*condition).truthy() {
-                    return self.do_statement(*consequence);
+                if self.do_expression(condition)?.truthy() {
+                    self.do_statement(consequence)
                 } else if let Some(alternative) = alternative {
-                    return self.do_statement(*alternative);
+                    self.do_statement(alternative)
+                } else {
+                    Ok(())
                 }
             }
-            Statement::Function {
+            StatementKind::Function {
                 name,
                 args,
                 body
             } => {
-                self.scope.set(name, Literal::Function {
-                    args,
-                    body
-                });
+                let func = self.builtins.new_function(args.clone(), Rc::new((**body).clone()), self.scope.capture());
+                self.scope.declare(name.clone(), func);
+                Ok(())
             }
-            Statement::Expression(expr) => {
-                self.do_expression(*expr);
+            StatementKind::Expression(expr) => {
+                self.do_expression(expr)?;
+                Ok(())
             }
-            Statement::Let {
+            StatementKind::Let {
                 name,
                 value
             } => {
-                let res = self.do_expression(*value);
-                self.scope.set(name, res);
+                let res = self.do_expression(value)?;
+                self.scope.declare(name.clone(), res);
+                Ok(())
             }
-            Statement::Return(expr) => {
+            StatementKind::Return(expr) => {
                 // FIXME: Right now we don't verify that this is in a function.
-                let val = self.do_expression(*expr);
-                return Some(ControlFlow::Return(val.into()));
+                let val = self.do_expression(expr)?;
+                Err(ControlFlow::Return(val.into()))
             }
-            Statement::Continue => {
-                return Some(ControlFlow::Continue);
-            },
-            Statement::Break => {
-                return Some(ControlFlow::Break);
-            }
-            Statement::While {
+            StatementKind::Continue => Err(ControlFlow::Continue),
+            StatementKind::Break => Err(ControlFlow::Break),
+            StatementKind::While {
                 condition,
                 body
             } => {
-                loop {
-                    if !self.do_expression(*condition.clone()).truthy() {
-                        break;
+                while self.do_expression(condition)?.truthy() {
+                    match self.do_statement(body) {
+                        Err(ControlFlow::Break) => { trace!("Breaking out of while loop."); break; },
+                        Ok(()) | Err(ControlFlow::Continue) => {},
+                        Err(other) => return Err(other)
                     }
+                }
 
-                    match self.do_statement(*body.clone()) {
-                        Some(ControlFlow::Return(val)) => { return Some(ControlFlow::Return(val)); },
-                        Some(ControlFlow::Break) => { trace!("Breaking out of while loop."); break; },
-                        Some(ControlFlow::Continue) => { continue; }, // This continues the loop
-                        None => {}
-                    }
+                Ok(())
+            }
+            StatementKind::Throw(expr) => {
+                let val = self.do_expression(expr)?;
+                Err(ControlFlow::Throw(val.into(), span))
+            }
+            StatementKind::Try {
+                body,
+                catch_param,
+                handler,
+                finalizer
+            } => {
+                let result = match (self.do_statement(body), handler) {
+                    (Err(ControlFlow::Throw(val, _)), Some(handler)) => {
+                        self.in_scope(|this| {
+                            if let Some(param) = catch_param {
+                                this.scope.declare(param.clone(), *val);
+                            }
+
+                            this.do_statement(handler)
+                        })
+                    },
+                    (result, _) => result
+                };
+
+                // An abrupt completion inside `finally` overrides whatever the try/catch produced.
+                if let Some(finalizer) = finalizer {
+                    self.do_statement(finalizer)?;
                 }
+
+                result
             }
+        }
+    }
+
+    /// Runs a top-level statement, evaluating to its value if it is an expression statement.
+    fn top_level(&mut self, stmt: &Statement) -> Result<Option<Literal>, UncaughtException> {
+        let result = match &stmt.kind {
+            StatementKind::Expression(expr) => self.do_expression(expr).map(Some),
+            _ => self.do_statement(stmt).map(|_| None),
+        };
+
+        match result {
+            Ok(value) => Ok(value),
+            Err(ControlFlow::Throw(value, span)) => Err(UncaughtException { value: *value, span }),
+            Err(_) => Ok(None)
+        }
+    }
+
+    /// Calls `func` from outside any script, with `this` undefined. `name` is only used in the
+    /// error if `func` isn't callable.
+    pub fn call_function(&mut self, func: Literal, args: Vec<Literal>, name: &str) -> Result<Literal, UncaughtException> {
+        match self.call(func, Literal::Undefined, args, name, Span::default()) {
+            Ok(value) => Ok(value),
+            Err(ControlFlow::Throw(value, span)) => Err(UncaughtException { value: *value, span }),
+            Err(_) => Ok(Literal::Undefined)
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), UncaughtException> {
+        let stmts = std::mem::take(&mut self.ast.statements);
+
+        for stmt in &stmts {
+            self.top_level(stmt)?;
+        }
+
+        Ok(())
+    }
 
+    /// Runs more code against the existing global scope, as the REPL does with each entry.
+    /// Evaluates to the value of the final statement if it is an expression.
+    pub fn eval(&mut self, ast: AST) -> Result<Option<Literal>, UncaughtException> {
+        let mut value = None;
+        for stmt in &ast.statements {
+            value = self.top_level(stmt)?;
         }
 
-        None
+        Ok(value)
+    }
+}
+
+impl Context for Interpreter {
+    fn global(&self, name: &str) -> Option<Literal> {
+        self.scope.get_global(name)
     }
 
-    pub fn run(&mut self) {
-        let stmts = self.ast.statements.iter().cloned().collect::<Vec<_>>();
-        self.builtins.load(&mut self.scope);
+    fn set_global(&mut self, name: &str, value: Literal) {
+        self.scope.set_global(name, value);
+    }
+
+    fn new_object(&self, properties: Properties) -> Literal {
+        self.builtins.new_object(properties)
+    }
 
-        for stmt in stmts {
-            self.do_statement(stmt);
+    fn call(&mut self, func: &Literal, this: Literal, args: Vec<Literal>) -> Result<Literal, Literal> {
+        match Interpreter::call(self, func.clone(), this, args, &func.to_string(), Span::default()) {
+            Ok(value) => Ok(value),
+            Err(ControlFlow::Throw(value, _)) => Err(*value),
+            Err(_) => Ok(Literal::Undefined)
         }
     }
-}
\ No newline at end of file
+
+    fn to_js_string(&mut self, value: Literal) -> Result<String, Literal> {
+        ops::to_string(&Rc::clone(&self.builtins), self, value, Span::default()).map_err(ops::thrown)
+    }
+
+    fn to_js_number(&mut self, value: Literal) -> Result<f64, Literal> {
+        ops::coerce_number(&Rc::clone(&self.builtins), self, value, Span::default()).map_err(ops::thrown)
+    }
+
+    fn error(&self, name: &str, message: String) -> Literal {
+        self.builtins.error(name, message)
+    }
+}
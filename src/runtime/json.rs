@@ -0,0 +1,451 @@
+//! `JSON.parse` and `JSON.stringify`.
+
+use std::rc::Rc;
+use crate::parser::{Array, ArrayRef, Literal, ObjectRef};
+use crate::runtime::{Args, Context};
+
+/// `JSON.parse(text, reviver)`: throws a SyntaxError for anything that isn't valid JSON, then
+/// passes every value through `reviver(key, value)`, innermost first, if one is given.
+pub fn parse(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+    let text = args.get(0).to_string();
+    let value = Parser { cx: &*cx, chars: text.chars().collect(), pos: 0 }.parse()?;
+
+    match args.get(1) {
+        reviver @ (Literal::Function { .. } | Literal::NativeFunction(_)) => {
+            let root = cx.new_object(vec![(String::new(), value.into())]);
+            revive(cx, &reviver, &root, String::new())
+        },
+        _ => Ok(value)
+    }
+}
+
+struct Parser<'a> {
+    cx: &'a dyn Context,
+    chars: Vec<char>,
+    pos: usize
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Literal, Literal> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(value),
+            Some(_) => Err(self.unexpected())
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn unexpected(&self) -> Literal {
+        match self.peek() {
+            Some(c) => self.cx.error("SyntaxError", format!("Unexpected token {c} in JSON at position {}", self.pos)),
+            None => self.cx.error("SyntaxError", "Unexpected end of JSON input".into())
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Literal> {
+        if self.peek() != Some(expected) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Literal, Literal> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Literal::String),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.word("true", Literal::Boolean(true)),
+            Some('f') => self.word("false", Literal::Boolean(false)),
+            Some('n') => self.word("null", Literal::Null),
+            _ => Err(self.unexpected())
+        }
+    }
+
+    fn word(&mut self, word: &str, value: Literal) -> Result<Literal, Literal> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+
+        Ok(value)
+    }
+
+    fn object(&mut self) -> Result<Literal, Literal> {
+        self.expect('{')?;
+        let object = self.cx.new_object(Vec::new());
+        let Literal::Object(obj) = &object else { unreachable!() };
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(object);
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.unexpected());
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            // A repeated key keeps its first position but takes the last value.
+            obj.borrow_mut().set(key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(object);
+                },
+                _ => return Err(self.unexpected())
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Literal, Literal> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Literal::Array(Array::new(elements)));
+        }
+
+        loop {
+            elements.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Literal::Array(Array::new(elements)));
+                },
+                _ => return Err(self.unexpected())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Literal> {
+        self.expect('"')?;
+        // Collected as UTF-16 so that `\u` escapes can spell out surrogate pairs.
+        let mut units = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(String::from_utf16_lossy(&units));
+                },
+                Some('\\') => {
+                    self.pos += 1;
+                    let unit = match self.peek() {
+                        Some('"') => '"' as u16,
+                        Some('\\') => '\\' as u16,
+                        Some('/') => '/' as u16,
+                        Some('b') => 0x08,
+                        Some('f') => 0x0c,
+                        Some('n') => '\n' as u16,
+                        Some('r') => '\r' as u16,
+                        Some('t') => '\t' as u16,
+                        Some('u') => {
+                            self.pos += 1;
+                            let mut unit = 0;
+                            for _ in 0..4 {
+                                let digit = self.peek().and_then(|c| c.to_digit(16)).ok_or_else(|| self.unexpected())?;
+                                unit = unit * 16 + digit as u16;
+                                self.pos += 1;
+                            }
+                            units.push(unit);
+                            continue;
+                        },
+                        _ => return Err(self.unexpected())
+                    };
+                    units.push(unit);
+                    self.pos += 1;
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(self.unexpected()),
+                Some(c) => {
+                    let mut buf = [0; 2];
+                    units.extend_from_slice(c.encode_utf16(&mut buf));
+                    self.pos += 1;
+                },
+                None => return Err(self.unexpected())
+            }
+        }
+    }
+
+    fn digits(&mut self) -> Result<(), Literal> {
+        if !matches!(self.peek(), Some('0'..='9')) {
+            return Err(self.unexpected());
+        }
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.pos += 1;
+        }
+
+        Ok(())
+    }
+
+    /// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+    fn number(&mut self) -> Result<Literal, Literal> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some('0') => self.pos += 1,
+            _ => self.digits()?
+        }
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            self.digits()?;
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            self.digits()?;
+        }
+
+        let text: String = self.chars[start..self.pos].iter().collect();
+        Ok(Literal::Number(text.parse().unwrap()))
+    }
+}
+
+/// The property `key` of a parsed object or array.
+fn own_property(holder: &Literal, key: &str) -> Literal {
+    match holder {
+        Literal::Object(obj) => obj.borrow().get_own(key),
+        Literal::Array(arr) => key.parse::<usize>().ok().and_then(|i| arr.borrow().get(i).cloned()),
+        _ => None
+    }.unwrap_or(Literal::Undefined)
+}
+
+/// Revives `holder[key]`'s members before the value itself. Members the reviver turns into
+/// `undefined` are removed from objects, and left as `undefined` in arrays.
+fn revive(cx: &mut dyn Context, reviver: &Literal, holder: &Literal, key: String) -> Result<Literal, Literal> {
+    let value = own_property(holder, &key);
+
+    match &value {
+        Literal::Array(arr) => {
+            let len = arr.borrow().len();
+            for i in 0..len {
+                let revived = revive(cx, reviver, &value, i.to_string())?;
+                if let Some(element) = arr.borrow_mut().get_mut(i) {
+                    *element = revived;
+                }
+            }
+        },
+        Literal::Object(obj) => {
+            let keys: Vec<String> = obj.borrow().properties.iter().map(|(key, _)| key.clone()).collect();
+            for key in keys {
+                match revive(cx, reviver, &value, key.clone())? {
+                    Literal::Undefined => obj.borrow_mut().properties.retain(|(name, _)| *name != key),
+                    revived => obj.borrow_mut().set(key, revived)
+                }
+            }
+        },
+        _ => {}
+    }
+
+    cx.call(reviver, holder.clone(), vec![Literal::String(key), value])
+}
+
+/// `JSON.stringify(value, replacer, space)`. `replacer` is a function that can swap out each
+/// value, or an array listing the object keys to keep. `space` indents nested values by that
+/// many spaces (up to 10), or by the string itself (its first 10 characters).
+pub fn stringify(cx: &mut dyn Context, args: Args) -> Result<Literal, Literal> {
+    let (replacer, allowed) = match args.get(1) {
+        replacer @ (Literal::Function { .. } | Literal::NativeFunction(_)) => (Some(replacer), None),
+        Literal::Array(keys) => {
+            let mut allowed: Vec<String> = Vec::new();
+            for key in keys.borrow().iter() {
+                if let Literal::String(_) | Literal::Number(_) = *key {
+                    let key = key.to_string();
+                    if !allowed.contains(&key) {
+                        allowed.push(key);
+                    }
+                }
+            }
+            (None, Some(allowed))
+        },
+        _ => (None, None)
+    };
+
+    let gap = match args.get(2) {
+        Literal::Number(n) => " ".repeat(n.clamp(0., 10.) as usize),
+        Literal::String(s) => s.chars().take(10).collect(),
+        _ => String::new()
+    };
+
+    let value = args.get(0);
+    let root = cx.new_object(vec![(String::new(), value.clone().into())]);
+    let mut stringifier = Stringifier { cx, replacer, allowed, gap, indent: String::new(), stack: Vec::new() };
+
+    Ok(match stringifier.property(&root, String::new(), value)? {
+        Some(json) => Literal::String(json),
+        None => Literal::Undefined
+    })
+}
+
+struct Stringifier<'a> {
+    cx: &'a mut dyn Context,
+    replacer: Option<Literal>,
+    allowed: Option<Vec<String>>,
+    gap: String,
+    indent: String,
+    /// The objects and arrays being serialized, innermost last, to catch cycles.
+    stack: Vec<*const ()>
+}
+
+impl Stringifier<'_> {
+    /// Serializes `value`, found at `holder[key]`, or `None` if it has no JSON form (such as
+    /// `undefined` or a function) and should be left out.
+    fn property(&mut self, holder: &Literal, key: String, value: Literal) -> Result<Option<String>, Literal> {
+        let mut value = self.read(holder, value)?;
+
+        if let Literal::Object(obj) = &value {
+            let to_json = obj.borrow().get("toJSON");
+            if let Some(to_json) = to_json.filter(|func| matches!(func, Literal::Function { .. } | Literal::NativeFunction(_))) {
+                value = self.cx.call(&to_json, value.clone(), vec![Literal::String(key.clone())])?;
+            }
+        }
+
+        if let Some(replacer) = &self.replacer {
+            value = self.cx.call(replacer, holder.clone(), vec![Literal::String(key), value])?;
+        }
+
+        Ok(match value {
+            Literal::Null => Some("null".into()),
+            Literal::Boolean(b) => Some(b.to_string()),
+            Literal::Number(n) if n.is_finite() => Some(Literal::Number(n).to_string()),
+            Literal::Number(_) => Some("null".into()),
+            Literal::String(s) => Some(quote(&s)),
+            Literal::BigInt(_) => return Err(self.cx.error("TypeError", "Do not know how to serialize a BigInt".into())),
+            Literal::Array(arr) => Some(self.array(&arr)?),
+            Literal::Object(obj) => Some(self.object(&obj)?),
+            _ => None
+        })
+    }
+
+    /// The value behind an accessor property, by calling its getter on `holder`.
+    fn read(&mut self, holder: &Literal, value: Literal) -> Result<Literal, Literal> {
+        match value {
+            Literal::Accessor { get: Some(getter), .. } => self.cx.call(&getter, holder.clone(), Vec::new()),
+            Literal::Accessor { get: None, .. } => Ok(Literal::Undefined),
+            value => Ok(value)
+        }
+    }
+
+    fn enter(&mut self, ptr: *const ()) -> Result<(), Literal> {
+        if self.stack.contains(&ptr) {
+            return Err(self.cx.error("TypeError", "Converting circular structure to JSON".into()));
+        }
+        self.stack.push(ptr);
+        self.indent.push_str(&self.gap);
+
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.stack.pop();
+        self.indent.truncate(self.indent.len() - self.gap.len());
+    }
+
+    /// Wraps the serialized members in brackets, putting each on its own line when indenting.
+    fn wrap(&self, open: char, members: Vec<String>, close: char) -> String {
+        if members.is_empty() {
+            return format!("{open}{close}");
+        }
+        if self.gap.is_empty() {
+            return format!("{open}{}{close}", members.join(","));
+        }
+
+        let outer = &self.indent[..self.indent.len() - self.gap.len()];
+        let separator = format!(",\n{}", self.indent);
+        format!("{open}\n{}{}\n{outer}{close}", self.indent, members.join(&separator))
+    }
+
+    fn array(&mut self, arr: &ArrayRef) -> Result<String, Literal> {
+        self.enter(Rc::as_ptr(arr) as *const ())?;
+
+        let holder = Literal::Array(Rc::clone(arr));
+        let mut members = Vec::new();
+        let mut i = 0;
+        // Read one at a time, since `toJSON` or the replacer can change the array.
+        while let Some(element) = arr.borrow().get(i).cloned() {
+            let member = self.property(&holder, i.to_string(), element)?;
+            members.push(member.unwrap_or_else(|| "null".into()));
+            i += 1;
+        }
+
+        let json = self.wrap('[', members, ']');
+        self.leave();
+        Ok(json)
+    }
+
+    fn object(&mut self, obj: &ObjectRef) -> Result<String, Literal> {
+        self.enter(Rc::as_ptr(obj) as *const ())?;
+
+        let holder = Literal::Object(Rc::clone(obj));
+        let keys = match &self.allowed {
+            Some(allowed) => allowed.clone(),
+            None => obj.borrow().properties.iter().map(|(key, _)| key.clone()).collect()
+        };
+
+        let colon = if self.gap.is_empty() { ":" } else { ": " };
+        let mut members = Vec::new();
+        for key in keys {
+            let Some(value) = obj.borrow().get_own(&key) else {
+                continue;
+            };
+            if let Some(json) = self.property(&holder, key.clone(), value)? {
+                members.push(format!("{}{colon}{json}", quote(&key)));
+            }
+        }
+
+        let json = self.wrap('{', members, '}');
+        self.leave();
+        Ok(json)
+    }
+}
+
+/// A JSON string literal, escaping quotes, backslashes and control characters.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{8}' => quoted.push_str("\\b"),
+            '\u{c}' => quoted.push_str("\\f"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
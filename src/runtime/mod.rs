@@ -1,8 +1,18 @@
 pub mod interpreter;
 pub mod scope;
+pub mod bytecode;
+pub mod emitter;
+pub mod vm;
+pub mod embed;
+pub mod native;
+mod convert;
 mod builtins;
-mod emitter;
-mod bytecode;
+mod json;
+mod ops;
 
 pub use interpreter::*;
-pub use scope::*;
\ No newline at end of file
+pub use scope::*;
+pub use emitter::*;
+pub use vm::*;
+pub use embed::*;
+pub use native::*;
@@ -0,0 +1,113 @@
+//! The interface between native functions and the engine running them, for builtins and for
+//! functions and objects registered by a host.
+
+use std::rc::Rc;
+use crate::parser::{Literal, Object, Properties};
+use crate::runtime::embed::Error;
+
+/// The engine a native function was called from.
+pub trait Context {
+    /// The value of the global `name`, if it is bound. Locals of the calling script aren't visible.
+    fn global(&self, name: &str) -> Option<Literal>;
+
+    /// Binds the global `name`, replacing any existing value.
+    fn set_global(&mut self, name: &str, value: Literal);
+
+    /// A new plain object inheriting from `Object.prototype`.
+    fn new_object(&self, properties: Properties) -> Literal;
+
+    /// Calls a JS function, closure or native with the given `this`. `Err` is whatever it threw,
+    /// which a native will usually pass straight on with `?`.
+    fn call(&mut self, func: &Literal, this: Literal, args: Vec<Literal>) -> Result<Literal, Literal>;
+
+    /// Converts `value` to a string as `String(value)` does, calling an object's `toString` or
+    /// `valueOf`. `Err` is whatever they threw.
+    fn to_js_string(&mut self, value: Literal) -> Result<String, Literal>;
+
+    /// Converts `value` to a number as unary `+` does, calling an object's `valueOf` or
+    /// `toString`. `Err` is whatever they threw.
+    fn to_js_number(&mut self, value: Literal) -> Result<f64, Literal>;
+
+    /// A builtin error such as `TypeError`, inheriting from its constructor's `prototype`, for a
+    /// native to throw.
+    fn error(&self, name: &str, message: String) -> Literal;
+
+    /// A new object whose properties are backed by `host`.
+    fn new_host_object(&self, host: Rc<dyn HostObject>) -> Literal {
+        let object = self.new_object(Vec::new());
+        if let Literal::Object(obj) = &object {
+            obj.borrow_mut().host = Some(host);
+        }
+
+        object
+    }
+}
+
+/// A builtin error object, such as `error("TypeError", "...")`, for a native to throw. Without
+/// an engine to hand it has no prototype yet, and gets its constructor's when it is thrown;
+/// `Context::error` builds one with its prototype straight away.
+pub fn error(name: &str, message: impl Into<String>) -> Literal {
+    Literal::Object(Object::new(vec![
+        ("name".into(), Literal::String(name.into()).into()),
+        ("message".into(), Literal::String(message.into()).into()),
+    ], None))
+}
+
+/// The receiver and arguments of a native call.
+#[derive(Debug)]
+pub struct Args {
+    pub this: Literal,
+    values: Vec<Literal>
+}
+
+impl Args {
+    pub fn new(this: Literal, values: Vec<Literal>) -> Self {
+        Self { this, values }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The argument at `index`, or `undefined` if too few were passed.
+    pub fn get(&self, index: usize) -> Literal {
+        self.values.get(index).map_or(Literal::Undefined, |value| value.clone())
+    }
+
+    /// The argument at `index` as a `T`, throwing a TypeError if it doesn't convert.
+    pub fn arg<T>(&self, index: usize) -> Result<T, Literal> where T: TryFrom<Literal>, Error: From<T::Error> {
+        T::try_from(self.get(index))
+            .map_err(|err| error("TypeError", format!("argument {}: {}", index + 1, Error::from(err))))
+    }
+
+    /// Like `arg`, but `None` if the argument is missing or `undefined`.
+    pub fn optional<T>(&self, index: usize) -> Result<Option<T>, Literal> where T: TryFrom<Literal>, Error: From<T::Error> {
+        match self.get(index) {
+            Literal::Undefined => Ok(None),
+            _ => self.arg(index).map(Some)
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Literal> {
+        self.values.iter()
+    }
+
+    pub fn into_values(self) -> Vec<Literal> {
+        self.values
+    }
+}
+
+/// Host-backed storage for an object's properties, consulted before the ones stored on the
+/// object itself. Lets a host expose live Rust state as a JS object.
+pub trait HostObject {
+    /// The value of `name`, or `None` to fall back to the object's ordinary properties.
+    fn get(&self, name: &str) -> Option<Literal>;
+
+    /// Handles an assignment to `name`. `Ok(false)` stores it as an ordinary property instead, and
+    /// `Err` throws the value.
+    fn set(&self, name: &str, value: Literal) -> Result<bool, Literal>;
+}
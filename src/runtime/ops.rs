@@ -0,0 +1,574 @@
+//! Value semantics shared by the tree-walking interpreter and the bytecode VM, so both engines
+//! agree on every result and every error message.
+
+use std::cmp::Ordering;
+use std::mem::discriminant;
+use std::rc::Rc;
+use num_bigint::{BigInt, Sign};
+use crate::lexer::{is_line_terminator, is_whitespace, Span};
+use crate::parser::{BinaryOperator, Literal, MemberKind, NativeFn, Object, ObjectRef, UnaryOperator};
+use crate::runtime::builtins::Builtins;
+use crate::runtime::{Args, Context, ControlFlow, EvalResult};
+
+/// Scope binding for the object a class method was defined on, which `super.name` starts from.
+/// Like the other hidden bindings, it isn't a valid identifier, so scripts can't shadow it.
+pub const HOME_OBJECT: &str = "%home";
+/// Scope binding for the class a derived class extends, which `super(...)` calls.
+pub const PARENT_CLASS: &str = "%parent";
+
+pub fn throw<T>(builtins: &Builtins, name: &str, message: impl Into<String>, span: Span) -> EvalResult<T> {
+    Err(ControlFlow::Throw(builtins.error(name, message).into(), span))
+}
+
+/// The value an abrupt completion threw, for handing back to a native.
+pub fn thrown(flow: ControlFlow) -> Literal {
+    match flow {
+        ControlFlow::Throw(value, _) => *value,
+        _ => Literal::Undefined
+    }
+}
+
+/// Runs a native function, attributing anything it throws to `span`.
+pub fn call_native(builtins: &Builtins, func: &NativeFn, cx: &mut dyn Context, this: Literal, args: Vec<Literal>, span: Span) -> EvalResult<Literal> {
+    (func.func)(cx, Args::new(this, args)).map_err(|thrown| ControlFlow::Throw(builtins.adopt_error(thrown).into(), span))
+}
+
+pub fn expect_number(builtins: &Builtins, value: Literal, span: Span) -> EvalResult<f64> {
+    match value {
+        Literal::Number(n) => Ok(n),
+        _ => throw(builtins, "TypeError", format!("Expected number, got {}", value.type_name()), span)
+    }
+}
+
+pub fn expect_index(builtins: &Builtins, value: Literal, span: Span) -> EvalResult<usize> {
+    let index = expect_number(builtins, value, span)?;
+    if index < 0. || index.fract() != 0. {
+        return throw(builtins, "RangeError", format!("Invalid array index: {index}"), span);
+    }
+
+    Ok(index as usize)
+}
+
+pub fn binary_op(builtins: &Builtins, cx: &mut dyn Context, op: &BinaryOperator, left: Literal, right: Literal, span: Span) -> EvalResult<Literal> {
+    match op {
+        BinaryOperator::StrictEqual => Ok(Literal::Boolean(left == right)),
+        BinaryOperator::StrictNotEqual => Ok(Literal::Boolean(left != right)),
+        BinaryOperator::Equal => Ok(Literal::Boolean(loose_equals(builtins, cx, left, right, span)?)),
+        BinaryOperator::NotEqual => Ok(Literal::Boolean(!loose_equals(builtins, cx, left, right, span)?)),
+        // `+` concatenates if either side is a string once both are primitives, and adds otherwise.
+        BinaryOperator::Add | BinaryOperator::PlusEqual => {
+            let left = to_primitive(builtins, cx, left, Hint::Default, span)?;
+            let right = to_primitive(builtins, cx, right, Hint::Default, span)?;
+            if let (Literal::String(_), _) | (_, Literal::String(_)) = (&left, &right) {
+                return Ok(Literal::String(format!("{left}{right}")));
+            }
+
+            arithmetic(builtins, op, numeric(left), numeric(right), span)
+        },
+        BinaryOperator::LessThan | BinaryOperator::LessThanOrEqual
+        | BinaryOperator::GreaterThan | BinaryOperator::GreaterThanOrEqual => {
+            let left = to_primitive(builtins, cx, left, Hint::Number, span)?;
+            let right = to_primitive(builtins, cx, right, Hint::Number, span)?;
+            let ordering = compare(left, right);
+
+            Ok(Literal::Boolean(ordering.is_some_and(|ordering| match op {
+                BinaryOperator::LessThan => ordering.is_lt(),
+                BinaryOperator::LessThanOrEqual => ordering.is_le(),
+                BinaryOperator::GreaterThan => ordering.is_gt(),
+                _ => ordering.is_ge()
+            })))
+        },
+        _ => {
+            let left = to_numeric(builtins, cx, left, span)?;
+            let right = to_numeric(builtins, cx, right, span)?;
+            arithmetic(builtins, op, left, right, span)
+        }
+    }
+}
+
+const MIXED_BIGINT: &str = "Cannot mix BigInt and other types, use explicit conversions";
+
+/// Arithmetic on two numeric values, which have to be both numbers or both BigInts.
+fn arithmetic(builtins: &Builtins, op: &BinaryOperator, left: Literal, right: Literal, span: Span) -> EvalResult<Literal> {
+    match (left, right) {
+        (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(match op {
+            BinaryOperator::Add | BinaryOperator::PlusEqual => l + r,
+            BinaryOperator::Sub | BinaryOperator::MinusEqual => l - r,
+            BinaryOperator::Mul | BinaryOperator::MulEqual => l * r,
+            BinaryOperator::Div | BinaryOperator::DivEqual => l / r,
+            BinaryOperator::Mod | BinaryOperator::ModEqual => l % r,
+            _ => unreachable!("{:?} is not an arithmetic operator", op)
+        })),
+        (Literal::BigInt(l), Literal::BigInt(r)) => Ok(Literal::BigInt(match op {
+            BinaryOperator::Add | BinaryOperator::PlusEqual => l + r,
+            BinaryOperator::Sub | BinaryOperator::MinusEqual => l - r,
+            BinaryOperator::Mul | BinaryOperator::MulEqual => l * r,
+            BinaryOperator::Div | BinaryOperator::DivEqual | BinaryOperator::Mod | BinaryOperator::ModEqual if r.sign() == Sign::NoSign => {
+                return throw(builtins, "RangeError", "Division by zero", span);
+            },
+            // Both truncate towards zero, as in JS.
+            BinaryOperator::Div | BinaryOperator::DivEqual => l / r,
+            BinaryOperator::Mod | BinaryOperator::ModEqual => l % r,
+            _ => unreachable!("{:?} is not an arithmetic operator", op)
+        })),
+        _ => throw(builtins, "TypeError", MIXED_BIGINT, span)
+    }
+}
+
+/// Orders two primitives for `<` and friends: strings by UTF-16 code units, anything else as
+/// numbers, with BigInts compared exactly. `None` if either side is NaN.
+fn compare(left: Literal, right: Literal) -> Option<Ordering> {
+    match (left, right) {
+        (Literal::String(l), Literal::String(r)) => Some(l.encode_utf16().cmp(r.encode_utf16())),
+        (Literal::BigInt(l), Literal::String(r)) => string_to_bigint(&r).map(|r| l.cmp(&r)),
+        (Literal::String(l), Literal::BigInt(r)) => string_to_bigint(&l).map(|l| l.cmp(&r)),
+        (left, right) => match (numeric(left), numeric(right)) {
+            (Literal::BigInt(l), Literal::BigInt(r)) => Some(l.cmp(&r)),
+            (Literal::BigInt(l), Literal::Number(r)) => compare_bigint(&l, r),
+            (Literal::Number(l), Literal::BigInt(r)) => compare_bigint(&r, l).map(Ordering::reverse),
+            (l, r) => to_number(&l).partial_cmp(&to_number(&r))
+        }
+    }
+}
+
+/// Orders a BigInt against a number by their exact values.
+fn compare_bigint(n: &BigInt, x: f64) -> Option<Ordering> {
+    if x.is_nan() {
+        return None;
+    }
+    if x.is_infinite() {
+        return Some(if x > 0. { Ordering::Less } else { Ordering::Greater });
+    }
+
+    // Formatting an integral float gives all of its digits, with no exponent.
+    let floor = BigInt::parse_bytes(format!("{:.0}", x.floor()).as_bytes(), 10).unwrap_or_default();
+    Some(match n.cmp(&floor) {
+        Ordering::Equal if x.fract() != 0. => Ordering::Less,
+        ordering => ordering
+    })
+}
+
+/// `==`: values of the same type compare strictly, `null` and `undefined` only equal each
+/// other, and anything else is converted towards a number until the two sides can be compared.
+pub fn loose_equals(builtins: &Builtins, cx: &mut dyn Context, left: Literal, right: Literal, span: Span) -> EvalResult<bool> {
+    if discriminant(&left) == discriminant(&right) || (is_object(&left) && is_object(&right)) {
+        return Ok(left == right);
+    }
+
+    Ok(match (left, right) {
+        (Literal::Null | Literal::Undefined, other) | (other, Literal::Null | Literal::Undefined) => {
+            matches!(other, Literal::Null | Literal::Undefined)
+        },
+        (Literal::Number(n), Literal::String(s)) | (Literal::String(s), Literal::Number(n)) => n == string_to_number(&s),
+        (Literal::BigInt(n), Literal::String(s)) | (Literal::String(s), Literal::BigInt(n)) => string_to_bigint(&s) == Some(n),
+        (Literal::BigInt(n), Literal::Number(x)) | (Literal::Number(x), Literal::BigInt(n)) => compare_bigint(&n, x) == Some(Ordering::Equal),
+        (Literal::Boolean(b), other) | (other, Literal::Boolean(b)) => {
+            return loose_equals(builtins, cx, Literal::Number(if b { 1. } else { 0. }), other, span);
+        },
+        (object, other) | (other, object) if is_object(&object) => {
+            let primitive = to_primitive(builtins, cx, object, Hint::Default, span)?;
+            return loose_equals(builtins, cx, primitive, other, span);
+        },
+        _ => false
+    })
+}
+
+pub fn unary_op(builtins: &Builtins, cx: &mut dyn Context, op: &UnaryOperator, value: Literal, span: Span) -> EvalResult<Literal> {
+    match op {
+        UnaryOperator::Negate => match to_numeric(builtins, cx, value, span)? {
+            Literal::BigInt(n) => Ok(Literal::BigInt(-n)),
+            n => Ok(Literal::Number(-to_number(&n)))
+        },
+        UnaryOperator::Plus => Ok(Literal::Number(coerce_number(builtins, cx, value, span)?)),
+        UnaryOperator::Not => Ok(Literal::Boolean(!value.truthy()))
+    }
+}
+
+/// The conversion `to_primitive` tries first: `toString` for a string hint, `valueOf` otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hint {
+    Default,
+    Number,
+    String,
+}
+
+fn is_object(value: &Literal) -> bool {
+    matches!(value, Literal::Object(_) | Literal::Array(_) | Literal::Function { .. } | Literal::NativeFunction(_))
+}
+
+/// Converts an object to a primitive by calling its `valueOf` and `toString` methods in the
+/// order `hint` asks for, and taking the first primitive either returns. Primitives are
+/// returned unchanged.
+pub fn to_primitive(builtins: &Builtins, cx: &mut dyn Context, value: Literal, hint: Hint, span: Span) -> EvalResult<Literal> {
+    if !is_object(&value) {
+        return Ok(value);
+    }
+
+    let order = if hint == Hint::String { ["toString", "valueOf"] } else { ["valueOf", "toString"] };
+    for name in order {
+        let call = |cx: &mut dyn Context, func: &Literal| cx.call(func, value.clone(), Vec::new())
+            .map_err(|thrown| ControlFlow::Throw(thrown.into(), span));
+
+        let method = match get_property(builtins, value.clone(), name.into(), span)? {
+            Literal::Accessor { get: Some(getter), .. } => call(cx, &getter)?,
+            method => method
+        };
+        if let Literal::Function { .. } | Literal::NativeFunction(_) = method {
+            let result = call(cx, &method)?;
+            if !is_object(&result) {
+                return Ok(result);
+            }
+        }
+    }
+
+    throw(builtins, "TypeError", "Cannot convert object to primitive value", span)
+}
+
+/// A primitive as a number, or as itself if it's a BigInt.
+fn numeric(primitive: Literal) -> Literal {
+    match primitive {
+        Literal::BigInt(n) => Literal::BigInt(n),
+        other => Literal::Number(to_number(&other))
+    }
+}
+
+/// Converts a value to a number or a BigInt, as arithmetic operators do.
+pub fn to_numeric(builtins: &Builtins, cx: &mut dyn Context, value: Literal, span: Span) -> EvalResult<Literal> {
+    Ok(numeric(to_primitive(builtins, cx, value, Hint::Number, span)?))
+}
+
+/// Converts a value to a number for unary `+`, `++` and `--`, which can't take BigInts.
+pub fn coerce_number(builtins: &Builtins, cx: &mut dyn Context, value: Literal, span: Span) -> EvalResult<f64> {
+    match to_numeric(builtins, cx, value, span)? {
+        Literal::BigInt(_) => throw(builtins, "TypeError", "Cannot convert a BigInt value to a number", span),
+        n => Ok(to_number(&n))
+    }
+}
+
+/// Converts a value to a string the way template literals and `String(value)` do.
+pub fn to_string(builtins: &Builtins, cx: &mut dyn Context, value: Literal, span: Span) -> EvalResult<String> {
+    Ok(to_primitive(builtins, cx, value, Hint::String, span)?.to_string())
+}
+
+pub fn get_index(builtins: &Builtins, target: Literal, index: Literal, span: Span) -> EvalResult<Literal> {
+    let index = expect_index(builtins, index, span)?;
+
+    let arr = match target {
+        Literal::Array(arr) => arr,
+        other => return throw(builtins, "TypeError", format!("Cannot index into {}", other.type_name()), span)
+    };
+
+    let arr = arr.borrow();
+    match arr.get(index) {
+        Some(val) => Ok(val.clone()),
+        None => throw(builtins, "RangeError", format!("Index out of bounds: {index}"), span)
+    }
+}
+
+/// Writes `arr[index]`, growing the array with `undefined` holes if needed.
+pub fn set_index(builtins: &Builtins, target: Literal, index: Literal, value: Literal, span: Span) -> EvalResult<()> {
+    let arr = match target {
+        Literal::Array(arr) => arr,
+        other => return throw(builtins, "TypeError", format!("Cannot assign by index to {}", other.type_name()), span)
+    };
+    let index = expect_index(builtins, index, span)?;
+
+    let mut arr = arr.borrow_mut();
+    if arr.frozen {
+        return throw(builtins, "TypeError", format!("Cannot assign to '{index}' of a frozen array"), span);
+    }
+    if index >= arr.len() {
+        arr.resize(index + 1, Literal::Undefined);
+    }
+    arr[index] = value;
+    Ok(())
+}
+
+/// Reads `target.name` from the target or its prototype chain. Primitives and arrays use the
+/// builtin prototype for their type.
+pub fn get_property(builtins: &Builtins, target: Literal, name: String, span: Span) -> EvalResult<Literal> {
+    let property = match &target {
+        Literal::Object(obj) if name == "__proto__" => Some(obj.borrow().proto.clone().map_or(Literal::Null, Literal::Object)),
+        Literal::Object(obj) => obj.borrow().get(&name),
+        Literal::Function { object, .. } => object.borrow().get(&name),
+        Literal::NativeFunction(f) => f.object.borrow().get(&name),
+        Literal::Array(arr) if name == "length" => Some(Literal::Number(arr.borrow().len() as f64)),
+        Literal::Array(arr) => arr.borrow().get_own(&name)
+            .or_else(|| builtins.prototype_of(&target).and_then(|proto| proto.borrow().get(&name))),
+        // Counted in UTF-16 code units, like every other string position.
+        Literal::String(str) if name == "length" => Some(Literal::Number(str.encode_utf16().count() as f64)),
+        Literal::Null | Literal::Undefined => {
+            return throw(builtins, "TypeError", format!("Cannot read properties of {} (reading '{name}')", target.type_name()), span);
+        },
+        other => builtins.prototype_of(other).and_then(|proto| proto.borrow().get(&name))
+    };
+
+    Ok(property.unwrap_or(Literal::Undefined))
+}
+
+/// Writes an own property. Assigning an object or `null` to `__proto__` changes the prototype.
+/// If the property is an accessor, nothing is written and its setter is returned for the caller
+/// to run instead.
+pub fn set_property(builtins: &Builtins, target: Literal, name: String, value: Literal, span: Span) -> EvalResult<Option<Literal>> {
+    let obj = match target {
+        Literal::Array(arr) if arr.borrow().frozen => {
+            return throw(builtins, "TypeError", format!("Cannot assign to '{name}' of a frozen array"), span);
+        },
+        // Shrinks the array, or pads it with `undefined`.
+        Literal::Array(arr) if name == "length" => {
+            let len = to_number(&value);
+            if len < 0. || len.fract() != 0. || len > u32::MAX as f64 {
+                return throw(builtins, "RangeError", "Invalid array length", span);
+            }
+
+            arr.borrow_mut().resize(len as usize, Literal::Undefined);
+            return Ok(None);
+        },
+        Literal::Array(arr) => {
+            arr.borrow_mut().set(name, value);
+            return Ok(None);
+        },
+        Literal::Object(obj) => obj,
+        Literal::Function { object, .. } => object,
+        Literal::NativeFunction(f) => f.object,
+        other @ (Literal::Null | Literal::Undefined) => {
+            return throw(builtins, "TypeError", format!("Cannot set properties of {} (setting '{name}')", other.type_name()), span);
+        },
+        other => return throw(builtins, "TypeError", format!("Cannot set property '{name}' of {}", other.type_name()), span)
+    };
+
+    if name != "__proto__" {
+        let host = obj.borrow().host.clone();
+        if let Some(host) = host {
+            match host.set(&name, value.clone()) {
+                Ok(true) => return Ok(None),
+                Ok(false) => {},
+                Err(thrown) => return Err(ControlFlow::Throw(builtins.adopt_error(thrown).into(), span))
+            }
+        }
+
+        // Accessors are found along the prototype chain, like any other property.
+        let accessor = obj.borrow().get(&name);
+        if let Some(Literal::Accessor { set, .. }) = accessor {
+            return Ok(set.map(|setter| *setter));
+        }
+
+        obj.borrow_mut().set(name, value);
+        return Ok(None);
+    }
+
+    let proto = match value {
+        Literal::Object(proto) => proto,
+        Literal::Null => {
+            obj.borrow_mut().proto = None;
+            return Ok(None);
+        },
+        _ => return Ok(None)
+    };
+
+    let mut ancestor = Some(Rc::clone(&proto));
+    while let Some(current) = ancestor {
+        if Rc::ptr_eq(&current, &obj) {
+            return throw(builtins, "TypeError", "Cyclic __proto__ value", span);
+        }
+        ancestor = current.borrow().proto.clone();
+    }
+
+    obj.borrow_mut().proto = Some(proto);
+    Ok(None)
+}
+
+/// The object `new func(...)` starts from. It inherits from `func.prototype`, or from
+/// `Object.prototype` if that isn't an object.
+pub fn new_instance(builtins: &Builtins, func: &Literal, name: &str, span: Span) -> EvalResult<Literal> {
+    let Some(object) = function_object(func) else {
+        return throw(builtins, "TypeError", format!("{name} is not a constructor"), span);
+    };
+
+    let prototype = object.borrow().get("prototype");
+    let proto = match prototype {
+        Some(Literal::Object(proto)) => proto,
+        _ => Rc::clone(builtins.object_prototype())
+    };
+
+    Ok(Literal::Object(Object::new(Vec::new(), Some(proto))))
+}
+
+/// What `new` evaluates to: whatever the constructor returned if that is an object, else the instance.
+pub fn constructed(instance: Literal, result: Literal) -> Literal {
+    match result {
+        Literal::Object(_) | Literal::Array(_) | Literal::Function { .. } | Literal::NativeFunction(_) => result,
+        _ => instance
+    }
+}
+
+fn function_object(func: &Literal) -> Option<&ObjectRef> {
+    match func {
+        Literal::Function { object, .. } => Some(object),
+        Literal::NativeFunction(f) => Some(&f.object),
+        _ => None
+    }
+}
+
+/// Wires up a freshly created class constructor: gives it a `prototype` inheriting from the
+/// parent's, and makes it inherit the parent's static members. Returns the new prototype.
+pub fn make_class(builtins: &Builtins, constructor: &Literal, parent: Option<Literal>, span: Span) -> EvalResult<Literal> {
+    let object = function_object(constructor).expect("class constructors are functions");
+
+    let (proto, statics) = match &parent {
+        None => (Some(Rc::clone(builtins.object_prototype())), None),
+        Some(Literal::Null) => (None, None),
+        Some(parent) => {
+            let Some(parent_object) = function_object(parent) else {
+                return throw(builtins, "TypeError", format!("Class extends value {parent} is not a constructor or null"), span);
+            };
+
+            let prototype = parent_object.borrow().get("prototype");
+            match prototype {
+                Some(Literal::Object(proto)) => (Some(proto), Some(Rc::clone(parent_object))),
+                Some(Literal::Null) => (None, Some(Rc::clone(parent_object))),
+                _ => return throw(builtins, "TypeError", "Class extends value does not have a valid prototype property", span)
+            }
+        }
+    };
+
+    let prototype = Literal::Object(Object::new(Vec::new(), proto));
+    let mut object = object.borrow_mut();
+    object.set("prototype", prototype.clone());
+    object.proto = statics;
+    object.class = true;
+
+    Ok(prototype)
+}
+
+/// Throws unless `func` may be called without `new`, which class constructors may not.
+pub fn check_callable(builtins: &Builtins, func: &Literal, name: &str, span: Span) -> EvalResult<()> {
+    if function_object(func).is_some_and(|object| object.borrow().class) {
+        return throw(builtins, "TypeError", format!("Class constructor {name} cannot be invoked without 'new'"), span);
+    }
+
+    Ok(())
+}
+
+/// Defines a class method or accessor on `target`, a class or its prototype. A getter and
+/// setter with the same name share one accessor property.
+pub fn define_method(target: &Literal, name: String, func: Literal, kind: MemberKind) {
+    let object = match target {
+        Literal::Object(object) => object,
+        other => function_object(other).expect("methods are defined on objects or classes")
+    };
+    let mut object = object.borrow_mut();
+
+    let value = match kind {
+        MemberKind::Method | MemberKind::Field => func,
+        MemberKind::Getter | MemberKind::Setter => {
+            let (mut get, mut set) = match object.get_own(&name) {
+                Some(Literal::Accessor { get, set }) => (get, set),
+                _ => (None, None)
+            };
+
+            if kind == MemberKind::Getter {
+                get = Some(func.into());
+            } else {
+                set = Some(func.into());
+            }
+            Literal::Accessor { get, set }
+        }
+    };
+
+    object.set(name, value);
+}
+
+/// The object `super.name` is looked up on: the prototype of the running method's home object.
+pub fn super_base(builtins: &Builtins, home: Option<Literal>, span: Span) -> EvalResult<Literal> {
+    let object = match &home {
+        Some(Literal::Object(object)) => Some(object),
+        Some(home) => function_object(home),
+        None => None
+    };
+    let Some(object) = object else {
+        return throw(builtins, "SyntaxError", "'super' keyword unexpected here", span);
+    };
+
+    let proto = object.borrow().proto.clone();
+    Ok(proto.map_or(Literal::Null, Literal::Object))
+}
+
+/// Finishes a `super(...)` call. Native constructors build and return a new object instead of
+/// initialising `this`, so whatever they built is copied onto `this`.
+pub fn adopt_super_result(parent: &Literal, this: &Literal, result: Literal) {
+    if let (Literal::NativeFunction(_), Literal::Object(this), Literal::Object(result)) = (parent, this, result) {
+        let result = result.borrow();
+        let mut this = this.borrow_mut();
+        for (name, value) in &result.properties {
+            this.set(name.clone(), *value.clone());
+        }
+    }
+}
+
+/// What `trim` strips and number conversion skips: the lexer's whitespace and line terminators.
+pub fn is_js_whitespace(c: char) -> bool {
+    is_whitespace(c) || is_line_terminator(c)
+}
+
+/// Converts a value to a number the way `Number(value)` does.
+pub fn to_number(value: &Literal) -> f64 {
+    match value {
+        Literal::Number(n) => *n,
+        Literal::BigInt(n) => n.to_string().parse().unwrap_or(f64::NAN),
+        Literal::Boolean(b) => if *b { 1. } else { 0. },
+        Literal::Null => 0.,
+        Literal::String(s) => string_to_number(s),
+        _ => f64::NAN
+    }
+}
+
+/// Parses a string the way `Number(string)` does: blank is 0, and anything but a decimal or
+/// `0x`, `0o` or `0b` integer literal, or `Infinity`, is NaN.
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(is_js_whitespace);
+    if let Some((radix, digits)) = radix_prefix(s) {
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return f64::NAN;
+        }
+        return digits.chars().fold(0., |value, digit| value * radix as f64 + digit.to_digit(radix).unwrap() as f64);
+    }
+
+    match s {
+        "" => 0.,
+        "Infinity" | "+Infinity" => f64::INFINITY,
+        "-Infinity" => f64::NEG_INFINITY,
+        // Rust also accepts `inf` and `NaN`, which JS doesn't.
+        s if s.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) => s.parse().unwrap_or(f64::NAN),
+        _ => f64::NAN
+    }
+}
+
+/// Parses a string as a BigInt, as `==` and `<` do when comparing one with a BigInt. Like
+/// `string_to_number`, but without fractions, exponents or `Infinity`.
+fn string_to_bigint(s: &str) -> Option<BigInt> {
+    let s = s.trim_matches(is_js_whitespace);
+    let (radix, digits) = match radix_prefix(s) {
+        Some((radix, digits)) => (radix, digits),
+        None if s.is_empty() => return Some(BigInt::default()),
+        None => (10, s.strip_prefix('+').unwrap_or(s))
+    };
+
+    let unsigned = if radix == 10 { digits.strip_prefix('-').unwrap_or(digits) } else { digits };
+    if unsigned.is_empty() || !unsigned.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+}
+
+/// Splits a `0x`, `0o` or `0b` prefix off an integer literal.
+fn radix_prefix(s: &str) -> Option<(u32, &str)> {
+    let radix = match s.get(..2)? {
+        "0x" | "0X" => 16,
+        "0o" | "0O" => 8,
+        "0b" | "0B" => 2,
+        _ => return None
+    };
+
+    Some((radix, &s[2..]))
+}
@@ -1,43 +1,151 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
 use crate::parser::Literal;
 
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<String, Literal>,
+    parent: Option<Environment>
+}
+
+/// A shared, mutable chain of variable frames. Function values hold on to the environment they
+/// were defined in, so every closure over a frame sees the same bindings.
+#[derive(Clone, Default)]
+pub struct Environment(Rc<RefCell<Frame>>);
+
+impl Environment {
+    /// A fresh, empty frame whose lookups fall back to `self`.
+    pub fn child(&self) -> Self {
+        Environment(Rc::new(RefCell::new(Frame {
+            vars: HashMap::new(),
+            parent: Some(self.clone())
+        })))
+    }
+
+    fn parent(&self) -> Option<Self> {
+        self.0.borrow().parent.clone()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Literal> {
+        let frame = self.0.borrow();
+        match frame.vars.get(name) {
+            Some(val) => Some(val.clone()),
+            None => frame.parent.as_ref()?.get(name)
+        }
+    }
+
+    pub fn declare(&self, name: impl Into<String>, value: Literal) {
+        self.0.borrow_mut().vars.insert(name.into(), value);
+    }
+
+    /// Assigns to the nearest frame that binds `name`, handing `value` back if none does.
+    pub fn assign(&self, name: &str, value: Literal) -> Result<(), Literal> {
+        let mut frame = self.0.borrow_mut();
+        if let Some(slot) = frame.vars.get_mut(name) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &frame.parent {
+            Some(parent) => parent.assign(name, value),
+            None => Err(value)
+        }
+    }
+}
+
+// Functions stored in an environment point back at it, so neither of these may recurse.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Environment")
+    }
+}
+
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct Scope {
-    scopes: Vec<HashMap<String, Literal>>
+    current: Environment,
+    global: Environment
 }
 
 impl Scope {
     pub fn new() -> Self {
+        let global = Environment::default();
         Scope {
-            scopes: vec![HashMap::new()]  // Global scope
+            current: global.clone(),
+            global
         }
     }
 
     pub fn enter(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.current = self.current.child();
     }
 
     pub fn exit(&mut self) {
-        self.scopes.pop();
+        if let Some(parent) = self.current.parent() {
+            self.current = parent;
+        }
+    }
+
+    /// The innermost environment, for closures to capture.
+    pub fn capture(&self) -> Environment {
+        self.current.clone()
+    }
+
+    /// Makes `env` the innermost environment, returning the one it replaces.
+    pub fn replace(&mut self, env: Environment) -> Environment {
+        std::mem::replace(&mut self.current, env)
+    }
+
+    /// Swaps the innermost frame for a copy of itself, so closures created before the swap keep
+    /// the old bindings. Gives `for (let ...)` loops a fresh binding per iteration.
+    pub fn renew(&mut self) {
+        let frame = self.current.0.borrow();
+        let copy = Frame {
+            vars: frame.vars.clone(),
+            parent: frame.parent.clone()
+        };
+        drop(frame);
+
+        self.current = Environment(Rc::new(RefCell::new(copy)));
     }
 
     pub fn get(&self, name: impl AsRef<str>) -> Option<Literal> {
-        let key = name.as_ref();
-        self.scopes.iter().rev()
-            .find_map(|scope| scope.get(key).cloned())
+        self.current.get(name.as_ref())
+    }
+
+    /// Binds `name` in the innermost scope, shadowing any outer binding.
+    pub fn declare(&mut self, name: impl AsRef<str>, value: Literal) {
+        self.current.declare(name.as_ref(), value);
+    }
+
+    /// Looks `name` up in the global frame only, wherever execution currently is.
+    pub fn get_global(&self, name: impl AsRef<str>) -> Option<Literal> {
+        self.global.get(name.as_ref())
+    }
+
+    pub fn set_global(&mut self, name: impl AsRef<str>, value: Literal) {
+        self.global.declare(name.as_ref(), value);
     }
 
     pub fn set(&mut self, name: impl AsRef<str>, value: Literal) {
         let key = name.as_ref();
 
         // Check if exists in a parent scope before creating a new one.
-        for scope in self.scopes.iter_mut().rev() {
-            if scope.contains_key(key) {
-                scope.insert(key.to_string(), value);
-                return;
-            }
+        if let Err(value) = self.current.assign(key, value) {
+            self.current.declare(key, value);
         }
+    }
+}
 
-        self.scopes.last_mut().unwrap().insert(key.to_string(), value);
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::lexer::Span;
+use crate::parser::{Array, Literal, Properties, Statement};
+use crate::runtime::builtins::Builtins;
+use crate::runtime::bytecode::{Bytecode, Chunk, Index, Label, Program, Register};
+use crate::runtime::ops;
+use crate::runtime::scope::{Environment, Scope};
+use crate::runtime::{Context, ControlFlow, EvalResult, UncaughtException};
+
+/// An active `try`: where to resume, where the exception goes, and the scope to restore.
+struct Handler {
+    target: Label,
+    exception: Register,
+    env: Environment,
+}
+
+/// Registers and exception handlers of one running chunk.
+struct Frame {
+    registers: Vec<Literal>,
+    handlers: Vec<Handler>,
+    pc: Label,
+    /// Where the most recently caught exception was thrown, for `Rethrow`.
+    caught: Span,
+}
+
+/// Executes a compiled `Program`. Produces the same results as `Interpreter`, which it shares
+/// its scope chain, builtins and operator semantics with.
+pub struct Vm {
+    pub scope: Scope,
+    /// Shared so operators can look up conversion methods while calling back into `self`.
+    builtins: Rc<Builtins>,
+    program: Program,
+    /// Maps a function's body back to its compiled chunk when it's called.
+    functions: HashMap<*const Statement, Index>,
+}
+
+impl Vm {
+    pub fn new(program: Program) -> Self {
+        let functions = program.functions.iter().enumerate()
+            .map(|(index, func)| (Rc::as_ptr(&func.body), index))
+            .collect();
+
+        let mut scope = Scope::new();
+        let builtins = Rc::new(Builtins::new());
+        builtins.load(&mut scope);
+
+        Self {
+            scope,
+            builtins,
+            program,
+            functions
+        }
+    }
+
+    /// Calls `func` with `receiver` bound to `this`. Class constructors throw here, as only
+    /// `new` and `super(...)` may run them, through `construct`.
+    fn call(&mut self, func: Literal, receiver: Literal, args: Vec<Literal>, name: &str, span: Span) -> EvalResult<Literal> {
+        ops::check_callable(&self.builtins, &func, name, span)?;
+        self.construct(func, receiver, args, name, span)
+    }
+
+    /// Runs `func` with `receiver` bound to `this`, whatever kind of function it is.
+    fn construct(&mut self, func: Literal, receiver: Literal, args: Vec<Literal>, name: &str, span: Span) -> EvalResult<Literal> {
+        match func {
+            Literal::Function { body, env, .. } => {
+                let Some(&index) = self.functions.get(&Rc::as_ptr(&body)) else {
+                    return ops::throw(&self.builtins, "TypeError", format!("{name} was not compiled for the VM"), span);
+                };
+                let chunk = Rc::clone(&self.program.functions[index].chunk);
+
+                let caller = self.scope.replace(env.child());
+                self.scope.declare("this", receiver);
+                self.scope.declare("arguments", Literal::Array(Array::new(args.clone())));
+                let mut args = args.into_iter();
+                for param in &chunk.params {
+                    let val = args.next().map_or(Literal::Undefined, |arg| arg);
+                    self.scope.declare(param, val);
+                }
+
+                let result = self.execute(&chunk);
+                self.scope.replace(caller);
+                result
+            },
+            Literal::NativeFunction(f) => ops::call_native(&Rc::clone(&self.builtins), &f, self, receiver, args, span),
+            _ => ops::throw(&self.builtins, "TypeError", format!("{name} is not a function"), span)
+        }
+    }
+
+    /// Reads `target.name`, running the getter with `receiver` as `this` if it's an accessor.
+    fn read_property(&mut self, target: Literal, name: String, receiver: Literal, span: Span) -> EvalResult<Literal> {
+        match ops::get_property(&self.builtins, target, name, span)? {
+            Literal::Accessor { get: Some(getter), .. } => self.call(*getter, receiver, Vec::new(), "getter", span),
+            Literal::Accessor { get: None, .. } => Ok(Literal::Undefined),
+            value => Ok(value)
+        }
+    }
+
+    fn write_property(&mut self, target: Literal, name: String, value: Literal, span: Span) -> EvalResult<()> {
+        if let Some(setter) = ops::set_property(&self.builtins, target.clone(), name, value.clone(), span)? {
+            self.call(setter, target, vec![value], "setter", span)?;
+        }
+
+        Ok(())
+    }
+
+    fn this(&self) -> Literal {
+        self.scope.get("this").unwrap_or(Literal::Undefined)
+    }
+
+    fn execute(&mut self, chunk: &Chunk) -> EvalResult<Literal> {
+        let mut frame = Frame {
+            registers: vec![Literal::Undefined; chunk.registers],
+            handlers: Vec::new(),
+            pc: 0,
+            caught: Span::default(),
+        };
+
+        loop {
+            let instruction = &chunk.code[frame.pc];
+            let span = chunk.spans[frame.pc];
+            frame.pc += 1;
+
+            match self.step(chunk, instruction, span, &mut frame) {
+                Ok(None) => {},
+                Ok(Some(value)) => return Ok(value),
+                Err(ControlFlow::Throw(value, span)) => {
+                    let Some(handler) = frame.handlers.pop() else {
+                        return Err(ControlFlow::Throw(value, span));
+                    };
+
+                    self.scope.replace(handler.env);
+                    frame.registers[handler.exception] = *value;
+                    frame.caught = span;
+                    frame.pc = handler.target;
+                },
+                Err(other) => return Err(other),
+            }
+        }
+    }
+
+    /// Runs one instruction, returning the frame's result once it executes `Return`.
+    fn step(&mut self, chunk: &Chunk, instruction: &Bytecode, span: Span, frame: &mut Frame) -> EvalResult<Option<Literal>> {
+        let registers = &mut frame.registers;
+        match *instruction {
+            Bytecode::LoadConst { dst, constant } => registers[dst] = chunk.constants[constant].clone(),
+            Bytecode::GetVar { dst, name } => {
+                let name = &chunk.names[name];
+                registers[dst] = match self.scope.get(name) {
+                    Some(val) => val,
+                    None => return ops::throw(&self.builtins, "ReferenceError", format!("{name} is not defined"), span)
+                };
+            },
+            Bytecode::SetVar { name, src } => self.scope.set(&chunk.names[name], registers[src].clone()),
+            Bytecode::DeclareVar { name, src } => self.scope.declare(&chunk.names[name], registers[src].clone()),
+            Bytecode::EnterScope => self.scope.enter(),
+            Bytecode::ExitScope => self.scope.exit(),
+            Bytecode::RenewScope => self.scope.renew(),
+            Bytecode::Binary { ref op, dst, left, right } => {
+                registers[dst] = ops::binary_op(&Rc::clone(&self.builtins), self, op, registers[left].clone(), registers[right].clone(), span)?;
+            },
+            Bytecode::Unary { ref op, dst, src } => {
+                registers[dst] = ops::unary_op(&Rc::clone(&self.builtins), self, op, registers[src].clone(), span)?;
+            },
+            Bytecode::ToNumber { dst, src } => {
+                registers[dst] = Literal::Number(ops::coerce_number(&Rc::clone(&self.builtins), self, registers[src].clone(), span)?);
+            },
+            Bytecode::ToString { dst, src } => {
+                registers[dst] = Literal::String(ops::to_string(&Rc::clone(&self.builtins), self, registers[src].clone(), span)?);
+            },
+            Bytecode::NewArray { dst, start, count } => {
+                let elements = registers[start..start + count].to_vec();
+                registers[dst] = Literal::Array(Array::new(elements));
+            },
+            Bytecode::NewObject { dst } => registers[dst] = self.builtins.new_object(Vec::new()),
+            Bytecode::GetIndex { dst, target, index } => {
+                registers[dst] = ops::get_index(&self.builtins, registers[target].clone(), registers[index].clone(), span)?;
+            },
+            Bytecode::SetIndex { target, index, src } => {
+                ops::set_index(&self.builtins, registers[target].clone(), registers[index].clone(), registers[src].clone(), span)?;
+            },
+            Bytecode::GetProperty { dst, target, name } => {
+                let target = registers[target].clone();
+                frame.registers[dst] = self.read_property(target.clone(), chunk.names[name].clone(), target, span)?;
+            },
+            Bytecode::SetProperty { target, name, src } => {
+                let (target, value) = (registers[target].clone(), registers[src].clone());
+                self.write_property(target, chunk.names[name].clone(), value, span)?;
+            },
+            Bytecode::Closure { dst, func } => {
+                let func = &self.program.functions[func];
+                registers[dst] = self.builtins.new_function(func.chunk.params.clone(), Rc::clone(&func.body), self.scope.capture());
+            },
+            Bytecode::Call { dst, callee, this, args, argc, name } => {
+                let callee = registers[callee].clone();
+                let receiver = this.map_or(Literal::Undefined, |this| registers[this].clone());
+                let args = registers[args..args + argc].to_vec();
+                frame.registers[dst] = self.call(callee, receiver, args, &chunk.names[name], span)?;
+            },
+            Bytecode::New { dst, callee, args, argc, name } => {
+                let callee = registers[callee].clone();
+                let args = registers[args..args + argc].to_vec();
+                let name = &chunk.names[name];
+
+                let instance = ops::new_instance(&self.builtins, &callee, name, span)?;
+                let result = self.construct(callee, instance.clone(), args, name, span)?;
+                frame.registers[dst] = ops::constructed(instance, result);
+            },
+            Bytecode::LoadThis { dst } => registers[dst] = self.this(),
+            Bytecode::MakeClass { dst, class, parent } => {
+                let parent = parent.map(|parent| registers[parent].clone());
+                registers[dst] = ops::make_class(&self.builtins, &registers[class], parent, span)?;
+            },
+            Bytecode::DefineMethod { target, name, src, kind } => {
+                ops::define_method(&registers[target], chunk.names[name].clone(), registers[src].clone(), kind);
+            },
+            Bytecode::SuperCall { dst, args } => {
+                let Some(parent) = self.scope.get(ops::PARENT_CLASS) else {
+                    return ops::throw(&self.builtins, "SyntaxError", "'super' keyword unexpected here", span);
+                };
+                let args = match (args, self.scope.get("arguments")) {
+                    (Some((args, argc)), _) => registers[args..args + argc].to_vec(),
+                    (None, Some(Literal::Array(args))) => args.borrow().clone(),
+                    (None, _) => Vec::new(),
+                };
+
+                let receiver = self.this();
+                let result = self.construct(parent.clone(), receiver.clone(), args, "super", span)?;
+                ops::adopt_super_result(&parent, &receiver, result);
+                frame.registers[dst] = Literal::Undefined;
+            },
+            Bytecode::GetSuper { dst, name } => {
+                let base = ops::super_base(&self.builtins, self.scope.get(ops::HOME_OBJECT), span)?;
+                let receiver = self.this();
+                frame.registers[dst] = self.read_property(base, chunk.names[name].clone(), receiver, span)?;
+            },
+            Bytecode::Jump { target } => frame.pc = target,
+            Bytecode::JumpIfFalse { cond, target } => {
+                if !registers[cond].truthy() {
+                    frame.pc = target;
+                }
+            },
+            Bytecode::JumpIfTrue { cond, target } => {
+                if registers[cond].truthy() {
+                    frame.pc = target;
+                }
+            },
+            Bytecode::JumpIfNotNullish { cond, target } => {
+                if !matches!(registers[cond], Literal::Null | Literal::Undefined) {
+                    frame.pc = target;
+                }
+            },
+            Bytecode::Return { src } => return Ok(Some(registers[src].clone())),
+            Bytecode::Throw { src } => return Err(ControlFlow::Throw(registers[src].clone().into(), span)),
+            Bytecode::Rethrow { src } => return Err(ControlFlow::Throw(registers[src].clone().into(), frame.caught)),
+            Bytecode::EnterTry { handler, exception } => {
+                frame.handlers.push(Handler { target: handler, exception, env: self.scope.capture() });
+            },
+            Bytecode::LeaveTry => { frame.handlers.pop(); },
+        }
+
+        Ok(None)
+    }
+
+    pub fn run(&mut self) -> Result<(), UncaughtException> {
+        let main = Rc::clone(&self.program.main);
+        match self.execute(&main) {
+            Err(ControlFlow::Throw(value, span)) => Err(UncaughtException { value: *value, span }),
+            _ => Ok(())
+        }
+    }
+}
+
+impl Context for Vm {
+    fn global(&self, name: &str) -> Option<Literal> {
+        self.scope.get_global(name)
+    }
+
+    fn set_global(&mut self, name: &str, value: Literal) {
+        self.scope.set_global(name, value);
+    }
+
+    fn new_object(&self, properties: Properties) -> Literal {
+        self.builtins.new_object(properties)
+    }
+
+    fn call(&mut self, func: &Literal, this: Literal, args: Vec<Literal>) -> Result<Literal, Literal> {
+        match Vm::call(self, func.clone(), this, args, &func.to_string(), Span::default()) {
+            Ok(value) => Ok(value),
+            Err(ControlFlow::Throw(value, _)) => Err(*value),
+            Err(_) => Ok(Literal::Undefined)
+        }
+    }
+
+    fn to_js_string(&mut self, value: Literal) -> Result<String, Literal> {
+        ops::to_string(&Rc::clone(&self.builtins), self, value, Span::default()).map_err(ops::thrown)
+    }
+
+    fn to_js_number(&mut self, value: Literal) -> Result<f64, Literal> {
+        ops::coerce_number(&Rc::clone(&self.builtins), self, value, Span::default()).map_err(ops::thrown)
+    }
+
+    fn error(&self, name: &str, message: String) -> Literal {
+        self.builtins.error(name, message)
+    }
+}
@@ -0,0 +1,138 @@
+//! Runs every script in js/tests under both the interpreter and the VM, with prelude.js
+//! prepended. A script passes if neither engine throws, both print the same lines, and the
+//! last of them is `<name>: ok`. The examples at the top of js/ run the same way, without the
+//! prelude, and only have to agree.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use tinyjs::lexer::Lexer;
+use tinyjs::optim::Optimizer;
+use tinyjs::parser::AST;
+use tinyjs::runtime::{Context, Emitter, Interpreter, Vm};
+use tinyjs::{Literal, Runtime};
+
+const PRELUDE: &str = include_str!("prelude.js");
+
+type Output = Rc<RefCell<Vec<String>>>;
+
+#[derive(Debug)]
+enum Engine {
+    Interpreter,
+    Vm
+}
+
+/// Puts the prelude ahead of `script`, but after its `#!` line, which has to come first.
+fn with_prelude(script: &str) -> String {
+    if script.starts_with("#!") {
+        let (shebang, rest) = script.split_once('\n').unwrap_or((script, ""));
+        format!("{shebang}\n{PRELUDE}\n{rest}")
+    } else {
+        format!("{PRELUDE}\n{script}")
+    }
+}
+
+/// A `console` object whose `log` appends each line to `output` instead of printing it.
+fn console(cx: &dyn Context, output: &Output) -> Literal {
+    let output = Rc::clone(output);
+    let log = Runtime::function("console.log", move |_, args| {
+        let line = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(" ");
+        output.borrow_mut().push(line);
+        Ok(Literal::Undefined)
+    });
+
+    cx.new_object(vec![("log".into(), log.into())])
+}
+
+/// Runs `source` the way the CLI does, returning the lines it logged, or the rendered error.
+fn run(source: &str, engine: Engine) -> Result<Vec<String>, String> {
+    let ast = AST::new(Lexer::new(source).lex())
+        .map_err(|errors| errors.iter().map(|error| error.render(source)).collect::<Vec<_>>().join("\n\n"))?;
+    let ast = Optimizer::new(ast).optimize();
+
+    let output = Output::default();
+    let result = match engine {
+        Engine::Interpreter => {
+            let mut interpreter = Interpreter::new(ast);
+            let console = console(&interpreter, &output);
+            interpreter.set_global("console", console);
+            interpreter.run()
+        },
+        Engine::Vm => {
+            let program = Emitter::new().emit_program(&ast).map_err(|error| error.render(source))?;
+            let mut vm = Vm::new(program);
+            let console = console(&vm, &output);
+            vm.set_global("console", console);
+            vm.run()
+        }
+    };
+    result.map_err(|exception| exception.render(source))?;
+
+    Ok(output.borrow().clone())
+}
+
+/// Runs `source` under both engines, returning the lines they agreed on, or why they didn't.
+fn run_both(name: &str, source: &str) -> Result<Vec<String>, String> {
+    let [interpreted, compiled] = [Engine::Interpreter, Engine::Vm].map(|engine| {
+        let label = format!("{name} ({engine:?})");
+        run(source, engine).map_err(|error| format!("{label} threw:\n{error}"))
+    });
+    let (interpreted, compiled) = (interpreted?, compiled?);
+    if interpreted != compiled {
+        return Err(format!("{name}: the engines disagree\n  Interpreter: {interpreted:?}\n  Vm: {compiled:?}"));
+    }
+
+    Ok(interpreted)
+}
+
+/// Runs the example `js/<name>.js` under both engines.
+fn example(name: &str) -> Result<Vec<String>, String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("js").join(format!("{name}.js"));
+    run_both(name, &fs::read_to_string(path).unwrap())
+}
+
+#[test]
+fn scripts() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("js/tests");
+    let mut paths: Vec<_> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scripts in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let source = with_prelude(&fs::read_to_string(&path).unwrap());
+
+        match run_both(&name, &source) {
+            Ok(output) if output.last() != Some(&format!("{name}: ok")) => failures.push(format!(
+                "{name}: didn't finish with \"{name}: ok\", printed {output:?}"
+            )),
+            Ok(_) => {},
+            Err(failure) => failures.push(failure)
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn examples() {
+    let failures: Vec<_> = ["basic", "euler_1", "euler_2", "euler_3", "optimize"].into_iter()
+        .filter_map(|name| example(name).err())
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+/// Too slow for an unoptimized build; run it with `cargo test --release -- --ignored`.
+#[test]
+#[ignore]
+fn euler_4() {
+    if let Err(failure) = example("euler_4") {
+        panic!("{failure}");
+    }
+}
@@ -0,0 +1,29 @@
+//! Syntax error recovery: each mistake is reported once, without knock-on errors from
+//! resuming in the middle of the construct it was made in.
+
+use tinyjs::lexer::Lexer;
+use tinyjs::parser::AST;
+
+/// The messages of the syntax errors in `source`, which must fail to parse.
+fn syntax_errors(source: &str) -> Vec<String> {
+    let errors = AST::new(Lexer::new(source).lex()).unwrap_err();
+    errors.iter().map(|error| error.to_string()).collect()
+}
+
+#[test]
+fn a_bad_function_header_skips_the_body() {
+    let errors = syntax_errors("function f(1) { }");
+    assert_eq!(errors.len(), 1, "{errors:?}");
+
+    let errors = syntax_errors("function f(a, 1) { if (a) { return a; } }\nlet = 2;");
+    assert_eq!(errors.len(), 2, "{errors:?}");
+
+    let errors = syntax_errors("let f = function(1) { return 2; };\nf();");
+    assert_eq!(errors.len(), 1, "{errors:?}");
+}
+
+#[test]
+fn a_bad_statement_in_a_body_stays_in_the_body() {
+    let errors = syntax_errors("function f() {\n  let o = {a: 1 2};\n  let = 3;\n}\nf();");
+    assert_eq!(errors.len(), 2, "{errors:?}");
+}
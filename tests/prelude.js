@@ -0,0 +1,17 @@
+// Helpers for the scripts in js/tests, which tests/js.rs runs with this file prepended.
+
+function assert(condition, label) {
+    if (!condition) {
+        throw Error("Assertion failed: " + label);
+    }
+}
+
+// Whether calling `f` throws an error named `name`.
+function throws(f, name) {
+    try {
+        f();
+    } catch (e) {
+        return e.name == name;
+    }
+    return false;
+}
@@ -0,0 +1,281 @@
+//! The embedding API: running scripts through `Runtime`, converting values between Rust and JS,
+//! and exposing native functions and host objects to scripts.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tinyjs::runtime::{error, HostObject};
+use tinyjs::{Error, Literal, Runtime};
+
+#[test]
+fn eval_evaluates_to_the_last_expression() {
+    let mut js = Runtime::new();
+
+    assert_eq!(js.eval("1 + 2").unwrap(), Literal::Number(3.0));
+    assert_eq!(js.eval("let x = 1;").unwrap(), Literal::Undefined);
+    assert_eq!(js.eval("x = 2; 'x is ' + x").unwrap(), Literal::String("x is 2".into()));
+}
+
+#[test]
+fn eval_keeps_globals_between_calls() {
+    let mut js = Runtime::new();
+    js.eval("let count = 1; function bump() { count += 1; return count; }").unwrap();
+
+    assert_eq!(js.eval("bump()").unwrap(), Literal::Number(2.0));
+    assert_eq!(js.eval("bump(); count").unwrap(), Literal::Number(3.0));
+}
+
+#[test]
+fn eval_reports_syntax_errors() {
+    let mut js = Runtime::new();
+
+    assert!(matches!(js.eval("let = ;"), Err(Error::Syntax(errors)) if !errors.is_empty()));
+}
+
+#[test]
+fn eval_reports_uncaught_exceptions() {
+    let mut js = Runtime::new();
+
+    let err = js.eval("throw TypeError('bad input');").unwrap_err();
+    assert!(matches!(err, Error::Exception(_)));
+    assert!(err.to_string().contains("bad input"), "{err}");
+}
+
+#[test]
+fn call_passes_arguments_and_returns_the_result() {
+    let mut js = Runtime::new();
+    js.eval("function add(a, b) { return a + b; }").unwrap();
+
+    assert_eq!(js.call("add", vec![2.into(), 3.into()]).unwrap(), Literal::Number(5.0));
+    assert_eq!(js.call("add", vec!["a".into(), "b".into()]).unwrap(), Literal::String("ab".into()));
+    assert!(f64::try_from(js.call("add", vec![1.into()]).unwrap()).unwrap().is_nan());
+}
+
+#[test]
+fn call_throws_for_missing_functions() {
+    let mut js = Runtime::new();
+    js.set("notAFunction", 1);
+
+    assert!(matches!(js.call("missing", Vec::new()), Err(Error::Exception(_))));
+    assert!(matches!(js.call("notAFunction", Vec::new()), Err(Error::Exception(_))));
+}
+
+#[test]
+fn call_reports_what_the_function_threw() {
+    let mut js = Runtime::new();
+    js.eval("function check(n) { if (n < 0) { throw RangeError('negative: ' + n); } return n; }").unwrap();
+
+    assert_eq!(js.call("check", vec![1.into()]).unwrap(), Literal::Number(1.0));
+    let err = js.call("check", vec![(-1).into()]).unwrap_err();
+    assert!(err.to_string().contains("negative: -1"), "{err}");
+}
+
+#[test]
+fn get_and_set_globals() {
+    let mut js = Runtime::new();
+
+    assert_eq!(js.get("answer"), None);
+    js.set("answer", 42);
+    assert_eq!(js.get("answer"), Some(Literal::Number(42.0)));
+    assert_eq!(js.eval("answer + 1").unwrap(), Literal::Number(43.0));
+
+    js.eval("answer = 'changed';").unwrap();
+    assert_eq!(js.get("answer"), Some(Literal::String("changed".into())));
+    js.set("answer", true);
+    assert_eq!(js.eval("answer").unwrap(), Literal::Boolean(true));
+}
+
+#[test]
+fn rust_values_convert_to_literals() {
+    assert_eq!(Literal::from(1.5), Literal::Number(1.5));
+    assert_eq!(Literal::from(2.5f32), Literal::Number(2.5));
+    assert_eq!(Literal::from(-3i32), Literal::Number(-3.0));
+    assert_eq!(Literal::from(4i64), Literal::Number(4.0));
+    assert_eq!(Literal::from(5u32), Literal::Number(5.0));
+    assert_eq!(Literal::from(6u64), Literal::Number(6.0));
+    assert_eq!(Literal::from(7usize), Literal::Number(7.0));
+    assert_eq!(Literal::from(true), Literal::Boolean(true));
+    assert_eq!(Literal::from("str"), Literal::String("str".into()));
+    assert_eq!(Literal::from(String::from("string")), Literal::String("string".into()));
+    assert_eq!(Literal::from(()), Literal::Undefined);
+    assert_eq!(Literal::from(Some(1)), Literal::Number(1.0));
+    assert_eq!(Literal::from(None::<i32>), Literal::Null);
+}
+
+#[test]
+fn vecs_convert_to_new_arrays() {
+    let mut js = Runtime::new();
+    js.set("list", vec![1, 2, 3]);
+
+    assert_eq!(js.eval("list.length").unwrap(), Literal::Number(3.0));
+    assert_eq!(js.eval("list.join('-')").unwrap(), Literal::String("1-2-3".into()));
+    assert_eq!(js.eval("Array.isArray(list)").unwrap(), Literal::Boolean(true));
+}
+
+#[test]
+fn literals_convert_to_rust_values() {
+    let mut js = Runtime::new();
+
+    assert_eq!(f64::try_from(js.eval("6 * 7").unwrap()).unwrap(), 42.0);
+    assert!(bool::try_from(js.eval("1 < 2").unwrap()).unwrap());
+    assert_eq!(String::try_from(js.eval("'a' + 'b'").unwrap()).unwrap(), "ab");
+    assert_eq!(Vec::<f64>::try_from(js.eval("[1, 2, 3]").unwrap()).unwrap(), vec![1.0, 2.0, 3.0]);
+    assert_eq!(Vec::<Vec<String>>::try_from(js.eval("[['a'], []]").unwrap()).unwrap(), vec![vec!["a".to_string()], vec![]]);
+}
+
+#[test]
+fn mismatched_literals_fail_to_convert() {
+    let mut js = Runtime::new();
+
+    let err = f64::try_from(Literal::String("1".into())).unwrap_err();
+    assert!(matches!(err, Error::Type { expected: "number", found: "string" }), "{err}");
+    assert!(matches!(bool::try_from(Literal::Null), Err(Error::Type { expected: "boolean", .. })));
+    assert!(matches!(String::try_from(Literal::Number(1.0)), Err(Error::Type { expected: "string", .. })));
+    assert!(matches!(Vec::<f64>::try_from(Literal::Undefined), Err(Error::Type { expected: "array", .. })));
+
+    let err = Vec::<f64>::try_from(js.eval("[1, 'two']").unwrap()).unwrap_err();
+    assert!(matches!(err, Error::Type { expected: "number", found: "string" }), "{err}");
+}
+
+#[test]
+fn host_functions_throw_their_errors() {
+    let mut js = Runtime::new();
+    js.register("fail", |_, args| Err(error("RangeError", format!("out of range: {}", args.get(0)))));
+
+    let err = js.eval("fail(7);").unwrap_err();
+    assert!(matches!(err, Error::Exception(_)));
+    assert!(err.to_string().contains("out of range: 7"), "{err}");
+
+    let caught = js.eval("let caught; try { fail(1); } catch (e) { caught = e.name + ': ' + e.message; } caught").unwrap();
+    assert_eq!(caught, Literal::String("RangeError: out of range: 1".into()));
+}
+
+#[test]
+fn host_errors_inherit_from_their_constructors_prototype() {
+    let mut js = Runtime::new();
+    js.register("fail", |_, _| Err(error("RangeError", "from the host")));
+    js.register("typed", |_, args| args.arg::<f64>(0).map(Literal::from));
+
+    let source = "let e; try { fail(); } catch (err) { e = err; } Object.getPrototypeOf(e) === RangeError.prototype";
+    assert_eq!(js.eval(source).unwrap(), Literal::Boolean(true));
+    let source = "try { typed('x'); } catch (err) { e = err; } Object.getPrototypeOf(e) === TypeError.prototype";
+    assert_eq!(js.eval(source).unwrap(), Literal::Boolean(true));
+}
+
+#[test]
+fn host_functions_can_throw_any_value() {
+    let mut js = Runtime::new();
+    js.register("reject", |_, _| Err("just a string".into()));
+
+    assert_eq!(js.eval("let thrown; try { reject(); } catch (e) { thrown = e; } thrown").unwrap(), Literal::String("just a string".into()));
+}
+
+/// Registers `typed(n, s, flag?)`, which echoes its arguments back as a string.
+fn typed(js: &mut Runtime) {
+    js.register("typed", |_, args| {
+        let n: f64 = args.arg(0)?;
+        let s: String = args.arg(1)?;
+        let flag: Option<bool> = args.optional(2)?;
+        Ok(format!("{n} {s} {flag:?}").into())
+    });
+}
+
+/// What `source` threw, as `name: message`.
+fn thrown(js: &mut Runtime, source: &str) -> String {
+    let source = format!("let error; try {{ {source} }} catch (e) {{ error = e.name + ': ' + e.message; }} error");
+    String::try_from(js.eval(&source).unwrap()).unwrap()
+}
+
+#[test]
+fn args_convert_to_the_types_asked_for() {
+    let mut js = Runtime::new();
+    typed(&mut js);
+
+    assert_eq!(js.eval("typed(1, 'a', true)").unwrap(), Literal::String("1 a Some(true)".into()));
+    assert_eq!(js.eval("typed(2, 'b')").unwrap(), Literal::String("2 b None".into()));
+    assert_eq!(js.eval("typed(3, 'c', undefined)").unwrap(), Literal::String("3 c None".into()));
+}
+
+#[test]
+fn missing_args_are_type_errors() {
+    let mut js = Runtime::new();
+    typed(&mut js);
+
+    assert_eq!(thrown(&mut js, "typed();"), "TypeError: argument 1: expected number, found undefined");
+    assert_eq!(thrown(&mut js, "typed(1);"), "TypeError: argument 2: expected string, found undefined");
+}
+
+#[test]
+fn mistyped_args_are_type_errors() {
+    let mut js = Runtime::new();
+    typed(&mut js);
+
+    assert_eq!(thrown(&mut js, "typed('1', 'a');"), "TypeError: argument 1: expected number, found string");
+    assert_eq!(thrown(&mut js, "typed(1, 2);"), "TypeError: argument 2: expected string, found number");
+    assert_eq!(thrown(&mut js, "typed(1, 'a', null);"), "TypeError: argument 3: expected boolean, found null");
+    assert_eq!(thrown(&mut js, "typed(1, 'a', 0);"), "TypeError: argument 3: expected boolean, found number");
+}
+
+/// Numeric settings kept on the Rust side. Names starting with `_` are left to the object itself.
+struct Settings(Rc<RefCell<HashMap<String, f64>>>);
+
+impl HostObject for Settings {
+    fn get(&self, name: &str) -> Option<Literal> {
+        self.0.borrow().get(name).map(|&value| value.into())
+    }
+
+    fn set(&self, name: &str, value: Literal) -> Result<bool, Literal> {
+        if name.starts_with('_') {
+            return Ok(false);
+        }
+        match value {
+            Literal::Number(n) => {
+                self.0.borrow_mut().insert(name.into(), n);
+                Ok(true)
+            },
+            other => Err(error("TypeError", format!("{name} must be a number, not {}", other.type_name())))
+        }
+    }
+}
+
+fn settings(js: &mut Runtime) -> Rc<RefCell<HashMap<String, f64>>> {
+    let values = Rc::new(RefCell::new(HashMap::from([("volume".to_string(), 5.0)])));
+    js.register_object("settings", Settings(Rc::clone(&values)));
+    values
+}
+
+#[test]
+fn host_objects_read_live_host_state() {
+    let mut js = Runtime::new();
+    let values = settings(&mut js);
+
+    assert_eq!(js.eval("settings.volume").unwrap(), Literal::Number(5.0));
+    values.borrow_mut().insert("volume".into(), 8.0);
+    assert_eq!(js.eval("settings.volume").unwrap(), Literal::Number(8.0));
+    assert_eq!(js.eval("settings.missing").unwrap(), Literal::Undefined);
+    assert_eq!(js.eval("settings.hasOwnProperty === Object.prototype.hasOwnProperty").unwrap(), Literal::Boolean(true));
+}
+
+#[test]
+fn host_objects_handle_assignments() {
+    let mut js = Runtime::new();
+    let values = settings(&mut js);
+
+    js.eval("settings.volume = 3; settings.brightness = settings.volume * 2;").unwrap();
+    assert_eq!(values.borrow().get("volume"), Some(&3.0));
+    assert_eq!(values.borrow().get("brightness"), Some(&6.0));
+    assert_eq!(js.eval("settings.brightness").unwrap(), Literal::Number(6.0));
+}
+
+#[test]
+fn host_objects_can_decline_or_reject_assignments() {
+    let mut js = Runtime::new();
+    let values = settings(&mut js);
+
+    js.eval("settings._note = 'kept on the object';").unwrap();
+    assert_eq!(values.borrow().get("_note"), None);
+    assert_eq!(js.eval("settings._note").unwrap(), Literal::String("kept on the object".into()));
+
+    assert_eq!(thrown(&mut js, "settings.volume = 'loud';"), "TypeError: volume must be a number, not string");
+    assert_eq!(values.borrow().get("volume"), Some(&5.0));
+}
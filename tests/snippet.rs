@@ -0,0 +1,39 @@
+//! Error snippets: the offending source line, found with the same line terminators the lexer
+//! counts lines by.
+
+use tinyjs::lexer::{Lexer, Span};
+use tinyjs::parser::AST;
+
+/// The rendered syntax error for `source`, which must fail to parse.
+fn syntax_error(source: &str) -> String {
+    let errors = AST::new(Lexer::new(source).lex()).unwrap_err();
+    errors[0].render(source)
+}
+
+#[test]
+fn snippets_show_the_offending_line() {
+    let source = "let a = 1;\nlet b = 2;\nlet c = 3;";
+    let span = Span::new(15, 16, 2, 5);
+
+    assert_eq!(span.snippet(source), "  |\n2 | let b = 2;\n  |     ^");
+}
+
+#[test]
+fn every_line_terminator_ends_a_line() {
+    for terminator in ["\n", "\r", "\r\n", "\u{2028}", "\u{2029}"] {
+        let source = ["let a = 1;", "let = 2;", "let c = 3;"].join(terminator);
+        let rendered = syntax_error(&source);
+
+        assert!(rendered.contains(" --> 2:5\n"), "{terminator:?}: {rendered}");
+        assert!(rendered.ends_with("2 | let = 2;\n  |     ^"), "{terminator:?}: {rendered}");
+    }
+}
+
+#[test]
+fn terminators_can_be_mixed() {
+    let source = "let a = 1;\r\nlet b = 2;\u{2028}let c = 3;\rlet d = 4 4;\nlet e = 5;";
+    let rendered = syntax_error(source);
+
+    assert!(rendered.contains(" --> 4:11\n"), "{rendered}");
+    assert!(rendered.ends_with("4 | let d = 4 4;\n  |           ^"), "{rendered}");
+}